@@ -1,31 +1,144 @@
 use std::collections::HashMap;
 
+use indexmap::IndexMap;
 use itertools::Itertools;
+use miette::IntoDiagnostic;
+use recipe_stage0::{
+    matchspec::PackageDependency,
+    recipe::{ConditionalList, Item},
+};
 
-use crate::{ProjectModel, Targets, traits::Dependencies};
+/// A compiler cache that can be used to speed up repeated builds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompilerCache {
+    /// [sccache](https://github.com/mozilla/sccache), supports C, C++ and Rust.
+    Sccache,
+    /// [ccache](https://ccache.dev), supports C and C++.
+    Ccache,
+}
+
+impl CompilerCache {
+    /// The name of the executable, which is also the name of the conda
+    /// package that provides it.
+    pub fn executable(&self) -> &'static str {
+        match self {
+            CompilerCache::Sccache => "sccache",
+            CompilerCache::Ccache => "ccache",
+        }
+    }
 
-pub fn sccache_tools() -> Vec<String> {
-    vec!["sccache".to_string()]
+    /// The prefix of the environment variables that configure this cache.
+    pub fn env_prefix(&self) -> &'static str {
+        match self {
+            CompilerCache::Sccache => "SCCACHE",
+            CompilerCache::Ccache => "CCACHE",
+        }
+    }
+
+    /// Returns the keys of `env` that configure this cache.
+    fn env_keys<'a, I: IntoIterator<Item = &'a String>>(&self, env: I) -> Vec<&'a str> {
+        env.into_iter()
+            .filter(|k| k.starts_with(self.env_prefix()))
+            .map(|k| k.as_str())
+            .collect()
+    }
 }
 
-/// Return environment variables that are used by sccache.
-pub fn sccache_envs(env: &HashMap<String, String>) -> Option<Vec<&str>> {
-    let res = env
-        .keys()
-        .filter(|k| k.starts_with("SCCACHE"))
-        .map(|k| k.as_str())
-        .collect_vec();
-    if res.is_empty() { None } else { Some(res) }
+/// A compiler cache that was detected from the environment together with the
+/// environment variables that should be passed to the build as secrets.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedCompilerCache {
+    /// The compiler cache to use.
+    pub cache: CompilerCache,
+
+    /// Cache related environment variables that are only set in the system
+    /// environment. These are passed to the build as secrets so that their
+    /// values never end up in the recipe. Variables that are also set in the
+    /// backend configuration are not included because the configured value
+    /// takes precedence.
+    pub secrets: Vec<String>,
 }
 
-pub fn add_sccache<'a, P: ProjectModel>(
-    dependencies: &mut Dependencies<'a, <P::Targets as Targets>::Spec>,
-    sccache_tools: &'a [String],
-    empty_spec: &'a <<P as ProjectModel>::Targets as Targets>::Spec,
-) {
-    for cache_tool in sccache_tools {
-        if !dependencies.build.contains_key(&cache_tool) {
-            dependencies.build.insert(cache_tool, empty_spec);
-        }
+/// Detects which of the `supported` compiler caches the user configured.
+///
+/// A cache is considered configured if any environment variable starting with
+/// its prefix (e.g. `SCCACHE_` or `CCACHE_`) is set, either in the backend
+/// configuration or in the system environment. If multiple caches are
+/// configured, the first one in `supported` wins.
+pub fn detect_compiler_cache(
+    supported: &[CompilerCache],
+    config_env: &IndexMap<String, String>,
+    system_env: &HashMap<String, String>,
+) -> Option<DetectedCompilerCache> {
+    let cache = supported.iter().copied().find(|cache| {
+        !cache.env_keys(config_env.keys()).is_empty()
+            || !cache.env_keys(system_env.keys()).is_empty()
+    })?;
+
+    let secrets = cache
+        .env_keys(system_env.keys())
+        .into_iter()
+        // we set only those keys that are present in the system environment variables
+        // and not in the config env
+        .filter(|key| !config_env.contains_key(*key))
+        .map(str::to_string)
+        .sorted()
+        .collect();
+
+    Some(DetectedCompilerCache { cache, secrets })
+}
+
+/// Adds the compiler cache executable to the build requirements if it is not
+/// already present.
+pub fn add_compiler_cache_to_requirements(
+    cache: CompilerCache,
+    build_requirements: &mut ConditionalList<PackageDependency>,
+) -> miette::Result<()> {
+    let dependency: Item<PackageDependency> = cache.executable().parse().into_diagnostic()?;
+    if !build_requirements.contains(&dependency) {
+        build_requirements.push(dependency);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_compiler_cache() {
+        let config_env = IndexMap::from([("SCCACHE_BUCKET".to_string(), "config".to_string())]);
+        let system_env = HashMap::from([
+            ("SCCACHE_BUCKET".to_string(), "system".to_string()),
+            ("SCCACHE_REGION".to_string(), "eu".to_string()),
+            ("CCACHE_DIR".to_string(), "/tmp/ccache".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+        ]);
+
+        // sccache is preferred and only system variables become secrets
+        let detected = detect_compiler_cache(
+            &[CompilerCache::Sccache, CompilerCache::Ccache],
+            &config_env,
+            &system_env,
+        )
+        .unwrap();
+        assert_eq!(detected.cache, CompilerCache::Sccache);
+        assert_eq!(detected.secrets, vec!["SCCACHE_REGION".to_string()]);
+
+        // Backends can restrict the supported caches
+        let detected =
+            detect_compiler_cache(&[CompilerCache::Ccache], &config_env, &system_env).unwrap();
+        assert_eq!(detected.cache, CompilerCache::Ccache);
+        assert_eq!(detected.secrets, vec!["CCACHE_DIR".to_string()]);
+
+        // Nothing is detected without any cache variables
+        assert_eq!(
+            detect_compiler_cache(
+                &[CompilerCache::Sccache, CompilerCache::Ccache],
+                &IndexMap::new(),
+                &HashMap::from([("PATH".to_string(), "/usr/bin".to_string())]),
+            ),
+            None
+        );
     }
 }
@@ -0,0 +1,258 @@
+use std::str::FromStr;
+
+use miette::{Context, IntoDiagnostic};
+use recipe_stage0::recipe::{
+    ConditionalList, DynamicLinking, ForceFileType, IgnoreRunExports, IntermediateRecipe,
+    LinkingCheckBehavior, PrefixDetection, RunExports, Value,
+};
+use serde::{Deserialize, Serialize};
+
+/// The run exports of the package, e.g.
+/// `weak = ["${{ pin_subpackage('libfoo', upper_bound='x.x') }}"]`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct RunExportsConfig {
+    #[serde(default)]
+    pub weak: Vec<String>,
+    #[serde(default)]
+    pub strong: Vec<String>,
+    #[serde(default)]
+    pub noarch: Vec<String>,
+    #[serde(default)]
+    pub weak_constraints: Vec<String>,
+    #[serde(default)]
+    pub strong_constraints: Vec<String>,
+}
+
+/// The run exports of dependencies that are not applied to the package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct IgnoreRunExportsConfig {
+    /// Names of run exported packages that are ignored.
+    #[serde(default)]
+    pub by_name: Vec<String>,
+    /// Names of dependencies whose run exports are all ignored.
+    #[serde(default)]
+    pub from_package: Vec<String>,
+}
+
+/// Settings for the relocation and linking checks of shared libraries and
+/// executables.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct DynamicLinkingConfig {
+    #[serde(default)]
+    pub rpaths: Vec<String>,
+    pub binary_relocation: Option<bool>,
+    #[serde(default)]
+    pub missing_dso_allowlist: Vec<String>,
+    #[serde(default)]
+    pub rpath_allowlist: Vec<String>,
+    pub overdepending_behavior: Option<LinkingCheckBehavior>,
+    pub overlinking_behavior: Option<LinkingCheckBehavior>,
+}
+
+/// Settings for the detection and replacement of the build prefix in the
+/// files of the package.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct PrefixDetectionConfig {
+    /// Files that are always treated as text files.
+    #[serde(default)]
+    pub force_text: Vec<String>,
+    /// Files that are always treated as binary files.
+    #[serde(default)]
+    pub force_binary: Vec<String>,
+    pub ignore: Option<bool>,
+    pub ignore_binary_files: Option<bool>,
+}
+
+/// The settings of the `build` and `requirements` sections of the recipe
+/// that can be configured for every backend.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BuildSettings<'a> {
+    pub number: Option<u64>,
+    /// The build string, this may be a template like
+    /// `"${{ hash }}_${{ git_commit }}"`. rattler-build resolves it when the
+    /// outputs are discovered, so the build string that `conda/outputs`
+    /// reports is the one `conda/build` matches against.
+    pub string: Option<&'a str>,
+    /// Constraints on packages that are installed alongside the package, e.g.
+    /// `numpy >=2`. These are added to the run constraints of the recipe.
+    pub run_constraints: &'a [String],
+    pub run_exports: Option<&'a RunExportsConfig>,
+    pub ignore_run_exports: Option<&'a IgnoreRunExportsConfig>,
+    pub files: &'a [String],
+    pub always_include_files: &'a [String],
+    pub dynamic_linking: Option<&'a DynamicLinkingConfig>,
+    pub prefix_detection: Option<&'a PrefixDetectionConfig>,
+}
+
+impl BuildSettings<'_> {
+    /// Writes the configured settings into the recipe. Settings that are not
+    /// configured leave the generated recipe untouched.
+    ///
+    /// The requirements are written to the top-level requirements, which
+    /// are added to every output of a multi-output recipe.
+    pub fn apply(&self, recipe: &mut IntermediateRecipe) -> miette::Result<()> {
+        if let Some(number) = self.number {
+            recipe.build.number = Some(Value::Concrete(number));
+        }
+
+        if let Some(string) = self.string {
+            recipe.build.string = Some(string.parse().into_diagnostic()?);
+        }
+
+        recipe
+            .requirements
+            .run_constraints
+            .extend(to_conditional_list(self.run_constraints)?);
+
+        if let Some(run_exports) = self.run_exports {
+            recipe.requirements.run_exports = Some(RunExports {
+                weak: to_conditional_list(&run_exports.weak)?,
+                strong: to_conditional_list(&run_exports.strong)?,
+                noarch: to_conditional_list(&run_exports.noarch)?,
+                weak_constraints: to_conditional_list(&run_exports.weak_constraints)?,
+                strong_constraints: to_conditional_list(&run_exports.strong_constraints)?,
+            });
+        }
+
+        if let Some(ignore_run_exports) = self.ignore_run_exports {
+            recipe.requirements.ignore_run_exports = Some(IgnoreRunExports {
+                by_name: to_conditional_list(&ignore_run_exports.by_name)?,
+                from_package: to_conditional_list(&ignore_run_exports.from_package)?,
+            });
+        }
+
+        if !self.files.is_empty() {
+            recipe.build.files = to_conditional_list(self.files)?;
+        }
+
+        if !self.always_include_files.is_empty() {
+            recipe.build.always_include_files = to_conditional_list(self.always_include_files)?;
+        }
+
+        if let Some(dynamic_linking) = self.dynamic_linking {
+            recipe.build.dynamic_linking = Some(DynamicLinking {
+                rpaths: dynamic_linking.rpaths.clone(),
+                binary_relocation: dynamic_linking.binary_relocation,
+                missing_dso_allowlist: dynamic_linking.missing_dso_allowlist.clone(),
+                rpath_allowlist: dynamic_linking.rpath_allowlist.clone(),
+                overdepending_behavior: dynamic_linking.overdepending_behavior,
+                overlinking_behavior: dynamic_linking.overlinking_behavior,
+            });
+        }
+
+        if let Some(prefix_detection) = self.prefix_detection {
+            let force_file_type = (!prefix_detection.force_text.is_empty()
+                || !prefix_detection.force_binary.is_empty())
+            .then(|| ForceFileType {
+                text: prefix_detection.force_text.clone(),
+                binary: prefix_detection.force_binary.clone(),
+            });
+            recipe.build.prefix_detection = Some(PrefixDetection {
+                force_file_type,
+                ignore: prefix_detection.ignore,
+                ignore_binary_files: prefix_detection.ignore_binary_files,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the values of the configuration into a conditional list, values
+/// that contain a template are kept as is.
+fn to_conditional_list<T>(values: &[String]) -> miette::Result<ConditionalList<T>>
+where
+    T: ToString + FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    values
+        .iter()
+        .map(|value| {
+            value
+                .parse()
+                .into_diagnostic()
+                .with_context(|| format!("failed to parse '{value}'"))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use recipe_stage0::recipe::Item;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_build_settings() {
+        let run_exports = RunExportsConfig {
+            weak: vec!["${{ pin_subpackage('libfoo', upper_bound='x.x') }}".to_string()],
+            ..Default::default()
+        };
+        let dynamic_linking: DynamicLinkingConfig = toml::from_str(
+            r#"
+            missing-dso-allowlist = ["/usr/lib/libGL.so*"]
+            overlinking-behavior = "error"
+            "#,
+        )
+        .unwrap();
+        let files = vec!["lib/libfoo*".to_string()];
+        let run_constraints = vec!["numpy >=2".to_string()];
+
+        let mut recipe = IntermediateRecipe::default();
+        BuildSettings {
+            number: Some(3),
+            string: Some("${{ hash }}_nightly"),
+            run_constraints: &run_constraints,
+            run_exports: Some(&run_exports),
+            files: &files,
+            dynamic_linking: Some(&dynamic_linking),
+            ..Default::default()
+        }
+        .apply(&mut recipe)
+        .unwrap();
+
+        assert_eq!(recipe.build.number, Some(Value::Concrete(3)));
+        assert_eq!(
+            recipe.build.string,
+            Some(Value::Template("${{ hash }}_nightly".to_string()))
+        );
+        assert_eq!(
+            recipe.requirements.run_constraints,
+            vec!["numpy >=2".parse().unwrap()]
+        );
+        let weak = &recipe.requirements.run_exports.as_ref().unwrap().weak;
+        assert!(matches!(&weak[0], Item::Value(Value::Template(_))));
+        assert_eq!(recipe.build.files.len(), 1);
+        assert!(recipe.build.always_include_files.is_empty());
+        assert_eq!(
+            recipe
+                .build
+                .dynamic_linking
+                .as_ref()
+                .unwrap()
+                .overlinking_behavior,
+            Some(LinkingCheckBehavior::Error)
+        );
+        assert!(recipe.build.prefix_detection.is_none());
+        assert!(recipe.requirements.ignore_run_exports.is_none());
+    }
+
+    #[test]
+    fn test_invalid_run_export_is_an_error() {
+        let run_exports = RunExportsConfig {
+            strong: vec!["foo[version=".to_string()],
+            ..Default::default()
+        };
+        let mut recipe = IntermediateRecipe::default();
+        let result = BuildSettings {
+            run_exports: Some(&run_exports),
+            ..Default::default()
+        }
+        .apply(&mut recipe);
+        assert!(result.is_err());
+    }
+}
@@ -1,12 +1,12 @@
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, str::FromStr};
 
-use miette::IntoDiagnostic;
+use miette::{Context, IntoDiagnostic};
 use pixi_build_types::PlatformAndVirtualPackages;
 use rattler_build::{
     NormalizedKey, metadata::PlatformWithVirtualPackages, recipe::variable::Variable,
     types::Directories,
 };
-use rattler_conda_types::ChannelUrl;
+use rattler_conda_types::{ChannelUrl, GenericVirtualPackage, PackageName, Platform, Version};
 use rattler_virtual_packages::VirtualPackageOverrides;
 use url::Url;
 
@@ -55,6 +55,130 @@ pub fn build_configuration(
     Ok(params)
 }
 
+/// Returns the build and host platform of a `conda/build` request together
+/// with their virtual packages.
+///
+/// `conda/build` v1 requests don't contain virtual packages, so they are
+/// detected on the current machine, which respects the `CONDA_OVERRIDE_*`
+/// environment variables. This matches the virtual packages that the frontend
+/// solved the build and host environments with.
+///
+/// The virtual packages of a platform other than the current one, e.g. the
+/// host platform when cross-compiling, can't be detected. They are derived
+/// from the platform instead, see [`assumed_virtual_packages`].
+pub fn platforms_with_virtual_packages(
+    build_platform: Platform,
+    host_platform: Platform,
+) -> miette::Result<(PlatformWithVirtualPackages, PlatformWithVirtualPackages)> {
+    let current_platform =
+        PlatformWithVirtualPackages::detect(&VirtualPackageOverrides::from_env())
+            .into_diagnostic()?;
+    platforms_with_virtual_packages_of(build_platform, host_platform, &current_platform, |key| {
+        std::env::var(key).ok()
+    })
+}
+
+/// Returns the build and host platform with their virtual packages, see
+/// [`platforms_with_virtual_packages`], with the virtual packages of the
+/// current platform and the environment variables that override them given.
+fn platforms_with_virtual_packages_of(
+    build_platform: Platform,
+    host_platform: Platform,
+    current_platform: &PlatformWithVirtualPackages,
+    env: impl Fn(&str) -> Option<String>,
+) -> miette::Result<(PlatformWithVirtualPackages, PlatformWithVirtualPackages)> {
+    let with_virtual_packages = |platform: Platform| -> miette::Result<_> {
+        let virtual_packages = if current_platform.platform == platform {
+            current_platform.virtual_packages.clone()
+        } else {
+            assumed_virtual_packages(platform, &env)?
+        };
+        Ok(PlatformWithVirtualPackages {
+            platform,
+            virtual_packages,
+        })
+    };
+
+    Ok((
+        with_virtual_packages(build_platform)?,
+        with_virtual_packages(host_platform)?,
+    ))
+}
+
+/// Returns the virtual packages that a platform other than the current one
+/// is assumed to have. The versions are read from the `CONDA_OVERRIDE_*`
+/// environment variables, or are the minimum versions that pixi assumes by
+/// default: `__glibc 2.28` and `__linux 4.18` on Linux and `__osx 13.0` on
+/// macOS. `__cuda` is only added if `CONDA_OVERRIDE_CUDA` is set.
+fn assumed_virtual_packages(
+    platform: Platform,
+    env: &impl Fn(&str) -> Option<String>,
+) -> miette::Result<Vec<GenericVirtualPackage>> {
+    let virtual_package = |name: &str,
+                           override_key: Option<&str>,
+                           default: Option<&str>|
+     -> miette::Result<Option<GenericVirtualPackage>> {
+        let Some(version) = override_key
+            .and_then(env)
+            .filter(|version| !version.is_empty())
+            .or(default.map(String::from))
+        else {
+            return Ok(None);
+        };
+        let version = Version::from_str(&version)
+            .into_diagnostic()
+            .with_context(|| format!("invalid version '{version}' of {name}"))?;
+        Ok(Some(GenericVirtualPackage {
+            name: PackageName::new_unchecked(name),
+            version,
+            build_string: String::from("0"),
+        }))
+    };
+
+    let mut virtual_packages = Vec::new();
+    if platform.is_unix() {
+        virtual_packages.extend(virtual_package("__unix", None, Some("0"))?);
+    }
+    if platform.is_linux() {
+        virtual_packages.extend(virtual_package(
+            "__linux",
+            Some("CONDA_OVERRIDE_LINUX"),
+            Some("4.18"),
+        )?);
+        virtual_packages.extend(virtual_package(
+            "__glibc",
+            Some("CONDA_OVERRIDE_GLIBC"),
+            Some("2.28"),
+        )?);
+    }
+    if platform.is_osx() {
+        virtual_packages.extend(virtual_package(
+            "__osx",
+            Some("CONDA_OVERRIDE_OSX"),
+            Some("13.0"),
+        )?);
+    }
+    if platform.is_windows() {
+        virtual_packages.extend(virtual_package("__win", None, Some("0"))?);
+    }
+    if platform.is_linux() || platform.is_windows() {
+        virtual_packages.extend(virtual_package(
+            "__cuda",
+            Some("CONDA_OVERRIDE_CUDA"),
+            None,
+        )?);
+    }
+    tracing::debug!(
+        "the virtual packages of {platform} can't be detected, assuming {}",
+        virtual_packages
+            .iter()
+            .map(|package| format!("{} {}", package.name.as_normalized(), package.version))
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    Ok(virtual_packages)
+}
+
 /// The parameters used to construct a BuildConfiguration
 #[derive(Debug)]
 pub struct BuildConfigurationParams {
@@ -64,3 +188,69 @@ pub struct BuildConfigurationParams {
     pub variant: BTreeMap<NormalizedKey, Variable>,
     pub directories: Directories,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn virtual_packages(platform: &PlatformWithVirtualPackages) -> Vec<String> {
+        platform
+            .virtual_packages
+            .iter()
+            .map(|package| format!("{}={}", package.name.as_normalized(), package.version))
+            .collect()
+    }
+
+    #[test]
+    fn test_platforms_with_virtual_packages() {
+        let current_platform = PlatformWithVirtualPackages {
+            platform: Platform::Osx64,
+            virtual_packages: vec![GenericVirtualPackage {
+                name: PackageName::new_unchecked("__osx"),
+                version: Version::from_str("14.5").unwrap(),
+                build_string: String::from("0"),
+            }],
+        };
+
+        // The virtual packages of the current platform are used as they are,
+        // the ones of other platforms are assumed
+        let (build_platform, host_platform) = platforms_with_virtual_packages_of(
+            Platform::Osx64,
+            Platform::Linux64,
+            &current_platform,
+            |_| None,
+        )
+        .unwrap();
+        assert_eq!(virtual_packages(&build_platform), vec!["__osx=14.5"]);
+        assert_eq!(
+            virtual_packages(&host_platform),
+            vec!["__unix=0", "__linux=4.18", "__glibc=2.28"]
+        );
+
+        // Overrides apply to the assumed virtual packages
+        let (_, host_platform) = platforms_with_virtual_packages_of(
+            Platform::Osx64,
+            Platform::LinuxAarch64,
+            &current_platform,
+            |key| match key {
+                "CONDA_OVERRIDE_GLIBC" => Some(String::from("2.17")),
+                "CONDA_OVERRIDE_CUDA" => Some(String::from("12.4")),
+                _ => None,
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            virtual_packages(&host_platform),
+            vec!["__unix=0", "__linux=4.18", "__glibc=2.17", "__cuda=12.4"]
+        );
+
+        let (_, host_platform) = platforms_with_virtual_packages_of(
+            Platform::Osx64,
+            Platform::Win64,
+            &current_platform,
+            |_| None,
+        )
+        .unwrap();
+        assert_eq!(virtual_packages(&host_platform), vec!["__win=0"]);
+    }
+}
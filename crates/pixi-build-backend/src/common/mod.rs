@@ -1,8 +1,24 @@
 //! Common utilities that are shared between the different build backends.
+mod build_settings;
 mod configuration;
+mod options;
+mod packaging;
 mod requirements;
+mod sandbox;
 mod variants;
 
-pub use configuration::{BuildConfigurationParams, build_configuration};
+pub use build_settings::{
+    BuildSettings, DynamicLinkingConfig, IgnoreRunExportsConfig, PrefixDetectionConfig,
+    RunExportsConfig,
+};
+pub use configuration::{
+    BuildConfigurationParams, build_configuration, platforms_with_virtual_packages,
+};
+pub use options::CommonOptions;
+pub use packaging::{
+    ARCHIVE_TYPE_ENV, ArchiveTypeConfig, COMPRESSION_LEVEL_ENV, CompressionLevelConfig,
+    NamedCompressionLevel, PackagingConfig,
+};
 pub use requirements::{PackageRequirements, SourceRequirements, requirements};
+pub use sandbox::SandboxConfig;
 pub use variants::compute_variants;
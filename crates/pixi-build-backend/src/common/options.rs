@@ -0,0 +1,320 @@
+//! Configuration options that are shared by all backends that generate a
+//! recipe.
+
+use serde::{Deserialize, Serialize};
+
+use super::{BuildSettings, PackagingConfig, SandboxConfig};
+
+/// Lists the fields of [`CommonOptions`] once, for the struct itself and for
+/// [`backend_config!`](crate::backend_config). Invokes the given macro of this
+/// crate with its input followed by the fields, each with its attributes.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __with_common_options {
+    ($callback:ident! { $($input:tt)* }) => {
+        $crate::$callback! {
+            { $($input)* }
+            /// Partial `recipe.yaml` that is deep-merged into the generated recipe.
+            recipe_overlay: Option<::std::path::PathBuf>,
+            /// The build number of the package, defaults to `0`
+            build_number: Option<u64>,
+            /// The build string of the package, may contain templates like
+            /// `${{ hash }}`. Derived by rattler-build if not set.
+            build_string: Option<String>,
+            /// Constraints on packages that are installed alongside the package,
+            /// e.g. `numpy >=2`.
+            #[serde(default)]
+            run_constraints: Vec<String>,
+            /// Run exports of the package, e.g. a `pin_subpackage` of a library.
+            run_exports: Option<$crate::common::RunExportsConfig>,
+            /// Run exports of dependencies that are ignored.
+            ignore_run_exports: Option<$crate::common::IgnoreRunExportsConfig>,
+            /// Glob patterns of the files that are included in the package.
+            #[serde(default)]
+            files: Vec<String>,
+            /// Glob patterns of the files that are always included in the package.
+            #[serde(default)]
+            always_include_files: Vec<String>,
+            /// Relocation and linking check settings of shared libraries.
+            dynamic_linking: Option<$crate::common::DynamicLinkingConfig>,
+            /// Prefix detection and replacement settings.
+            prefix_detection: Option<$crate::common::PrefixDetectionConfig>,
+            /// Builds with a fixed timestamp so that the package is reproducible,
+            /// enabled by `SOURCE_DATE_EPOCH` if not set.
+            reproducible: Option<bool>,
+            /// The format of the package archive, defaults to `conda`
+            archive_type: Option<$crate::common::ArchiveTypeConfig>,
+            /// The compression level of the package archive
+            compression_level: Option<$crate::common::CompressionLevelConfig>,
+            /// Runs the build script in a sandbox, only supported on Linux.
+            sandbox: Option<$crate::common::SandboxConfig>,
+            /// Runs the tests of the recipe on the package after it was built.
+            run_tests: Option<bool>,
+        }
+    };
+}
+
+/// Defines [`CommonOptions`] from the fields of [`__with_common_options!`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __common_options {
+    (
+        { $($item:tt)* }
+        $(
+            $(#[$meta:meta])*
+            $field:ident: $ty:ty
+        ),* $(,)?
+    ) => {
+        $($item)* {
+            $(
+                $(#[$meta])*
+                pub $field: $ty,
+            )*
+        }
+
+        impl CommonOptions {
+            /// Merges these options with the options of a target-specific
+            /// configuration. Lists are replaced by the target value if it is
+            /// not empty, all other options are replaced by the target value if
+            /// it is set.
+            pub fn merge(&self, target: &Self) -> Self {
+                Self {
+                    $($field: MergeOption::merge(&self.$field, &target.$field),)*
+                }
+            }
+        }
+    };
+}
+
+__with_common_options! {
+    __common_options! {
+        /// The options that every backend which generates a recipe accepts in
+        /// addition to its own options.
+        ///
+        /// Backends embed these options with [`backend_config!`](crate::backend_config),
+        /// which accepts them at the top level of the backend configuration.
+        #[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+        #[serde(rename_all = "kebab-case")]
+        pub struct CommonOptions
+    }
+}
+
+/// How the value of a common option is merged with the value of a
+/// target-specific configuration.
+trait MergeOption {
+    fn merge(&self, target: &Self) -> Self;
+}
+
+impl<T: Clone> MergeOption for Option<T> {
+    fn merge(&self, target: &Self) -> Self {
+        target.clone().or_else(|| self.clone())
+    }
+}
+
+impl<T: Clone> MergeOption for Vec<T> {
+    fn merge(&self, target: &Self) -> Self {
+        if target.is_empty() {
+            self.clone()
+        } else {
+            target.clone()
+        }
+    }
+}
+
+impl CommonOptions {
+    /// The run exports, file filters and linking settings that are written
+    /// into the generated recipe.
+    pub fn build_settings(&self) -> BuildSettings<'_> {
+        BuildSettings {
+            number: self.build_number,
+            string: self.build_string.as_deref(),
+            run_constraints: &self.run_constraints,
+            run_exports: self.run_exports.as_ref(),
+            ignore_run_exports: self.ignore_run_exports.as_ref(),
+            files: &self.files,
+            always_include_files: &self.always_include_files,
+            dynamic_linking: self.dynamic_linking.as_ref(),
+            prefix_detection: self.prefix_detection.as_ref(),
+        }
+    }
+
+    /// The format and compression of the package archive.
+    pub fn packaging(&self) -> PackagingConfig {
+        PackagingConfig {
+            archive_type: self.archive_type,
+            compression_level: self.compression_level,
+        }
+    }
+
+    /// The sandbox the build script runs in, if any.
+    pub fn sandbox(&self) -> Option<&SandboxConfig> {
+        self.sandbox.as_ref()
+    }
+}
+
+/// Defines the configuration struct of a backend with a `common` field that
+/// holds the [`CommonOptions`].
+///
+/// The common options are deserialized from the top level of the
+/// configuration, next to the options of the backend, so that unknown fields
+/// can still be rejected with `deny_unknown_fields`, which doesn't work
+/// together with `#[serde(flatten)]`. `Serialize` is derived for the struct,
+/// with the common options flattened. `Deserialize` is implemented through a
+/// private struct that has the fields of the backend and the common options
+/// side by side, with the attributes of the struct and of its fields, so these
+/// attributes must also be valid on a struct that derives `Deserialize`.
+///
+/// ```ignore
+/// pixi_build_backend::backend_config! {
+///     /// The configuration of the backend.
+///     #[derive(Debug, Clone, Default)]
+///     #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+///     pub struct MyBackendConfig {
+///         /// Extra args to pass to the build tool
+///         #[serde(default)]
+///         pub extra_args: Vec<String>,
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! backend_config {
+    ($($input:tt)*) => {
+        $crate::__with_common_options! {
+            __backend_config! { $($input)* }
+        }
+    };
+}
+
+/// Implements [`backend_config!`] with the fields of
+/// [`__with_common_options!`].
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __backend_config {
+    (
+        {
+            $(#[$meta:meta])*
+            $vis:vis struct $name:ident {
+                $(
+                    $(#[$field_meta:meta])*
+                    $field_vis:vis $field:ident: $ty:ty
+                ),* $(,)?
+            }
+        }
+        $(
+            $(#[$common_meta:meta])*
+            $common_field:ident: $common_ty:ty
+        ),* $(,)?
+    ) => {
+        #[derive(::serde::Serialize)]
+        $(#[$meta])*
+        $vis struct $name {
+            $(
+                $(#[$field_meta])*
+                $field_vis $field: $ty,
+            )*
+            /// The options that are shared by all backends.
+            #[serde(flatten)]
+            pub common: $crate::common::CommonOptions,
+        }
+
+        const _: () = {
+            /// The options of the backend and the common options side by side.
+            #[derive(::serde::Deserialize)]
+            $(#[$meta])*
+            struct Fields {
+                $(
+                    $(#[$field_meta])*
+                    $field: $ty,
+                )*
+                $(
+                    $(#[$common_meta])*
+                    $common_field: $common_ty,
+                )*
+            }
+
+            impl<'de> ::serde::Deserialize<'de> for $name {
+                fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+                where
+                    D: ::serde::Deserializer<'de>,
+                {
+                    let fields = Fields::deserialize(deserializer)?;
+                    Ok($name {
+                        $($field: fields.$field,)*
+                        common: $crate::common::CommonOptions {
+                            $($common_field: fields.$common_field,)*
+                        },
+                    })
+                }
+            }
+        };
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::common::RunExportsConfig;
+
+    crate::backend_config! {
+        #[derive(Debug, Clone, Default)]
+        /// A configuration whose attributes are not in the usual order.
+        #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+        struct TestBackendConfig {
+            #[serde(default)]
+            /// Extra args to pass to the build tool
+            extra_args: Vec<String>,
+        }
+    }
+
+    #[test]
+    fn test_backend_config() {
+        let config: TestBackendConfig =
+            serde_json::from_value(json!({ "extra-args": ["-v"], "build-number": 3 })).unwrap();
+        assert_eq!(config.extra_args, vec!["-v".to_string()]);
+        assert_eq!(config.common.build_number, Some(3));
+
+        // Unknown options are rejected, also next to the common options
+        assert!(serde_json::from_value::<TestBackendConfig>(json!({ "build-nmber": 3 })).is_err());
+
+        // The common options are serialized next to the options of the backend
+        let value = serde_json::to_value(&config).unwrap();
+        assert_eq!(value["extra-args"], json!(["-v"]));
+        assert_eq!(value["build-number"], json!(3));
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = CommonOptions {
+            recipe_overlay: Some(PathBuf::from("recipe.patch.yaml")),
+            run_exports: Some(RunExportsConfig {
+                weak: vec!["${{ pin_subpackage('foo', upper_bound='x.x') }}".to_string()],
+                ..Default::default()
+            }),
+            files: vec!["bin/*".to_string()],
+            always_include_files: vec!["share/licenses/*".to_string()],
+            ..Default::default()
+        };
+        let target = CommonOptions {
+            recipe_overlay: Some(PathBuf::from("recipe.linux.patch.yaml")),
+            files: vec!["lib/*".to_string()],
+            ..Default::default()
+        };
+
+        let merged = base.merge(&target);
+
+        // Options that are not set for the target keep the base value
+        assert_eq!(merged.run_exports, base.run_exports);
+        assert_eq!(merged.always_include_files, base.always_include_files);
+        // Options that are set for the target override the base value
+        assert_eq!(merged.recipe_overlay, target.recipe_overlay);
+        // Lists are replaced, not extended
+        assert_eq!(merged.files, vec!["lib/*".to_string()]);
+
+        // An empty target configuration changes nothing
+        assert_eq!(base.merge(&CommonOptions::default()), base);
+    }
+}
@@ -0,0 +1,194 @@
+use std::{fmt::Display, str::FromStr};
+
+use miette::Context;
+use rattler_build::types::PackagingSettings;
+use rattler_conda_types::{compression_level::CompressionLevel, package::ArchiveType};
+use serde::{Deserialize, Serialize};
+
+/// Overrides the configured archive type, e.g. `PIXI_BUILD_ARCHIVE_TYPE=tar.bz2`.
+pub const ARCHIVE_TYPE_ENV: &str = "PIXI_BUILD_ARCHIVE_TYPE";
+
+/// Overrides the configured compression level, e.g.
+/// `PIXI_BUILD_COMPRESSION_LEVEL=lowest` for fast local builds.
+pub const COMPRESSION_LEVEL_ENV: &str = "PIXI_BUILD_COMPRESSION_LEVEL";
+
+/// The format of the package archive.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ArchiveTypeConfig {
+    #[default]
+    #[serde(rename = "conda")]
+    Conda,
+    #[serde(rename = "tar.bz2")]
+    TarBz2,
+}
+
+impl FromStr for ArchiveTypeConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "conda" => Ok(Self::Conda),
+            "tar.bz2" => Ok(Self::TarBz2),
+            _ => Err(format!(
+                "unknown archive type '{s}', expected 'conda' or 'tar.bz2'"
+            )),
+        }
+    }
+}
+
+impl From<ArchiveTypeConfig> for ArchiveType {
+    fn from(value: ArchiveTypeConfig) -> Self {
+        match value {
+            ArchiveTypeConfig::Conda => ArchiveType::Conda,
+            ArchiveTypeConfig::TarBz2 => ArchiveType::TarBz2,
+        }
+    }
+}
+
+/// The compression level of the package archive, either a number or one of
+/// `lowest`, `default` and `highest`. The valid numbers depend on the archive
+/// type: `-7` to `22` for `.conda` and `1` to `9` for `.tar.bz2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CompressionLevelConfig {
+    Numeric(i32),
+    Named(NamedCompressionLevel),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NamedCompressionLevel {
+    Lowest,
+    Default,
+    Highest,
+}
+
+impl FromStr for CompressionLevelConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lowest" => Ok(Self::Named(NamedCompressionLevel::Lowest)),
+            "default" => Ok(Self::Named(NamedCompressionLevel::Default)),
+            "highest" => Ok(Self::Named(NamedCompressionLevel::Highest)),
+            _ => s.parse().map(Self::Numeric).map_err(|_| {
+                format!(
+                    "unknown compression level '{s}', expected a number or one of 'lowest', 'default' and 'highest'"
+                )
+            }),
+        }
+    }
+}
+
+impl From<CompressionLevelConfig> for CompressionLevel {
+    fn from(value: CompressionLevelConfig) -> Self {
+        match value {
+            CompressionLevelConfig::Numeric(level) => CompressionLevel::Numeric(level),
+            CompressionLevelConfig::Named(NamedCompressionLevel::Lowest) => {
+                CompressionLevel::Lowest
+            }
+            CompressionLevelConfig::Named(NamedCompressionLevel::Default) => {
+                CompressionLevel::Default
+            }
+            CompressionLevelConfig::Named(NamedCompressionLevel::Highest) => {
+                CompressionLevel::Highest
+            }
+        }
+    }
+}
+
+/// The format and compression of the package archive that `conda/build`
+/// produces.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PackagingConfig {
+    pub archive_type: Option<ArchiveTypeConfig>,
+    pub compression_level: Option<CompressionLevelConfig>,
+}
+
+impl PackagingConfig {
+    /// Returns the packaging settings of the build. The environment variables
+    /// [`ARCHIVE_TYPE_ENV`] and [`COMPRESSION_LEVEL_ENV`] take precedence
+    /// over the configuration.
+    pub fn packaging_settings(&self) -> miette::Result<PackagingSettings> {
+        let archive_type = env_override(ARCHIVE_TYPE_ENV)?
+            .or(self.archive_type)
+            .unwrap_or_default();
+        Ok(PackagingSettings::from_args(
+            archive_type.into(),
+            self.compression_level()?,
+        ))
+    }
+
+    /// Returns the compression level of the package archive, the environment
+    /// variable [`COMPRESSION_LEVEL_ENV`] takes precedence over the
+    /// configuration.
+    pub fn compression_level(&self) -> miette::Result<CompressionLevel> {
+        Ok(env_override(COMPRESSION_LEVEL_ENV)?
+            .or(self.compression_level)
+            .map_or(CompressionLevel::Default, CompressionLevel::from))
+    }
+}
+
+/// Parses the value of the environment variable `name`, if it is set.
+fn env_override<T>(name: &str) -> miette::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let Ok(value) = std::env::var(name) else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|err| miette::miette!("{err}"))
+        .with_context(|| format!("invalid value of {name}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    struct Config {
+        archive_type: Option<ArchiveTypeConfig>,
+        compression_level: Option<CompressionLevelConfig>,
+    }
+
+    #[test]
+    fn test_deserialize_packaging_config() {
+        let config: Config = toml::from_str(
+            r#"
+            archive-type = "tar.bz2"
+            compression-level = 1
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.archive_type, Some(ArchiveTypeConfig::TarBz2));
+        assert_eq!(
+            config.compression_level,
+            Some(CompressionLevelConfig::Numeric(1))
+        );
+
+        let config: Config = toml::from_str(r#"compression-level = "lowest""#).unwrap();
+        assert_eq!(
+            config.compression_level,
+            Some(CompressionLevelConfig::Named(NamedCompressionLevel::Lowest))
+        );
+    }
+
+    #[test]
+    fn test_parse_env_overrides() {
+        assert_eq!("conda".parse(), Ok(ArchiveTypeConfig::Conda));
+        assert!("zip".parse::<ArchiveTypeConfig>().is_err());
+        assert_eq!("-5".parse(), Ok(CompressionLevelConfig::Numeric(-5)));
+        assert_eq!(
+            "highest".parse(),
+            Ok(CompressionLevelConfig::Named(
+                NamedCompressionLevel::Highest
+            ))
+        );
+        assert!("fast".parse::<CompressionLevelConfig>().is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::path::{Path, PathBuf};
+
+use rattler_build::script::{SandboxArguments, SandboxConfiguration};
+use rattler_conda_types::Platform;
+use serde::{Deserialize, Serialize};
+
+/// Runs the build script in a sandbox that denies network access and
+/// restricts the file system to the given paths, e.g.
+/// `sandbox = { allow-network = false, read = ["/opt/sdk"] }`.
+///
+/// Relative paths are resolved against the source directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case", deny_unknown_fields)]
+pub struct SandboxConfig {
+    /// Allows the build to access the network.
+    #[serde(default)]
+    pub allow_network: bool,
+    /// Paths the build may read from.
+    #[serde(default)]
+    pub read: Vec<PathBuf>,
+    /// Paths the build may read from and execute files in.
+    #[serde(default)]
+    pub read_execute: Vec<PathBuf>,
+    /// Paths the build may read from and write to.
+    #[serde(default)]
+    pub read_write: Vec<PathBuf>,
+}
+
+impl SandboxConfig {
+    /// Returns the sandbox configuration of rattler-build for a build of the
+    /// sources in `source_dir`.
+    ///
+    /// The sandbox is only supported on Linux. On other platforms a warning
+    /// is printed and the build runs without a sandbox.
+    pub fn sandbox_configuration(
+        &self,
+        build_platform: Platform,
+        source_dir: &Path,
+        work_dir: &Path,
+        build_prefix: &Path,
+        host_prefix: &Path,
+    ) -> Option<SandboxConfiguration> {
+        if !build_platform.is_linux() {
+            tracing::warn!(
+                "the build sandbox is only supported on Linux, building without a sandbox on {build_platform}"
+            );
+            return None;
+        }
+
+        self.sandbox_arguments(source_dir, work_dir, build_prefix, host_prefix)
+            .into()
+    }
+
+    /// Returns the paths the build may access. Apart from the configured
+    /// paths the build may read the sources, execute the tools in the build
+    /// prefix and write to the work directory and the host prefix.
+    fn sandbox_arguments(
+        &self,
+        source_dir: &Path,
+        work_dir: &Path,
+        build_prefix: &Path,
+        host_prefix: &Path,
+    ) -> SandboxArguments {
+        let resolve = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| source_dir.join(path))
+                .collect::<Vec<_>>()
+        };
+
+        SandboxArguments {
+            sandbox: true,
+            allow_network: self.allow_network,
+            allow_read: std::iter::once(source_dir.to_path_buf())
+                .chain(resolve(&self.read))
+                .collect(),
+            allow_read_execute: std::iter::once(build_prefix.to_path_buf())
+                .chain(resolve(&self.read_execute))
+                .collect(),
+            allow_read_write: [work_dir.to_path_buf(), host_prefix.to_path_buf()]
+                .into_iter()
+                .chain(resolve(&self.read_write))
+                .collect(),
+            overwrite_default_sandbox_config: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize_sandbox_config() {
+        let config: SandboxConfig = toml::from_str(
+            r#"
+            read = ["/opt/sdk"]
+            read-write = ["cache"]
+            "#,
+        )
+        .unwrap();
+        assert!(!config.allow_network);
+        assert_eq!(config.read, vec![PathBuf::from("/opt/sdk")]);
+        assert_eq!(config.read_write, vec![PathBuf::from("cache")]);
+
+        assert!(toml::from_str::<SandboxConfig>("write = [\"/tmp\"]").is_err());
+    }
+    #[test]
+    fn test_sandbox_arguments() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_dir = source_dir.path();
+        let work_dir = source_dir.join("build").join("work");
+        let build_prefix = source_dir.join("build").join("build_env");
+        let host_prefix = source_dir.join("build").join("host_env");
+
+        let config = SandboxConfig {
+            read: vec![PathBuf::from("/opt/sdk"), PathBuf::from("data")],
+            read_execute: vec![PathBuf::from("tools")],
+            read_write: vec![PathBuf::from("../cache")],
+            ..Default::default()
+        };
+        let arguments =
+            config.sandbox_arguments(source_dir, &work_dir, &build_prefix, &host_prefix);
+
+        assert!(arguments.sandbox);
+        assert!(!arguments.allow_network);
+        assert_eq!(
+            arguments.allow_read,
+            vec![
+                source_dir.to_path_buf(),
+                PathBuf::from("/opt/sdk"),
+                source_dir.join("data"),
+            ]
+        );
+        assert_eq!(
+            arguments.allow_read_execute,
+            vec![build_prefix.clone(), source_dir.join("tools")]
+        );
+        assert_eq!(
+            arguments.allow_read_write,
+            vec![
+                work_dir.clone(),
+                host_prefix.clone(),
+                source_dir.join("../cache")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sandbox_on_unsupported_platform() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_dir = source_dir.path();
+        let work_dir = source_dir.join("work");
+        let build_prefix = source_dir.join("build_env");
+        let host_prefix = source_dir.join("host_env");
+        let sandbox_configuration = |platform| {
+            SandboxConfig::default().sandbox_configuration(
+                platform,
+                source_dir,
+                &work_dir,
+                &build_prefix,
+                &host_prefix,
+            )
+        };
+
+        // Other platforms build without a sandbox
+        assert!(sandbox_configuration(Platform::Linux64).is_some());
+        assert!(sandbox_configuration(Platform::OsxArm64).is_none());
+        assert!(sandbox_configuration(Platform::Win64).is_none());
+    }
+}
@@ -13,7 +13,10 @@ use std::{
 };
 use thiserror::Error;
 
-use crate::specs_conversion::from_targets_v1_to_conditional_requirements;
+use crate::{
+    common::{BuildSettings, CommonOptions, PackagingConfig, SandboxConfig},
+    specs_conversion::from_targets_v1_to_conditional_requirements,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct PythonParams {
@@ -37,6 +40,10 @@ pub trait GenerateRecipe {
 
     /// Generates an [`IntermediateRecipe`] from a [`ProjectModelV1`].
     ///
+    /// A backend can produce several packages from a single source build by
+    /// populating [`IntermediateRecipe::outputs`], optionally together with
+    /// a shared [`IntermediateRecipe::cache`] build.
+    ///
     /// # Parameters
     ///
     /// * `model` - The project model to convert into a recipe
@@ -77,6 +84,25 @@ pub trait GenerateRecipe {
         Ok(BTreeSet::new())
     }
 
+    /// Returns the input globs of the build like
+    /// [`Self::extract_input_globs_from_build`], for backends that need the
+    /// source directory the globs are matched in. By default it returns the
+    /// globs of [`Self::extract_input_globs_from_build`].
+    ///
+    /// The globs should be passed through
+    /// [`crate::input_globs::input_globs_respecting_ignores`] so that the
+    /// wildcards don't match the files ignored in `source_dir` or written by
+    /// the build.
+    fn extract_input_globs_from_source(
+        &self,
+        config: &Self::Config,
+        _source_dir: impl AsRef<Path>,
+        workdir: impl AsRef<Path>,
+        editable: bool,
+    ) -> miette::Result<BTreeSet<String>> {
+        self.extract_input_globs_from_build(config, workdir, editable)
+    }
+
     /// Returns "default" variants for the given host platform. This allows
     /// backends to set some default variant configuration that can be
     /// completely overwritten by the user.
@@ -96,6 +122,54 @@ pub trait BackendConfig: DeserializeOwned + Clone {
     /// Debug dir provided by the backend config
     fn debug_dir(&self) -> Option<&Path>;
 
+    /// The options shared by all backends that generate a recipe, if the
+    /// backend embeds them with [`backend_config!`](crate::backend_config).
+    /// The default implementations of the other methods read them.
+    fn common_options(&self) -> Option<&CommonOptions> {
+        None
+    }
+
+    /// Path to a partial `recipe.yaml`, relative to the source directory, that
+    /// is deep-merged into the generated recipe (see
+    /// [`recipe_stage0::overlay`]).
+    fn recipe_overlay(&self) -> Option<&Path> {
+        self.common_options()?.recipe_overlay.as_deref()
+    }
+
+    /// The run exports, file filters and linking settings that are written
+    /// into the generated recipe.
+    fn build_settings(&self) -> BuildSettings<'_> {
+        self.common_options()
+            .map(CommonOptions::build_settings)
+            .unwrap_or_default()
+    }
+
+    /// Whether builds are reproducible, see [`crate::reproducible`]. `None`
+    /// enables reproducible builds only if `SOURCE_DATE_EPOCH` is set.
+    fn reproducible(&self) -> Option<bool> {
+        self.common_options()?.reproducible
+    }
+
+    /// The format and compression of the package archive.
+    fn packaging(&self) -> PackagingConfig {
+        self.common_options()
+            .map(CommonOptions::packaging)
+            .unwrap_or_default()
+    }
+
+    /// The sandbox the build script runs in, if any.
+    fn sandbox(&self) -> Option<&SandboxConfig> {
+        self.common_options()?.sandbox()
+    }
+
+    /// Whether the tests of the recipe are run on the package after it was
+    /// built by `conda/build`.
+    fn run_tests(&self) -> bool {
+        self.common_options()
+            .and_then(|options| options.run_tests)
+            .unwrap_or(false)
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values typically override base values.
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self>;
@@ -114,6 +188,10 @@ pub enum GenerateRecipeError<MetadataProviderError: Diagnostic + 'static> {
 #[derive(Default, Clone)]
 pub struct GeneratedRecipe {
     pub recipe: IntermediateRecipe,
+    /// Globs, relative to the manifest root, that match every file the
+    /// backend read to generate the recipe. These are reported as the input
+    /// globs of `conda/outputs`, the frontend doesn't need to call it again
+    /// as long as none of the matched files change.
     pub metadata_input_globs: BTreeSet<String>,
     pub build_input_globs: BTreeSet<String>,
 }
@@ -264,3 +342,101 @@ pub struct DefaultMetadataProvider;
 impl MetadataProvider for DefaultMetadataProvider {
     type Error = Infallible;
 }
+
+impl<T: MetadataProvider + ?Sized> MetadataProvider for &mut T {
+    type Error = T::Error;
+
+    fn name(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).name()
+    }
+    fn version(&mut self) -> Result<Option<Version>, Self::Error> {
+        (**self).version()
+    }
+    fn homepage(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).homepage()
+    }
+    fn license(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).license()
+    }
+    fn license_file(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).license_file()
+    }
+    fn summary(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).summary()
+    }
+    fn description(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).description()
+    }
+    fn documentation(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).documentation()
+    }
+    fn repository(&mut self) -> Result<Option<String>, Self::Error> {
+        (**self).repository()
+    }
+}
+
+/// A [`MetadataProvider`] that asks `first` and falls back to `second` for
+/// every value that `first` does not provide, e.g. a version from git tags
+/// for a `Cargo.toml` without a version.
+pub struct ChainedMetadataProvider<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A, B> ChainedMetadataProvider<A, B> {
+    pub fn new(first: A, second: B) -> Self {
+        Self { first, second }
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum ChainedMetadataProviderError<A: Diagnostic + 'static, B: Diagnostic + 'static> {
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    First(A),
+    #[error(transparent)]
+    #[diagnostic(transparent)]
+    Second(B),
+}
+
+macro_rules! chain_metadata {
+    ($($ident:ident -> $ty:ty),* $(,)?) => {
+        $(
+            fn $ident(&mut self) -> Result<Option<$ty>, Self::Error> {
+                match self
+                    .first
+                    .$ident()
+                    .map_err(ChainedMetadataProviderError::First)?
+                {
+                    Some(value) => Ok(Some(value)),
+                    None => self
+                        .second
+                        .$ident()
+                        .map_err(ChainedMetadataProviderError::Second),
+                }
+            }
+        )*
+    };
+}
+
+impl<A, B> MetadataProvider for ChainedMetadataProvider<A, B>
+where
+    A: MetadataProvider,
+    B: MetadataProvider,
+    A::Error: 'static,
+    B::Error: 'static,
+{
+    type Error = ChainedMetadataProviderError<A::Error, B::Error>;
+
+    chain_metadata!(
+        name -> String,
+        version -> Version,
+        homepage -> String,
+        license -> String,
+        license_file -> String,
+        summary -> String,
+        description -> String,
+        documentation -> String,
+        repository -> String,
+    );
+}
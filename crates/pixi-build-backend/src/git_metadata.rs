@@ -0,0 +1,565 @@
+//! A [`MetadataProvider`] that derives the version of a package from the git
+//! tags of its source directory.
+
+use std::{
+    collections::BTreeSet,
+    io::ErrorKind,
+    path::{Component, Path, PathBuf},
+    process::Command,
+    str::FromStr,
+};
+
+use miette::Diagnostic;
+use rattler_conda_types::{ParseVersionError, Version};
+
+use crate::generated_recipe::MetadataProvider;
+
+#[derive(Debug, thiserror::Error, Diagnostic)]
+pub enum GitMetadataError {
+    #[error("failed to read the git repository")]
+    Io(#[from] std::io::Error),
+    #[error("failed to convert the git tag '{0}' into a version")]
+    ParseVersion(String, #[source] ParseVersionError),
+}
+
+/// An implementation of [`MetadataProvider`] that derives the version from
+/// `git describe --tags`, e.g. `v1.2.3-4-gabc` becomes `1.2.3.post4+gabc`.
+///
+/// If the `git` binary is not available the `.git` directory is read
+/// directly. In that case only a tag that points at the checked out commit is
+/// found, because counting the commits since a tag requires reading the
+/// object database.
+///
+/// The provider only provides a version, so it is meant to be chained behind
+/// the provider of a backend with [`ChainedMetadataProvider`].
+///
+/// [`ChainedMetadataProvider`]: crate::generated_recipe::ChainedMetadataProvider
+pub struct GitVersionMetadataProvider {
+    source_dir: PathBuf,
+    /// The `.git` entry the version was read from, if any.
+    git_entry: Option<PathBuf>,
+}
+
+impl GitVersionMetadataProvider {
+    /// Constructs a new provider for the repository that contains
+    /// `source_dir`.
+    pub fn new(source_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            source_dir: source_dir.into(),
+            git_entry: None,
+        }
+    }
+
+    /// Returns the globs of the git files that determine the version, relative
+    /// to the source directory. These are only returned if the version was
+    /// actually read from git.
+    ///
+    /// The git directory is usually located in a parent of the source
+    /// directory, so the globs start with `../` like the globs of other files
+    /// outside of the source directory, e.g. a Cargo workspace manifest.
+    pub fn input_globs(&self) -> BTreeSet<String> {
+        match &self.git_entry {
+            Some(git_entry) => git_input_globs(&self.source_dir, git_entry),
+            None => BTreeSet::new(),
+        }
+    }
+
+    /// Returns the output of `git describe --tags --long`, or `None` if the
+    /// source directory is not part of a git repository or has no tags.
+    /// Returns an error of kind [`ErrorKind::NotFound`] if `git` is not
+    /// installed.
+    fn git_describe(&self) -> std::io::Result<Option<String>> {
+        let output = Command::new("git")
+            .args(["describe", "--tags", "--long"])
+            .current_dir(&self.source_dir)
+            .output()?;
+        if !output.status.success() {
+            tracing::debug!(
+                "`git describe` failed in {}: {}",
+                self.source_dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+            return Ok(None);
+        }
+        Ok(Some(
+            String::from_utf8_lossy(&output.stdout).trim().to_string(),
+        ))
+    }
+}
+
+impl MetadataProvider for GitVersionMetadataProvider {
+    type Error = GitMetadataError;
+
+    fn version(&mut self) -> Result<Option<Version>, Self::Error> {
+        let Some(git_entry) = find_git_entry(&self.source_dir) else {
+            return Ok(None);
+        };
+
+        let describe = match self.git_describe() {
+            Ok(describe) => describe,
+            Err(err) if err.kind() == ErrorKind::NotFound => {
+                let git_dir = resolve_git_dir(&git_entry)?;
+                tag_of_head(&git_dir)?
+            }
+            Err(err) => return Err(err.into()),
+        };
+        let Some(describe) = describe else {
+            return Ok(None);
+        };
+
+        let version = version_from_git_describe(&describe)
+            .map_err(|err| GitMetadataError::ParseVersion(describe, err))?;
+        self.git_entry = Some(git_entry);
+        Ok(Some(version))
+    }
+}
+
+/// Converts the output of `git describe --tags --long` into a conda version.
+/// A leading `v` of the tag is removed and the commits since the tag are
+/// added as a post release with the abbreviated commit as local version, e.g.
+/// `v1.2.3-4-gabc` becomes `1.2.3.post4+gabc` and `v1.2.3-0-gabc` becomes
+/// `1.2.3`. A plain tag is converted as is.
+pub fn version_from_git_describe(describe: &str) -> Result<Version, ParseVersionError> {
+    let strip_v = |tag: &str| {
+        tag.strip_prefix(['v', 'V'])
+            .filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit()))
+            .unwrap_or(tag)
+            .to_string()
+    };
+
+    let mut parts = describe.rsplitn(3, '-');
+    let version = match (parts.next(), parts.next(), parts.next()) {
+        (Some(hash), Some(distance), Some(tag))
+            if hash.starts_with('g') && distance.parse::<u64>().is_ok() =>
+        {
+            if distance == "0" {
+                strip_v(tag)
+            } else {
+                format!("{}.post{distance}+{hash}", strip_v(tag))
+            }
+        }
+        _ => strip_v(describe),
+    };
+
+    Version::from_str(&version)
+}
+
+/// Returns the commit time of `HEAD` of the repository that contains
+/// `source_dir` as seconds since the Unix epoch, or `None` if the directory
+/// is not part of a git repository or `git` is not installed.
+pub fn last_commit_timestamp(source_dir: &Path) -> Option<i64> {
+    let output = Command::new("git")
+        .args(["log", "-1", "--format=%ct"])
+        .current_dir(source_dir)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout).trim().parse().ok()
+}
+
+/// Finds the `.git` directory or file of the repository that contains `dir`.
+fn find_git_entry(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|ancestor| ancestor.join(".git"))
+        .find(|entry| entry.exists())
+}
+
+/// Resolves a `.git` file of a worktree or submodule to the git directory it
+/// points to.
+fn resolve_git_dir(git_entry: &Path) -> std::io::Result<PathBuf> {
+    if git_entry.is_dir() {
+        return Ok(git_entry.to_path_buf());
+    }
+
+    let content = fs_err::read_to_string(git_entry)?;
+    let git_dir = content
+        .trim()
+        .strip_prefix("gitdir:")
+        .map(|path| PathBuf::from(path.trim()))
+        .ok_or_else(|| {
+            std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("{} does not contain a gitdir", git_entry.display()),
+            )
+        })?;
+    Ok(match git_entry.parent() {
+        Some(parent) if git_dir.is_relative() => parent.join(git_dir),
+        _ => git_dir,
+    })
+}
+
+/// Returns the directory with the refs that are shared by all worktrees of
+/// the git directory, which is the git directory itself for a repository
+/// without worktrees.
+fn common_dir(git_dir: &Path) -> std::io::Result<PathBuf> {
+    match fs_err::read_to_string(git_dir.join("commondir")) {
+        Ok(common_dir) => Ok(git_dir.join(common_dir.trim())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(git_dir.to_path_buf()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Removes the `.` and `..` components of `path` without touching the file
+/// system, git stores relative paths like `../.git/modules/foo`.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+/// Returns the name of the tag that points at `HEAD`, in the format of `git
+/// describe --tags --long`, without running git.
+fn tag_of_head(git_dir: &Path) -> std::io::Result<Option<String>> {
+    let common_dir = common_dir(git_dir)?;
+    let packed_refs = read_packed_refs(&common_dir)?;
+    let Some(head) = read_head(git_dir, &common_dir, &packed_refs)? else {
+        return Ok(None);
+    };
+
+    let mut tags = Vec::new();
+    collect_loose_tags(&common_dir.join("refs/tags"), "", &mut tags)?;
+    tags.extend(packed_refs.into_iter().filter_map(|packed| {
+        Some((
+            packed.name.strip_prefix("refs/tags/")?.to_string(),
+            packed.commit,
+        ))
+    }));
+
+    // Prefer the highest version if multiple tags point at `HEAD`.
+    let tag = tags
+        .into_iter()
+        .filter(|(_, commit)| commit == &head)
+        .map(|(tag, _)| tag)
+        .max_by_key(|tag| version_from_git_describe(tag).ok());
+    Ok(tag.map(|tag| format!("{tag}-0-g{}", short_commit(&head))))
+}
+
+/// Returns the abbreviated hash of the commit that is checked out in the
+/// repository that contains `source_dir`, or `None` if the directory is not
+/// part of a git repository or the checked out branch has no commits.
+pub fn head_commit(source_dir: &Path) -> std::io::Result<Option<String>> {
+    let Some(git_entry) = find_git_entry(source_dir) else {
+        return Ok(None);
+    };
+    let git_dir = resolve_git_dir(&git_entry)?;
+    let common_dir = common_dir(&git_dir)?;
+    let packed_refs = read_packed_refs(&common_dir)?;
+    let head = read_head(&git_dir, &common_dir, &packed_refs)?;
+    Ok(head.map(|head| short_commit(&head).to_string()))
+}
+
+/// Returns the globs of the git files that determine the commit that is
+/// checked out in the repository that contains `source_dir`, relative to
+/// `source_dir`.
+pub fn head_commit_input_globs(source_dir: &Path) -> BTreeSet<String> {
+    find_git_entry(source_dir)
+        .map(|git_entry| git_input_globs(source_dir, &git_entry))
+        .unwrap_or_default()
+}
+
+/// Returns the globs of the files of the git repository at `git_entry` that
+/// determine the checked out commit and the tags, relative to `source_dir`.
+fn git_input_globs(source_dir: &Path, git_entry: &Path) -> BTreeSet<String> {
+    let source_dir = normalize_path(source_dir);
+    let relative = |path: &Path| {
+        let path = normalize_path(path);
+        pathdiff::diff_paths(&path, &source_dir)
+            .unwrap_or(path)
+            .display()
+            .to_string()
+            .replace('\\', "/")
+    };
+
+    let mut globs = BTreeSet::new();
+    if git_entry.is_file() {
+        // A worktree or submodule, the `.git` file points to the actual
+        // git directory.
+        globs.insert(relative(git_entry));
+    }
+
+    // The refs are shared by all worktrees, only `HEAD` is specific to the
+    // worktree.
+    let git_dirs = resolve_git_dir(git_entry).and_then(|git_dir| {
+        let common_dir = common_dir(&git_dir)?;
+        Ok((git_dir, common_dir))
+    });
+    match git_dirs {
+        Ok((git_dir, common_dir)) => {
+            let git_dir = relative(&git_dir);
+            let common_dir = relative(&common_dir);
+            globs.insert(format!("{git_dir}/HEAD"));
+            globs.insert(format!("{common_dir}/packed-refs"));
+            globs.insert(format!("{common_dir}/refs/**"));
+        }
+        Err(err) => tracing::debug!(
+            "failed to resolve the git directory of {}: {err}",
+            git_entry.display()
+        ),
+    }
+    globs
+}
+
+/// Returns the commit that `HEAD` points to, or `None` for a branch without
+/// commits.
+fn read_head(
+    git_dir: &Path,
+    common_dir: &Path,
+    packed_refs: &[PackedRef],
+) -> std::io::Result<Option<String>> {
+    let head = fs_err::read_to_string(git_dir.join("HEAD"))?;
+    let Some(reference) = head.trim().strip_prefix("ref:") else {
+        return Ok(Some(head.trim().to_string()));
+    };
+
+    let reference = reference.trim();
+    match fs_err::read_to_string(common_dir.join(reference)) {
+        Ok(commit) => Ok(Some(commit.trim().to_string())),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(packed_refs
+            .iter()
+            .find(|packed| packed.name == reference)
+            .map(|packed| packed.commit.clone())),
+        Err(err) => Err(err),
+    }
+}
+
+/// Abbreviates a commit hash like `git describe` does.
+fn short_commit(commit: &str) -> &str {
+    commit.get(..7).unwrap_or(commit)
+}
+
+struct PackedRef {
+    name: String,
+    /// The commit the ref points to, annotated tags are peeled.
+    commit: String,
+}
+
+/// Reads the `packed-refs` file of a git directory.
+fn read_packed_refs(common_dir: &Path) -> std::io::Result<Vec<PackedRef>> {
+    let content = match fs_err::read_to_string(common_dir.join("packed-refs")) {
+        Ok(content) => content,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+
+    let mut refs: Vec<PackedRef> = Vec::new();
+    for line in content.lines() {
+        if let Some(peeled) = line.strip_prefix('^') {
+            // The commit an annotated tag on the previous line points to
+            if let Some(last) = refs.last_mut() {
+                last.commit = peeled.trim().to_string();
+            }
+        } else if let Some((commit, name)) = line
+            .split_once(' ')
+            .filter(|(commit, _)| !commit.starts_with('#'))
+        {
+            refs.push(PackedRef {
+                name: name.trim().to_string(),
+                commit: commit.to_string(),
+            });
+        }
+    }
+    Ok(refs)
+}
+
+/// Collects the loose tags below `dir`. Annotated tags point to a tag object
+/// instead of a commit and are therefore never matched.
+fn collect_loose_tags(
+    dir: &Path,
+    prefix: &str,
+    tags: &mut Vec<(String, String)>,
+) -> std::io::Result<()> {
+    let entries = match fs_err::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err),
+    };
+
+    for entry in entries {
+        let entry = entry?;
+        let name = format!("{prefix}{}", entry.file_name().to_string_lossy());
+        if entry.file_type()?.is_dir() {
+            collect_loose_tags(&entry.path(), &format!("{name}/"), tags)?;
+        } else {
+            let commit = fs_err::read_to_string(entry.path())?;
+            tags.push((name, commit.trim().to_string()));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_version_from_git_describe() {
+        let version = |describe: &str| version_from_git_describe(describe).unwrap().to_string();
+
+        assert_eq!(version("v1.2.3-4-gabc1234"), "1.2.3.post4+gabc1234");
+        assert_eq!(version("v1.2.3-0-gabc1234"), "1.2.3");
+        assert_eq!(version("1.2.3-12-gabc1234"), "1.2.3.post12+gabc1234");
+        assert_eq!(version("v2.0.0"), "2.0.0");
+    }
+
+    #[test]
+    fn test_tag_of_head_without_git() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git_dir = temp_dir.path().join(".git");
+        let head = "0123456789abcdef0123456789abcdef01234567";
+        fs_err::create_dir_all(git_dir.join("refs/heads")).unwrap();
+        fs_err::create_dir_all(git_dir.join("refs/tags")).unwrap();
+        fs_err::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs_err::write(git_dir.join("refs/heads/main"), format!("{head}\n")).unwrap();
+        fs_err::write(
+            git_dir.join("refs/tags/v0.9.0"),
+            "fedcba9876543210fedcba9876543210fedcba98\n",
+        )
+        .unwrap();
+        fs_err::write(
+            git_dir.join("packed-refs"),
+            format!(
+                "# pack-refs with: peeled fully-peeled sorted\n\
+                 1111111111111111111111111111111111111111 refs/tags/v1.0.0\n\
+                 ^{head}\n"
+            ),
+        )
+        .unwrap();
+
+        assert_eq!(
+            tag_of_head(&git_dir).unwrap(),
+            Some("v1.0.0-0-g0123456".to_string())
+        );
+
+        assert_eq!(
+            head_commit(temp_dir.path()).unwrap(),
+            Some("0123456".to_string())
+        );
+
+        // Without a tag on `HEAD` the version cannot be derived.
+        fs_err::write(git_dir.join("packed-refs"), "").unwrap();
+        assert_eq!(tag_of_head(&git_dir).unwrap(), None);
+    }
+
+    #[test]
+    fn test_input_globs() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let source_dir = temp_dir.path().join("packages/foo");
+        fs_err::create_dir_all(&source_dir).unwrap();
+        fs_err::create_dir_all(temp_dir.path().join(".git")).unwrap();
+
+        let mut provider = GitVersionMetadataProvider::new(&source_dir);
+        assert!(provider.input_globs().is_empty());
+
+        provider.git_entry = find_git_entry(&source_dir);
+        assert_eq!(
+            provider.input_globs(),
+            BTreeSet::from([
+                "../../.git/HEAD".to_string(),
+                "../../.git/packed-refs".to_string(),
+                "../../.git/refs/**".to_string(),
+            ])
+        );
+    }
+
+    /// Resolves the input globs against the source directory like the
+    /// frontend does.
+    fn resolve_globs(source_dir: &Path, globs: &BTreeSet<String>) -> BTreeSet<PathBuf> {
+        globs
+            .iter()
+            .map(|glob| normalize_path(&source_dir.join(glob)))
+            .collect()
+    }
+
+    #[test]
+    fn test_input_globs_of_parent_directory() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let source_dir = root.join("packages/foo");
+        fs_err::create_dir_all(&source_dir).unwrap();
+        fs_err::create_dir_all(root.join(".git")).unwrap();
+
+        let mut provider = GitVersionMetadataProvider::new(&source_dir);
+        provider.git_entry = find_git_entry(&source_dir);
+
+        // The parent relative globs point to the git directory of the
+        // repository.
+        assert_eq!(
+            resolve_globs(&source_dir, &provider.input_globs()),
+            BTreeSet::from([
+                root.join(".git/HEAD"),
+                root.join(".git/packed-refs"),
+                root.join(".git/refs/**"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_input_globs_of_worktree() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let source_dir = root.join("worktree");
+        let git_dir = root.join("main/.git/worktrees/worktree");
+        fs_err::create_dir_all(&source_dir).unwrap();
+        fs_err::create_dir_all(&git_dir).unwrap();
+        fs_err::write(
+            source_dir.join(".git"),
+            format!("gitdir: {}\n", git_dir.display()),
+        )
+        .unwrap();
+        fs_err::write(git_dir.join("commondir"), "../..\n").unwrap();
+
+        let mut provider = GitVersionMetadataProvider::new(&source_dir);
+        provider.git_entry = find_git_entry(&source_dir);
+
+        // `HEAD` belongs to the worktree, the refs to the main repository.
+        assert_eq!(
+            resolve_globs(&source_dir, &provider.input_globs()),
+            BTreeSet::from([
+                source_dir.join(".git"),
+                git_dir.join("HEAD"),
+                root.join("main/.git/packed-refs"),
+                root.join("main/.git/refs/**"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_input_globs_of_submodule() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let root = temp_dir.path();
+        let source_dir = root.join("sub");
+        fs_err::create_dir_all(&source_dir).unwrap();
+        fs_err::create_dir_all(root.join(".git/modules/sub")).unwrap();
+        fs_err::write(source_dir.join(".git"), "gitdir: ../.git/modules/sub\n").unwrap();
+
+        let mut provider = GitVersionMetadataProvider::new(&source_dir);
+        provider.git_entry = find_git_entry(&source_dir);
+
+        assert_eq!(
+            provider.input_globs(),
+            BTreeSet::from([
+                ".git".to_string(),
+                "../.git/modules/sub/HEAD".to_string(),
+                "../.git/modules/sub/packed-refs".to_string(),
+                "../.git/modules/sub/refs/**".to_string(),
+            ])
+        );
+    }
+}
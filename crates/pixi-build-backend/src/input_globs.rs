@@ -0,0 +1,409 @@
+//! Input globs of a build that respect ignore files.
+//!
+//! The default input globs of a backend are wildcards like `**/*.rs`, which
+//! also match files in directories that are written by a build, such as
+//! `target/`, `build/` or the pixi environments in `.pixi/`. Every build then
+//! changes its own inputs and the package is rebuilt over and over again. The
+//! helpers in this module add exclude globs, starting with a `!`, for those
+//! directories and for the files of the source directory that git ignores.
+
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+};
+
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use ignore::WalkBuilder;
+use itertools::Itertools;
+
+/// Directories that never contain inputs of a build.
+const ALWAYS_EXCLUDED: [&str; 2] = [".git", ".pixi"];
+
+/// Returns the `default_globs` of a backend and the `extra_globs` of the user
+/// together with exclude globs for the pixi environments, the `output_dirs`
+/// of the backend, relative to the source directory, and the ignored files
+/// that the wildcard default globs match.
+///
+/// The files are matched against the ignore rules with the [`ignore`] crate,
+/// which reads the `.gitignore` and `.ignore` files of the source directory,
+/// its subdirectories and its parents, as well as `.git/info/exclude`.
+/// Files that an explicit default glob, like `Cargo.toml`, or an extra glob
+/// matches are never excluded. Only the directories that the wildcard globs
+/// can match files in are walked, e.g. `src` for `src/**/*.rs`.
+pub fn input_globs_respecting_ignores(
+    source_dir: &Path,
+    default_globs: impl IntoIterator<Item = String>,
+    extra_globs: impl IntoIterator<Item = String>,
+    output_dirs: &[&str],
+) -> BTreeSet<String> {
+    let (wildcard_globs, explicit_globs): (Vec<_>, Vec<_>) = default_globs
+        .into_iter()
+        .partition(|glob| is_wildcard(glob));
+    let extra_globs = extra_globs.into_iter().collect_vec();
+
+    let protected_globs = explicit_globs.iter().chain(&extra_globs).collect_vec();
+    let matchers = Matchers {
+        wildcards: glob_set(&wildcard_globs),
+        protected: glob_set(&protected_globs),
+        // Only a wildcard can match files anywhere in an ignored directory,
+        // the explicit globs are checked by their path.
+        protected_paths: if protected_globs.iter().any(|glob| is_wildcard(glob)) {
+            None
+        } else {
+            Some(protected_globs.iter().map(PathBuf::from).collect())
+        },
+    };
+
+    let excluded_dirs = ALWAYS_EXCLUDED
+        .iter()
+        .chain(output_dirs)
+        .map(|dir| PathBuf::from(dir.trim_end_matches('/')))
+        .collect_vec();
+    let excludes = excluded_dirs
+        .iter()
+        .flat_map(|dir| matchers.dir_excludes(source_dir, dir))
+        .chain(
+            ignored_paths(source_dir, &walk_roots(&wildcard_globs), &excluded_dirs)
+                .into_iter()
+                .flat_map(|path| {
+                    if source_dir.join(&path).is_dir() {
+                        matchers.dir_excludes(source_dir, &path)
+                    } else {
+                        matchers.file_excludes([path])
+                    }
+                }),
+        );
+
+    wildcard_globs
+        .into_iter()
+        .chain(explicit_globs)
+        .chain(extra_globs)
+        .chain(excludes.map(|glob| format!("!{glob}")))
+        .collect()
+}
+
+/// The globs that decide which ignored files are excluded.
+struct Matchers {
+    /// The wildcard default globs, only the files they match are excluded.
+    wildcards: GlobSet,
+    /// The explicit default globs and the extra globs, the files they match
+    /// are never excluded.
+    protected: GlobSet,
+    /// The paths of the protected globs if none of them is a wildcard.
+    protected_paths: Option<Vec<PathBuf>>,
+}
+
+impl Matchers {
+    /// Returns the excludes for a directory that is ignored as a whole. The
+    /// directory is excluded with a single glob unless it contains protected
+    /// files, then its files are excluded one by one.
+    fn dir_excludes(&self, source_dir: &Path, dir: &Path) -> Vec<String> {
+        let may_contain_protected = self
+            .protected_paths
+            .as_ref()
+            .is_none_or(|paths| paths.iter().any(|path| path.starts_with(dir)));
+        if !may_contain_protected {
+            return vec![format!("{}/**", glob_path(dir))];
+        }
+
+        let files = WalkBuilder::new(source_dir.join(dir))
+            .standard_filters(false)
+            .build()
+            .flatten()
+            .filter(|entry| {
+                entry
+                    .file_type()
+                    .is_some_and(|file_type| !file_type.is_dir())
+            })
+            .filter_map(|entry| {
+                entry
+                    .path()
+                    .strip_prefix(source_dir)
+                    .ok()
+                    .map(Path::to_path_buf)
+            })
+            .collect_vec();
+        if files.iter().any(|file| self.protected.is_match(file)) {
+            self.file_excludes(files)
+        } else {
+            vec![format!("{}/**", glob_path(dir))]
+        }
+    }
+
+    /// Returns the excludes for ignored files that a wildcard glob matches.
+    fn file_excludes(&self, files: impl IntoIterator<Item = PathBuf>) -> Vec<String> {
+        files
+            .into_iter()
+            .filter(|file| self.wildcards.is_match(file) && !self.protected.is_match(file))
+            .map(|file| glob_path(&file))
+            .collect()
+    }
+}
+
+/// Returns the directories, relative to the source directory, that the
+/// wildcard globs can match files in. That is the path of a glob up to its
+/// first wildcard, e.g. `src` for `src/**/*.rs` and the source directory
+/// itself for `**/*.rs`. Directories within another root are dropped, and so
+/// are globs that leave the source directory.
+fn walk_roots(wildcard_globs: &[String]) -> Vec<PathBuf> {
+    let bases = wildcard_globs
+        .iter()
+        .map(|glob| {
+            glob.split('/')
+                .take_while(|component| !is_wildcard(component))
+                .collect::<PathBuf>()
+        })
+        .filter(|base| {
+            base.components()
+                .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+        })
+        .map(|base| base.components().collect::<PathBuf>())
+        .sorted()
+        .collect_vec();
+
+    // A root sorts before the directories within it
+    let mut roots: Vec<PathBuf> = Vec::new();
+    for base in bases {
+        if !roots.iter().any(|root| base.starts_with(root)) {
+            roots.push(base);
+        }
+    }
+    roots
+}
+
+/// Returns the ignored files and directories in the `roots` of `source_dir`,
+/// relative to it. The contents of an ignored directory are not listed, and
+/// the `excluded_dirs` are skipped.
+fn ignored_paths(source_dir: &Path, roots: &[PathBuf], excluded_dirs: &[PathBuf]) -> Vec<PathBuf> {
+    let mut roots = roots
+        .iter()
+        .map(|root| source_dir.join(root))
+        .filter(|root| root.is_dir());
+    let Some(first_root) = roots.next() else {
+        return Vec::new();
+    };
+    let mut builder = WalkBuilder::new(first_root);
+    for root in roots {
+        builder.add(root);
+    }
+
+    let root = source_dir.to_path_buf();
+    let skipped = excluded_dirs.to_vec();
+    let walk = builder
+        .hidden(false)
+        .git_global(false)
+        .require_git(false)
+        .filter_entry(move |entry| {
+            let path = entry.path().strip_prefix(&root).unwrap_or(entry.path());
+            !skipped.iter().any(|dir| dir == path)
+        })
+        .build();
+
+    let mut kept_dirs = Vec::new();
+    let mut kept = BTreeSet::new();
+    for entry in walk.flatten() {
+        let Ok(path) = entry.path().strip_prefix(source_dir) else {
+            continue;
+        };
+        if entry
+            .file_type()
+            .is_some_and(|file_type| file_type.is_dir())
+        {
+            kept_dirs.push(path.to_path_buf());
+        }
+        kept.insert(path.to_path_buf());
+    }
+
+    // Everything in a walked directory that the walk skipped is ignored
+    kept_dirs
+        .iter()
+        .filter_map(|dir| fs_err::read_dir(source_dir.join(dir)).ok())
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path().strip_prefix(source_dir).ok()?.to_path_buf();
+            (!kept.contains(&path) && !excluded_dirs.contains(&path)).then_some(path)
+        })
+        .collect()
+}
+
+/// Returns true if the glob contains a wildcard, as opposed to a single path.
+fn is_wildcard(glob: &str) -> bool {
+    glob.contains(['*', '?', '[', '{'])
+}
+
+/// Builds a set of the globs, a `*` doesn't match a `/`. Invalid globs are
+/// skipped.
+fn glob_set(globs: impl IntoIterator<Item = impl AsRef<str>>) -> GlobSet {
+    let mut builder = GlobSetBuilder::new();
+    for glob in globs {
+        if let Ok(glob) = GlobBuilder::new(glob.as_ref())
+            .literal_separator(true)
+            .build()
+        {
+            builder.add(glob);
+        }
+    }
+    builder.build().unwrap_or_else(|_| GlobSet::empty())
+}
+
+/// Converts a relative path to a glob that matches exactly that path.
+fn glob_path(path: &Path) -> String {
+    globset::escape(
+        &path
+            .components()
+            .map(|component| component.as_os_str().to_string_lossy())
+            .join("/"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(source_dir: &Path, path: &str, contents: &str) {
+        let path = source_dir.join(path);
+        fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+        fs_err::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_input_globs_respecting_ignores() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_dir = source_dir.path();
+        write(
+            source_dir,
+            ".gitignore",
+            "# build output\n/cmake-build-debug/\ngenerated/\n*.rs.bk\n",
+        );
+        write(source_dir, "src/.gitignore", "*.gen.rs\n!keep.gen.rs\n");
+        write(source_dir, ".git/info/exclude", "scratch.rs\n");
+        write(source_dir, "cmake-build-debug/main.rs", "");
+        write(source_dir, "src/lib.rs", "");
+        write(source_dir, "src/lib.gen.rs", "");
+        write(source_dir, "src/keep.gen.rs", "");
+        write(source_dir, "src/lib.rs.bk", "");
+        write(source_dir, "scratch.rs", "");
+        write(source_dir, "generated/out.rs", "");
+        write(source_dir, "generated/version.txt", "");
+        write(source_dir, "notes/.ignore", "draft.rs\n");
+        write(source_dir, "notes/draft.rs", "");
+        write(source_dir, "Cargo.lock", "");
+
+        let globs = input_globs_respecting_ignores(
+            source_dir,
+            ["**/*.rs", "Cargo.lock"].map(String::from),
+            [String::from("generated/*.txt")],
+            &["target"],
+        );
+
+        assert_eq!(
+            globs,
+            BTreeSet::from(
+                [
+                    // The output directories are always excluded
+                    "!.git/**",
+                    "!.pixi/**",
+                    "!target/**",
+                    // Ignored by the root `.gitignore`
+                    "!cmake-build-debug/**",
+                    // Ignored by the nested `.gitignore`, except for the
+                    // negated `keep.gen.rs`
+                    "!src/lib.gen.rs",
+                    // Ignored by `.git/info/exclude`
+                    "!scratch.rs",
+                    // Ignored by the nested `.ignore`
+                    "!notes/draft.rs",
+                    // The directory contains a file of an extra glob, only
+                    // the files of the wildcard globs are excluded
+                    "!generated/out.rs",
+                    "**/*.rs",
+                    "Cargo.lock",
+                    "generated/*.txt",
+                ]
+                .map(String::from)
+            )
+        );
+    }
+
+    #[test]
+    fn test_explicit_globs_are_never_excluded() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_dir = source_dir.path();
+        write(source_dir, ".gitignore", "Cargo.lock\nbuild/\n");
+        write(source_dir, "Cargo.lock", "");
+        write(source_dir, "build/config.rs", "");
+        write(source_dir, "build/generated.rs", "");
+
+        let globs = input_globs_respecting_ignores(
+            source_dir,
+            ["**/*.rs", "Cargo.lock", "build/config.rs"].map(String::from),
+            [],
+            &[],
+        );
+
+        assert_eq!(
+            globs,
+            BTreeSet::from(
+                [
+                    "!.git/**",
+                    "!.pixi/**",
+                    "!build/generated.rs",
+                    "**/*.rs",
+                    "Cargo.lock",
+                    "build/config.rs",
+                ]
+                .map(String::from)
+            )
+        );
+    }
+
+    #[test]
+    fn test_only_the_globbed_dirs_are_walked() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let source_dir = source_dir.path();
+        write(source_dir, ".gitignore", "*.gen.c\n*.gen.h\nvendor/\n");
+        write(source_dir, "src/main.gen.c", "");
+        write(source_dir, "include/api.gen.h", "");
+        write(source_dir, "include/detail/impl.gen.h", "");
+        write(source_dir, "tools/tool.gen.c", "");
+        write(source_dir, "vendor/lib.c", "");
+
+        let globs = input_globs_respecting_ignores(
+            source_dir,
+            ["src/**/*.c", "include/*.h", "include/detail/*.h"].map(String::from),
+            [],
+            &[],
+        );
+
+        assert_eq!(
+            globs,
+            BTreeSet::from(
+                [
+                    "!.git/**",
+                    "!.pixi/**",
+                    "!include/api.gen.h",
+                    "!include/detail/impl.gen.h",
+                    "!src/main.gen.c",
+                    "include/*.h",
+                    "include/detail/*.h",
+                    "src/**/*.c",
+                ]
+                .map(String::from)
+            )
+        );
+    }
+
+    #[test]
+    fn test_walk_roots() {
+        let roots =
+            |globs: &[&str]| walk_roots(&globs.iter().map(|glob| glob.to_string()).collect_vec());
+
+        assert_eq!(roots(&["**/*.rs", "src/*.rs"]), [PathBuf::new()]);
+        assert_eq!(
+            roots(&["src/**/*.c", "include/detail/*.h", "include/*.h"]),
+            [PathBuf::from("include"), PathBuf::from("src")]
+        );
+        assert_eq!(roots(&["../shared/**/*.h"]), Vec::<PathBuf>::new());
+    }
+}
@@ -5,7 +5,8 @@ use std::{
 };
 
 use indexmap::{IndexMap, IndexSet};
-use miette::{Context, IntoDiagnostic};
+use itertools::Itertools;
+use miette::{Context, Diagnostic, IntoDiagnostic};
 use ordermap::OrderMap;
 use pixi_build_types::{
     BackendCapabilities, PathSpecV1, ProjectModelV1, SourcePackageSpecV1, TargetSelectorV1,
@@ -23,33 +24,58 @@ use rattler_build::{
     build::{WorkingDirectoryBehavior, run_build},
     console_utils::LoggingOutputHandler,
     hash::HashInfo,
-    metadata::{BuildConfiguration, Debug, Output, PlatformWithVirtualPackages},
+    metadata::{BuildConfiguration, Debug, Output},
+    package_test::{TestConfiguration, TestError, run_test},
     recipe::{ParsingError, Recipe, parser::find_outputs_from_src, variable::Variable},
     selectors::SelectorConfig,
     source_code::Source,
     tool_configuration::Configuration,
-    types::{Directories, PackageIdentifier, PackagingSettings},
+    types::{Directories, PackageIdentifier},
     variant_config::{DiscoveredOutput, ParseErrors, VariantConfig},
 };
-use rattler_conda_types::{Platform, compression_level::CompressionLevel, package::ArchiveType};
+use rattler_conda_types::{ChannelUrl, Platform};
+use recipe_stage0::{
+    matchspec::PackageDependency,
+    overlay::apply_overlay,
+    recipe::{ConditionalList, Value},
+};
 
 use serde::Deserialize;
+use thiserror::Error;
 use tracing::warn;
 
 use crate::{
     TargetSelector,
+    common::platforms_with_virtual_packages,
     consts::DEBUG_OUTPUT_DIR,
     dependencies::{
         convert_binary_dependencies, convert_dependencies, convert_input_variant_configuration,
     },
-    generated_recipe::{BackendConfig, GenerateRecipe, PythonParams},
+    generated_recipe::{BackendConfig, GenerateRecipe, GeneratedRecipe, PythonParams},
+    git_metadata::{head_commit, head_commit_input_globs},
     protocol::{Protocol, ProtocolInstantiator},
-    specs_conversion::from_build_v1_args_to_finalized_dependencies,
+    reproducible::{export_source_date_epoch, normalize_package, reproducible_timestamp},
+    selector::{SelectorExpression, SelectorVariant},
+    specs_conversion::{
+        from_build_v1_args_to_finalized_dependencies, run_constraints_to_conditional_list,
+    },
     tools::{OneOrMultipleOutputs, output_directory},
 };
 
 use fs_err::tokio as tokio_fs;
 
+/// The tests of a package that was built by `conda/build` failed.
+#[derive(Debug, Error, Diagnostic)]
+#[error("the tests of {package} failed")]
+#[diagnostic(help(
+    "the tests of the recipe are run because `run-tests` is enabled in the backend configuration"
+))]
+pub struct PackageTestError {
+    pub package: String,
+    #[source]
+    pub source: TestError,
+}
+
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "kebab-case")]
 pub struct IntermediateBackendConfig {
@@ -75,6 +101,68 @@ impl<T: GenerateRecipe> IntermediateBackendInstantiator<T> {
     }
 }
 
+/// A target specific configuration and the values it was parsed from.
+pub(crate) struct TargetConfig<C> {
+    pub(crate) config: C,
+    pub(crate) values: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Returns the options that two equally specific target configurations, which
+/// can apply to the same platform and variant, set to different values,
+/// together with the selector of the overridden and of the overriding
+/// configuration. Nested options are compared per key, e.g. `env.A`, so that
+/// options which are merged by extending them are not reported.
+fn conflicting_keys<'a, C>(
+    targets: &[(&'a TargetSelectorV1, &'a TargetConfig<C>)],
+) -> Vec<(String, &'a TargetSelectorV1, &'a TargetSelectorV1)> {
+    targets
+        .iter()
+        .tuple_combinations()
+        .filter(|((lhs, _), (rhs, _))| {
+            lhs.specificity() == rhs.specificity() && selectors_overlap(lhs, rhs)
+        })
+        .flat_map(|(&(lhs, lhs_config), &(rhs, rhs_config))| {
+            let rhs_values = leaf_values(&rhs_config.values);
+            leaf_values(&lhs_config.values)
+                .into_iter()
+                .filter(move |(key, value)| {
+                    rhs_values
+                        .iter()
+                        .any(|(other_key, other)| other_key == key && other != value)
+                })
+                .map(move |(key, _)| (key, lhs, rhs))
+        })
+        .collect()
+}
+
+/// Returns true if both selectors can match the same platform and variant.
+fn selectors_overlap(lhs: &TargetSelectorV1, rhs: &TargetSelectorV1) -> bool {
+    match (
+        SelectorExpression::try_from(lhs),
+        SelectorExpression::try_from(rhs),
+    ) {
+        (Ok(lhs), Ok(rhs)) => lhs.overlaps(&rhs),
+        _ => false,
+    }
+}
+
+/// Flattens the values of a configuration to the paths of the values that are
+/// not objects, e.g. `{"env": {"A": "1"}}` to `env.A`.
+fn leaf_values(
+    values: &serde_json::Map<String, serde_json::Value>,
+) -> Vec<(String, &serde_json::Value)> {
+    values
+        .iter()
+        .flat_map(|(key, value)| match value {
+            serde_json::Value::Object(nested) => leaf_values(nested)
+                .into_iter()
+                .map(|(nested_key, value)| (format!("{key}.{nested_key}"), value))
+                .collect(),
+            _ => vec![(key.clone(), value)],
+        })
+        .collect()
+}
+
 pub struct IntermediateBackend<T: GenerateRecipe> {
     pub(crate) logging_output_handler: LoggingOutputHandler,
     pub(crate) source_dir: PathBuf,
@@ -83,7 +171,7 @@ pub struct IntermediateBackend<T: GenerateRecipe> {
     pub(crate) project_model: ProjectModelV1,
     pub(crate) generate_recipe: Arc<T>,
     pub(crate) config: T::Config,
-    pub(crate) target_config: OrderMap<TargetSelectorV1, T::Config>,
+    pub(crate) target_config: OrderMap<TargetSelectorV1, TargetConfig<T::Config>>,
     pub(crate) cache_dir: Option<PathBuf>,
 }
 impl<T: GenerateRecipe> IntermediateBackend<T> {
@@ -141,14 +229,23 @@ impl<T: GenerateRecipe> IntermediateBackend<T> {
         let target_config = target_config
             .into_iter()
             .map(|(target, config)| {
+                SelectorExpression::try_from(&target).into_diagnostic()?;
+                let values = config.as_object().cloned().unwrap_or_default();
                 let config = serde_json::from_value::<T::Config>(config)
                     .into_diagnostic()
                     .wrap_err_with(|| {
                         format!("failed to parse target configuration for {target}")
                     })?;
-                Ok((target, config))
+                Ok((target, TargetConfig { config, values }))
             })
-            .collect::<Result<_, miette::Report>>()?;
+            .collect::<Result<OrderMap<_, _>, miette::Report>>()?;
+
+        let targets = target_config.iter().collect_vec();
+        for (key, overridden, selector) in conflicting_keys(&targets) {
+            warn!(
+                "the target configurations `{overridden}` and `{selector}` are equally specific and both set `{key}`, the value of `{selector}` is used where both apply because it is declared last"
+            );
+        }
 
         Ok(Self {
             source_dir,
@@ -226,14 +323,7 @@ where
     ) -> miette::Result<CondaOutputsResult> {
         let build_platform = params.host_platform;
 
-        let config = self
-            .target_config
-            .iter()
-            .find(|(selector, _)| selector.matches(params.host_platform))
-            .map(|(_, target_config)| self.config.merge_with_target_config(target_config))
-            .unwrap_or_else(|| Ok(self.config.clone()))?;
-
-        let selector_config_for_variants = SelectorConfig {
+        let mut selector_config_for_variants = SelectorConfig {
             target_platform: params.host_platform,
             host_platform: params.host_platform,
             build_platform,
@@ -263,33 +353,43 @@ where
                 .unwrap_or_default();
         variant_config.variants.append(&mut param_variants);
 
-        // Construct the intermediate recipe
-        let generated_recipe = self.generate_recipe.generate_recipe(
-            &self.project_model,
-            &config,
-            self.source_dir.clone(),
+        // A target selector on a variant that is not defined never matches,
+        // which is most likely a typo in the selector.
+        for (selector, key) in self.target_config.keys().flat_map(|selector| {
+            SelectorExpression::try_from(selector)
+                .map(|expression| expression.variant_keys())
+                .unwrap_or_default()
+                .into_iter()
+                .map(move |key| (selector, key))
+        }) {
+            if !variant_config.variants.contains_key(&key) {
+                warn!(
+                    "the target selector `{selector}` refers to the variant `{}`, which is not defined",
+                    key.normalize()
+                );
+            }
+        }
+
+        // The variants are not expanded yet, the outputs are discovered with the
+        // target configurations that match any of the variant values. The
+        // targets of the variant of each output are checked below.
+        let (_, generated_recipe) = self.generate_recipe_for_variant(
             params.host_platform,
-            Some(PythonParams { editable: false }),
-            &variant_config.variants.keys().cloned().collect(),
-            params.channels,
+            &variant_config.variants,
+            PythonParams { editable: false },
+            params.channels.clone(),
         )?;
+        let targets = self.matching_targets(params.host_platform, &variant_config.variants);
+
+        // The cache of a multi-output recipe is an experimental feature of
+        // rattler-build.
+        selector_config_for_variants.experimental = generated_recipe.recipe.cache.is_some();
 
         // Convert the recipe to source code.
         // TODO(baszalmstra): In the future it would be great if we could just
         // immediately use the intermediate recipe for some of this rattler-build
         // functions.
-        let recipe_path = self.source_dir.join(&self.manifest_rel_path);
-        let named_source = Source {
-            name: self.manifest_rel_path.display().to_string(),
-            code: Arc::from(
-                generated_recipe
-                    .recipe
-                    .to_yaml_pretty()
-                    .into_diagnostic()?
-                    .as_str(),
-            ),
-            path: recipe_path.clone(),
-        };
+        let named_source = self.recipe_source(&generated_recipe)?;
 
         // Determine the different outputs that are supported by the recipe by expanding
         // all the different variant combinations.
@@ -325,8 +425,47 @@ where
         let num_of_outputs = discovered_outputs.len();
 
         let mut variants_saved = false;
+        let mut metadata_input_globs = generated_recipe.metadata_input_globs.clone();
 
         for discovered_output in discovered_outputs {
+            // A target configuration applies to the outputs whose variant its
+            // selector matches, like in `conda/build`. An output whose variant
+            // matches other targets than the recipe was generated with is
+            // discovered again from the recipe that is generated for its own
+            // variant.
+            let output_variant: SelectorVariant = discovered_output
+                .used_vars
+                .iter()
+                .map(|(key, value)| (key.clone(), vec![value.clone()]))
+                .collect();
+            let (discovered_output, named_source) =
+                if self.matching_targets(params.host_platform, &output_variant) == targets {
+                    (discovered_output, named_source.clone())
+                } else {
+                    let (_, output_recipe) = self.generate_recipe_for_variant(
+                        params.host_platform,
+                        &output_variant,
+                        PythonParams { editable: false },
+                        params.channels.clone(),
+                    )?;
+                    let output_source = self.recipe_source(&output_recipe)?;
+                    let output_variant_config = VariantConfig {
+                        variants: output_variant,
+                        pin_run_as_build: None,
+                        zip_keys: None,
+                    };
+                    let output = find_output_of_variant(
+                        &discovered_output,
+                        output_variant_config.find_variants(
+                            &find_outputs_from_src(output_source.clone())?,
+                            output_source.clone(),
+                            &selector_config_for_variants,
+                        )?,
+                    )?;
+                    metadata_input_globs.extend(output_recipe.metadata_input_globs);
+                    (output, output_source)
+                };
+
             let variant = discovered_output.used_vars;
             let hash = HashInfo::from_variant(&variant, &discovered_output.noarch_type);
 
@@ -357,6 +496,19 @@ where
 
             let build_number = recipe.build().number;
 
+            // The cache of a multi-output recipe is built in the same environments
+            // as the output, so its requirements are added to those of the output.
+            let (cache_build_requirements, cache_host_requirements) = recipe
+                .cache
+                .as_ref()
+                .map(|cache| {
+                    (
+                        cache.requirements.build.clone(),
+                        cache.requirements.host.clone(),
+                    )
+                })
+                .unwrap_or_default();
+
             let directories = output_directory(
                 if num_of_outputs == 1 {
                     OneOrMultipleOutputs::Single(discovered_output.name.clone())
@@ -383,7 +535,7 @@ where
                 .await
                 .into_diagnostic()?;
 
-            let recipe_yaml = generated_recipe.recipe.to_yaml_pretty().into_diagnostic()?;
+            let recipe_yaml = named_source.code.to_string();
 
             tokio_fs::write(&package_recipe_path, &recipe_yaml)
                 .await
@@ -442,7 +594,12 @@ where
                 },
                 build_dependencies: Some(CondaOutputDependencies {
                     depends: convert_dependencies(
-                        recipe.requirements.build,
+                        recipe
+                            .requirements
+                            .build
+                            .into_iter()
+                            .chain(cache_build_requirements)
+                            .collect(),
                         &variant,
                         &subpackages,
                         &local_source_packages,
@@ -451,7 +608,12 @@ where
                 }),
                 host_dependencies: Some(CondaOutputDependencies {
                     depends: convert_dependencies(
-                        recipe.requirements.host,
+                        recipe
+                            .requirements
+                            .host
+                            .into_iter()
+                            .chain(cache_host_requirements)
+                            .collect(),
                         &variant,
                         &subpackages,
                         &local_source_packages,
@@ -524,7 +686,7 @@ where
 
         Ok(CondaOutputsResult {
             outputs,
-            input_globs: generated_recipe.metadata_input_globs,
+            input_globs: metadata_input_globs,
         })
     }
 
@@ -541,13 +703,6 @@ where
             .as_ref()
             .map_or_else(Platform::current, |prefix| prefix.platform);
 
-        let config = self
-            .target_config
-            .iter()
-            .find(|(selector, _)| selector.matches(host_platform))
-            .map(|(_, target_config)| self.config.merge_with_target_config(target_config))
-            .unwrap_or_else(|| Ok(self.config.clone()))?;
-
         // Construct the variants based on the input parameters. We only
         // have a single variant here so we can just use the variant from the
         // parameters.
@@ -558,29 +713,27 @@ where
             .map(|(k, v)| (k.as_str().into(), vec![Variable::from_string(v)]))
             .collect();
 
-        // Construct the intermediate recipe
-        let mut recipe = self.generate_recipe.generate_recipe(
-            &self.project_model,
-            &config,
-            self.source_dir.clone(),
+        let (config, mut recipe) = self.generate_recipe_for_variant(
             host_platform,
-            Some(PythonParams {
+            &variants,
+            PythonParams {
                 editable: params.editable.unwrap_or_default(),
-            }),
-            &variants.keys().cloned().collect(),
-            params.channels,
+            },
+            params.channels.clone(),
         )?;
 
+        // In reproducible mode the build uses a fixed timestamp instead of the
+        // current time.
+        let timestamp = reproducible_timestamp(config.reproducible(), &self.source_dir)?;
+        if let Some(timestamp) = timestamp {
+            export_source_date_epoch(&mut recipe.recipe, timestamp);
+        }
+
         // Convert the recipe to source code.
         // TODO(baszalmstra): In the future it would be great if we could just
         // immediately use the intermediate recipe for some of this rattler-build
         // functions.
-        let recipe_path = self.source_dir.join(&self.manifest_rel_path);
-        let named_source = Source {
-            name: self.manifest_rel_path.display().to_string(),
-            code: Arc::from(recipe.recipe.to_yaml_pretty().into_diagnostic()?.as_str()),
-            path: recipe_path.clone(),
-        };
+        let named_source = self.recipe_source(&recipe)?;
 
         // Determine the different outputs that are supported by the recipe.
         let selector_config_for_variants = SelectorConfig {
@@ -589,7 +742,7 @@ where
             build_platform,
             hash: None,
             variant: Default::default(),
-            experimental: false,
+            experimental: recipe.recipe.cache.is_some(),
             allow_undefined: false,
             recipe_path: Some(self.source_dir.join(&self.manifest_rel_path)),
         };
@@ -614,9 +767,19 @@ where
             params.work_directory.clone(),
             self.cache_dir.as_deref(),
             params.output_directory.as_deref(),
-            recipe_path,
+            named_source.path.clone(),
         );
 
+        let sandbox_config = config.sandbox().and_then(|sandbox| {
+            sandbox.sandbox_configuration(
+                build_platform,
+                &self.source_dir,
+                &directories.work_dir,
+                &directories.build_prefix,
+                &directories.host_prefix,
+            )
+        });
+
         // Save intermediate recipe and the used variant
         // in the debug dir by hash of the variant
         let variant = discovered_output.used_vars;
@@ -676,45 +839,52 @@ where
             .with_environments_externally_managed(true)
             .finish();
 
+        let finalized_dependencies = from_build_v1_args_to_finalized_dependencies(
+            params.build_prefix,
+            params.host_prefix,
+            params.run_dependencies,
+            params.run_constraints,
+            params.run_exports,
+        );
+
+        // The cache of a multi-output recipe is built in the same, externally
+        // managed, environments as the output itself.
+        let finalized_cache_dependencies = discovered_output
+            .recipe
+            .cache
+            .is_some()
+            .then(|| finalized_dependencies.clone());
+
+        // Use the same virtual packages and channels that the build and host
+        // environments were solved with, so that e.g. run exports depending on
+        // `__glibc` or `__cuda` resolve the same way.
+        let (build_platform, host_platform) =
+            platforms_with_virtual_packages(build_platform, host_platform)?;
+
         let output = Output {
             recipe: discovered_output.recipe,
             build_configuration: BuildConfiguration {
                 target_platform: discovered_output.target_platform,
-                host_platform: PlatformWithVirtualPackages {
-                    platform: host_platform,
-                    virtual_packages: vec![],
-                },
-                build_platform: PlatformWithVirtualPackages {
-                    platform: build_platform,
-                    virtual_packages: vec![],
-                },
+                host_platform,
+                build_platform,
                 hash: discovered_output.hash,
                 variant,
                 directories,
-                channels: vec![],
+                channels: params.channels.clone(),
                 channel_priority: Default::default(),
                 solve_strategy: Default::default(),
-                timestamp: chrono::Utc::now(),
+                timestamp: timestamp.unwrap_or_else(chrono::Utc::now),
                 subpackages: BTreeMap::new(),
-                packaging_settings: PackagingSettings::from_args(
-                    ArchiveType::Conda,
-                    CompressionLevel::default(),
-                ),
+                packaging_settings: config.packaging().packaging_settings()?,
                 store_recipe: false,
                 force_colors: true,
-                sandbox_config: None,
+                sandbox_config,
                 debug: Debug::new(false),
                 exclude_newer: None,
             },
-            finalized_dependencies: Some(from_build_v1_args_to_finalized_dependencies(
-                params.build_prefix,
-                params.host_prefix,
-                params.run_dependencies,
-                params.run_constraints,
-                params.run_exports,
-            )),
+            finalized_dependencies: Some(finalized_dependencies),
             finalized_sources: None,
-            finalized_cache_dependencies: None,
+            finalized_cache_dependencies,
             finalized_cache_sources: None,
             build_summary: Arc::default(),
             system_tools: Default::default(),
@@ -724,9 +894,30 @@ where
         let (output, output_path) =
             run_build(output, &tool_config, WorkingDirectoryBehavior::Preserve).await?;
 
+        // Normalize the metadata of the files in the archive, which
+        // rattler-build takes from the installed files.
+        if let Some(timestamp) = timestamp {
+            normalize_package(
+                &output_path,
+                config.packaging().compression_level()?,
+                timestamp,
+            )?;
+        }
+
+        if config.run_tests() {
+            self.run_package_tests(
+                &output,
+                &output_path,
+                params.channels,
+                &params.work_directory,
+            )
+            .await?;
+        }
+
         // Extract the input globs from the build and recipe
-        let mut input_globs = self.generate_recipe.extract_input_globs_from_build(
+        let mut input_globs = self.generate_recipe.extract_input_globs_from_source(
             &config,
+            &self.source_dir,
             &params.work_directory,
             params.editable.unwrap_or_default(),
         )?;
@@ -743,6 +934,229 @@ where
     }
 }
 
+impl<T: GenerateRecipe> IntermediateBackend<T> {
+    /// Returns the selectors of the target configurations that match the
+    /// platform and variant, from the least to the most specific selector.
+    fn matching_targets(
+        &self,
+        platform: Platform,
+        variant: &SelectorVariant,
+    ) -> Vec<&TargetSelectorV1> {
+        self.target_config
+            .keys()
+            .filter(|selector| selector.matches_variant(platform, variant))
+            .sorted_by_key(|selector| selector.specificity())
+            .collect()
+    }
+
+    /// Generates the recipe for the platform with the configuration that is
+    /// resolved for the variant, and applies the build settings and the
+    /// recipe overlay of that configuration to it.
+    fn generate_recipe_for_variant(
+        &self,
+        platform: Platform,
+        variant: &SelectorVariant,
+        python_params: PythonParams,
+        channels: Vec<ChannelUrl>,
+    ) -> miette::Result<(T::Config, GeneratedRecipe)> {
+        let config = self.resolve_config(platform, variant)?;
+
+        // Construct the intermediate recipe
+        let mut generated_recipe = self.generate_recipe.generate_recipe(
+            &self.project_model,
+            &config,
+            self.source_dir.clone(),
+            platform,
+            Some(python_params),
+            &variant.keys().cloned().collect(),
+            channels,
+        )?;
+        // The run constraints of the target configurations are conditional, so
+        // they are not taken from the configuration that is resolved for the
+        // platform.
+        let mut build_settings = config.build_settings();
+        build_settings.run_constraints = &[];
+        build_settings.apply(&mut generated_recipe.recipe)?;
+        generated_recipe
+            .recipe
+            .requirements
+            .run_constraints
+            .extend(self.run_constraints()?);
+        self.apply_recipe_overlay(&config, &mut generated_recipe)?;
+
+        // rattler-build doesn't know the commit that is built, so it is added
+        // to the context if the build string refers to it. Another commit
+        // changes the build string, so the git files are inputs of the metadata.
+        let recipe = &mut generated_recipe.recipe;
+        let uses_git_commit = matches!(
+            &recipe.build.string,
+            Some(Value::Template(template)) if template.contains("git_commit")
+        );
+        if uses_git_commit && !recipe.context.contains_key("git_commit") {
+            let commit = head_commit(&self.source_dir)
+                .into_diagnostic()?
+                .ok_or_else(|| {
+                    miette::miette!(
+                        help = "`git_commit` can only be used if the source directory is part of a git repository with at least one commit",
+                        "the build string refers to `git_commit`, but the commit of {} could not be determined",
+                        self.source_dir.display()
+                    )
+                })?;
+            recipe
+                .context
+                .insert("git_commit".to_string(), Value::Concrete(commit));
+            generated_recipe
+                .metadata_input_globs
+                .extend(head_commit_input_globs(&self.source_dir));
+        }
+
+        Ok((config, generated_recipe))
+    }
+
+    /// Returns the run constraints of the configuration, the ones of a target
+    /// configuration are wrapped in a condition on its selector.
+    fn run_constraints(&self) -> miette::Result<ConditionalList<PackageDependency>> {
+        let targets = self
+            .target_config
+            .iter()
+            .sorted_by_key(|(selector, _)| selector.specificity())
+            .map(|(selector, target)| (selector, target.config.build_settings().run_constraints))
+            .filter(|(_, run_constraints)| !run_constraints.is_empty())
+            .collect_vec();
+        run_constraints_to_conditional_list(self.config.build_settings().run_constraints, &targets)
+    }
+
+    /// Converts the generated recipe to the source code that rattler-build
+    /// parses.
+    fn recipe_source(&self, generated_recipe: &GeneratedRecipe) -> miette::Result<Source> {
+        Ok(Source {
+            name: self.manifest_rel_path.display().to_string(),
+            code: Arc::from(
+                generated_recipe
+                    .recipe
+                    .to_yaml_pretty()
+                    .into_diagnostic()?
+                    .as_str(),
+            ),
+            path: self.source_dir.join(&self.manifest_rel_path),
+        })
+    }
+
+    /// Returns the configuration for the platform and variant: the base
+    /// configuration merged with every matching target configuration, from
+    /// the least to the most specific selector.
+    fn resolve_config(
+        &self,
+        platform: Platform,
+        variant: &SelectorVariant,
+    ) -> miette::Result<T::Config> {
+        self.matching_targets(platform, variant)
+            .into_iter()
+            .try_fold(self.config.clone(), |config, selector| {
+                config.merge_with_target_config(&self.target_config[selector].config)
+            })
+    }
+
+    /// Deep-merges the recipe overlay of the configuration, if any, into the
+    /// generated recipe. The overlay is added to the input globs so that
+    /// changing it invalidates the metadata and the build.
+    fn apply_recipe_overlay(
+        &self,
+        config: &T::Config,
+        generated_recipe: &mut GeneratedRecipe,
+    ) -> miette::Result<()> {
+        let Some(overlay) = config.recipe_overlay() else {
+            return Ok(());
+        };
+
+        let overlay_path = self.source_dir.join(overlay);
+        let overlay_source = fs_err::read_to_string(&overlay_path).into_diagnostic()?;
+        generated_recipe.recipe = apply_overlay(&generated_recipe.recipe, &overlay_source)
+            .with_context(|| {
+                format!(
+                    "failed to apply the recipe overlay '{}'",
+                    overlay_path.display()
+                )
+            })?;
+
+        let overlay_glob = overlay_input_glob(&self.source_dir, overlay)?;
+        generated_recipe
+            .metadata_input_globs
+            .insert(overlay_glob.clone());
+        generated_recipe.build_input_globs.insert(overlay_glob);
+
+        Ok(())
+    }
+}
+
+/// Returns the input glob of the recipe overlay at `overlay`, relative to the
+/// source directory like all input globs.
+fn overlay_input_glob(source_dir: &Path, overlay: &Path) -> miette::Result<String> {
+    if !overlay.is_absolute() {
+        return Ok(overlay.display().to_string());
+    }
+    let relative = overlay.strip_prefix(source_dir).map_err(|_| {
+        miette::miette!(
+            "the recipe overlay '{}' is not located in the source directory '{}'",
+            overlay.display(),
+            source_dir.display()
+        )
+    })?;
+    Ok(relative.display().to_string())
+}
+
+impl<T: GenerateRecipe> IntermediateBackend<T> {
+    /// Runs the tests of the recipe against the built `package`.
+    ///
+    /// The package contents tests only inspect the package itself. The other
+    /// tests need an environment with the package and its test dependencies,
+    /// which is solved from `channels` and installed into a test prefix in the
+    /// work directory, because the externally managed build and host prefixes
+    /// don't contain the package. Those prefixes are not reused, the test
+    /// runner of rattler-build always solves its own environment, even if the
+    /// test dependencies are already installed in them.
+    async fn run_package_tests(
+        &self,
+        output: &Output,
+        package: &Path,
+        channels: Vec<ChannelUrl>,
+        work_directory: &Path,
+    ) -> Result<(), PackageTestError> {
+        let tool_config = Configuration::builder()
+            .with_opt_cache_dir(self.cache_dir.clone())
+            .with_logging_output_handler(self.logging_output_handler.clone())
+            .finish();
+
+        let test_config = TestConfiguration {
+            test_prefix: work_directory.join("test"),
+            target_platform: Some(*output.target_platform()),
+            host_platform: Some(output.build_configuration.host_platform.clone()),
+            current_platform: output.build_configuration.build_platform.clone(),
+            keep_test_prefix: false,
+            test_index: None,
+            channels,
+            channel_priority: Default::default(),
+            solve_strategy: Default::default(),
+            tool_configuration: tool_config,
+            output_dir: work_directory.to_path_buf(),
+            debug: Debug::new(false),
+            exclude_newer: None,
+        };
+
+        run_test(package, &test_config, None)
+            .await
+            .map_err(|source| PackageTestError {
+                package: format!(
+                    "{}-{}-{}",
+                    output.name().as_normalized(),
+                    output.version(),
+                    output.build_string()
+                ),
+                source,
+            })
+    }
+}
+
 pub fn find_matching_output(
     expected_output: &CondaBuildV1Output,
     discovered_outputs: IndexSet<DiscoveredOutput>,
@@ -778,6 +1192,31 @@ pub fn find_matching_output(
     Ok(discovered_output)
 }
 
+/// Returns the output with the name of `discovered_output` from the outputs
+/// of the recipe that is generated for its variant. The target configurations
+/// of the variant must not remove the output, otherwise `conda/outputs` would
+/// silently omit a package.
+fn find_output_of_variant(
+    discovered_output: &DiscoveredOutput,
+    variant_outputs: IndexSet<DiscoveredOutput>,
+) -> miette::Result<DiscoveredOutput> {
+    variant_outputs
+        .into_iter()
+        .find(|output| output.name == discovered_output.name)
+        .ok_or_else(|| {
+            let variant = discovered_output
+                .used_vars
+                .iter()
+                .map(|(key, value)| format!("{}={value}", key.0))
+                .join(", ");
+            miette::miette!(
+                help = "the target configurations of the variant must not rename or remove it",
+                "the output '{}' is missing from the recipe for its variant {variant}",
+                discovered_output.name,
+            )
+        })
+}
+
 pub fn conda_build_v1_directories(
     host_prefix: Option<&Path>,
     build_prefix: Option<&Path>,
@@ -819,3 +1258,72 @@ fn default_capabilities() -> BackendCapabilities {
         ),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn target_config(values: serde_json::Value) -> TargetConfig<()> {
+        TargetConfig {
+            config: (),
+            values: values.as_object().cloned().unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_overlay_input_glob() {
+        let source_dir = tempfile::tempdir().unwrap();
+        let other_dir = tempfile::tempdir().unwrap();
+        let overlay = Path::new("recipe").join("overlay.yaml");
+
+        assert_eq!(
+            overlay_input_glob(source_dir.path(), &overlay).unwrap(),
+            overlay.display().to_string()
+        );
+        assert_eq!(
+            overlay_input_glob(source_dir.path(), &source_dir.path().join(&overlay)).unwrap(),
+            overlay.display().to_string()
+        );
+        assert!(overlay_input_glob(source_dir.path(), &other_dir.path().join(&overlay)).is_err());
+    }
+
+    #[test]
+    fn test_conflicting_keys() {
+        let unix = TargetSelectorV1::Unix;
+        let glob = TargetSelectorV1::Platform("linux-*".to_string());
+        let cuda = TargetSelectorV1::Platform("cuda".to_string());
+        let no_cuda = TargetSelectorV1::Platform("not cuda".to_string());
+        let linux = TargetSelectorV1::Platform("linux-64".to_string());
+        let osx = TargetSelectorV1::Platform("osx-64".to_string());
+
+        let unix_config = target_config(json!({ "build-number": 0, "env": { "A": "1" } }));
+        let glob_config = target_config(json!({ "build-number": 1, "env": { "A": "1" } }));
+        let cuda_config = target_config(json!({ "build-number": 2, "env": { "B": "1" } }));
+        let no_cuda_config = target_config(json!({ "build-number": 1, "env": { "A": "2" } }));
+        let linux_config = target_config(json!({ "build-number": 3 }));
+        let osx_config = target_config(json!({ "build-number": 4 }));
+
+        let targets = [
+            (&unix, &unix_config),
+            (&glob, &glob_config),
+            (&cuda, &cuda_config),
+            (&no_cuda, &no_cuda_config),
+            (&linux, &linux_config),
+            (&osx, &osx_config),
+        ];
+
+        // Only equally specific selectors that can apply together conflict, a
+        // more specific selector simply takes precedence. Different keys of
+        // `env` are merged, `linux-64` and `osx-64` or `cuda` and `not cuda`
+        // never apply together.
+        assert_eq!(
+            conflicting_keys(&targets),
+            vec![
+                ("build-number".to_string(), &glob, &cuda),
+                ("env.A".to_string(), &glob, &no_cuda),
+            ]
+        );
+    }
+}
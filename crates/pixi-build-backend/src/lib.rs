@@ -11,6 +11,10 @@ pub mod common;
 pub mod compilers;
 pub mod dependencies;
 mod encoded_source_spec_url;
+pub mod git_metadata;
+pub mod input_globs;
+pub mod reproducible;
+pub mod selector;
 pub mod source;
 pub mod tools;
 pub mod traits;
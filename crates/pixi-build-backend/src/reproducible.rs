@@ -0,0 +1,393 @@
+//! Support for reproducible builds.
+//!
+//! A build is reproducible if building the same sources twice results in
+//! byte-identical packages. Reproducible mode fixes the time of the build,
+//! which rattler-build writes to the `index.json` of the package. The
+//! timestamp is taken from the `SOURCE_DATE_EPOCH` environment variable, or
+//! the time of the last git commit of the sources, instead of the current
+//! time.
+//!
+//! After the build the package archive is normalized by
+//! [`normalize_package`]: the files are packed again in sorted order, with
+//! the timestamp as their modification time, permissions that only keep
+//! whether a file is executable and without their owners. The build itself
+//! still has to be deterministic with respect to the contents of the
+//! installed files.
+//!
+//! See <https://reproducible-builds.org/docs/source-date-epoch/>.
+
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use chrono::{DateTime, Utc};
+use miette::{Context, IntoDiagnostic};
+use rattler_conda_types::{compression_level::CompressionLevel, package::ArchiveType};
+use rattler_package_streaming::write::{write_conda_package, write_tar_bz2_package};
+use recipe_stage0::recipe::IntermediateRecipe;
+
+use crate::git_metadata::last_commit_timestamp;
+
+/// The environment variable that holds the timestamp of a reproducible build
+/// as seconds since the Unix epoch.
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Determines the timestamp of a reproducible build of the sources in
+/// `source_dir`, or `None` if the build is not reproducible.
+///
+/// Reproducible mode is enabled by `reproducible` or, if that is not
+/// configured, by setting `SOURCE_DATE_EPOCH`. The timestamp is read from
+/// `SOURCE_DATE_EPOCH`, the last git commit or, if neither is available, the
+/// Unix epoch.
+pub fn reproducible_timestamp(
+    reproducible: Option<bool>,
+    source_dir: &Path,
+) -> miette::Result<Option<DateTime<Utc>>> {
+    timestamp_of_build(
+        reproducible,
+        std::env::var(SOURCE_DATE_EPOCH).ok(),
+        source_dir,
+    )
+}
+
+/// Determines the timestamp of a reproducible build, see
+/// [`reproducible_timestamp`], with the given value of `SOURCE_DATE_EPOCH`.
+fn timestamp_of_build(
+    reproducible: Option<bool>,
+    source_date_epoch: Option<String>,
+    source_dir: &Path,
+) -> miette::Result<Option<DateTime<Utc>>> {
+    if !reproducible.unwrap_or(source_date_epoch.is_some()) {
+        return Ok(None);
+    }
+
+    if let Some(source_date_epoch) = source_date_epoch {
+        return parse_source_date_epoch(&source_date_epoch).map(Some);
+    }
+
+    let seconds = last_commit_timestamp(source_dir).unwrap_or_else(|| {
+        tracing::warn!(
+            "{SOURCE_DATE_EPOCH} is not set and {} is not part of a git repository, using the Unix epoch as the timestamp of the build",
+            source_dir.display()
+        );
+        0
+    });
+    DateTime::from_timestamp(seconds, 0)
+        .map(Some)
+        .ok_or_else(|| miette::miette!("the timestamp of the last git commit is out of range"))
+}
+
+/// Parses the value of `SOURCE_DATE_EPOCH`.
+fn parse_source_date_epoch(value: &str) -> miette::Result<DateTime<Utc>> {
+    let seconds = value
+        .trim()
+        .parse::<i64>()
+        .into_diagnostic()
+        .with_context(|| format!("{SOURCE_DATE_EPOCH} '{value}' is not a number of seconds"))?;
+    DateTime::from_timestamp(seconds, 0)
+        .ok_or_else(|| miette::miette!("{SOURCE_DATE_EPOCH} '{value}' is out of range"))
+}
+
+/// Exports `SOURCE_DATE_EPOCH` to all the build scripts of the recipe, so that
+/// the build tools embed the same timestamp as the package.
+pub fn export_source_date_epoch(recipe: &mut IntermediateRecipe, timestamp: DateTime<Utc>) {
+    let value = timestamp.timestamp().to_string();
+    let builds = std::iter::once(&mut recipe.build)
+        .chain(recipe.cache.iter_mut().map(|cache| &mut cache.build))
+        .chain(
+            recipe
+                .outputs
+                .iter_mut()
+                .filter_map(|output| output.build.as_mut()),
+        );
+    for build in builds {
+        build
+            .script
+            .env
+            .insert(SOURCE_DATE_EPOCH.to_string(), value.clone());
+    }
+}
+
+/// Normalizes the metadata of the files in the package archive at `package`
+/// and writes the archive again in place.
+///
+/// The package is extracted, the modification time of every file is set to
+/// `timestamp` and, on Unix, the permissions of files are reset to `0o755`
+/// if they are executable and `0o644` otherwise. The files are then packed in
+/// sorted order with `timestamp` as modification time, and the packaging
+/// library does not store the owners of the files.
+pub fn normalize_package(
+    package: &Path,
+    compression_level: CompressionLevel,
+    timestamp: DateTime<Utc>,
+) -> miette::Result<()> {
+    let file_name = package
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| miette::miette!("invalid package path '{}'", package.display()))?;
+    let (stem, archive_type) = ArchiveType::split_str(file_name)
+        .ok_or_else(|| miette::miette!("'{file_name}' is not a conda package"))?;
+
+    let extracted = tempfile::tempdir().into_diagnostic()?;
+    rattler_package_streaming::fs::extract(package, extracted.path())
+        .into_diagnostic()
+        .with_context(|| format!("failed to extract '{}'", package.display()))?;
+
+    let mut paths = Vec::new();
+    collect_files(extracted.path(), &mut paths)?;
+    paths.sort();
+    for path in &paths {
+        normalize_file(path, timestamp.into())?;
+    }
+
+    let parent = package.parent().unwrap_or(Path::new("."));
+    let mut normalized = tempfile::NamedTempFile::new_in(parent).into_diagnostic()?;
+    match archive_type {
+        ArchiveType::TarBz2 => write_tar_bz2_package(
+            normalized.as_file_mut(),
+            extracted.path(),
+            &paths,
+            compression_level,
+            Some(&timestamp),
+            None,
+        ),
+        ArchiveType::Conda => write_conda_package(
+            normalized.as_file_mut(),
+            extracted.path(),
+            &paths,
+            compression_level,
+            None,
+            stem,
+            Some(&timestamp),
+            None,
+        ),
+    }
+    .into_diagnostic()
+    .with_context(|| format!("failed to write '{}'", package.display()))?;
+
+    normalized
+        .persist(package)
+        .into_diagnostic()
+        .with_context(|| format!("failed to replace '{}'", package.display()))?;
+    Ok(())
+}
+
+/// Collects the files and symlinks below `dir`, which are the entries of a
+/// package archive.
+fn collect_files(dir: &Path, paths: &mut Vec<PathBuf>) -> miette::Result<()> {
+    for entry in fs_err::read_dir(dir).into_diagnostic()? {
+        let entry = entry.into_diagnostic()?;
+        let path = entry.path();
+        if entry.file_type().into_diagnostic()?.is_dir() {
+            collect_files(&path, paths)?;
+        } else {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Sets the modification time and, on Unix, the permissions of a file.
+/// Symlinks are left alone, their metadata is not part of the archive.
+fn normalize_file(path: &Path, modified: SystemTime) -> miette::Result<()> {
+    let metadata = fs_err::symlink_metadata(path).into_diagnostic()?;
+    if metadata.is_symlink() {
+        return Ok(());
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if metadata.permissions().mode() & 0o111 != 0 {
+            0o755
+        } else {
+            0o644
+        };
+        fs_err::set_permissions(path, std::fs::Permissions::from_mode(mode)).into_diagnostic()?;
+    }
+
+    fs_err::OpenOptions::new()
+        .write(true)
+        .open(path)
+        .into_diagnostic()?
+        .file()
+        .set_modified(modified)
+        .into_diagnostic()
+        .with_context(|| {
+            format!(
+                "failed to set the modification time of '{}'",
+                path.display()
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use recipe_stage0::recipe::Cache;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_source_date_epoch() {
+        assert_eq!(
+            parse_source_date_epoch("1700000000\n").unwrap(),
+            DateTime::from_timestamp(1_700_000_000, 0).unwrap()
+        );
+        assert!(parse_source_date_epoch("yesterday").is_err());
+    }
+
+    #[test]
+    fn test_reproducible_disabled() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        assert_eq!(
+            reproducible_timestamp(Some(false), temp_dir.path()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_source_date_epoch() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        // Setting `SOURCE_DATE_EPOCH` enables reproducible mode
+        assert_eq!(
+            timestamp_of_build(None, Some("1700000000".to_string()), temp_dir.path()).unwrap(),
+            Some(timestamp)
+        );
+        assert_eq!(
+            timestamp_of_build(Some(true), Some("1700000000".to_string()), temp_dir.path())
+                .unwrap(),
+            Some(timestamp)
+        );
+        // unless it is disabled explicitly
+        assert_eq!(
+            timestamp_of_build(Some(false), Some("1700000000".to_string()), temp_dir.path())
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            timestamp_of_build(None, None, temp_dir.path()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_timestamp_of_last_commit() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .args(args)
+                .current_dir(temp_dir.path())
+                .env("GIT_AUTHOR_NAME", "pixi")
+                .env("GIT_AUTHOR_EMAIL", "pixi@prefix.dev")
+                .env("GIT_COMMITTER_NAME", "pixi")
+                .env("GIT_COMMITTER_EMAIL", "pixi@prefix.dev")
+                .env("GIT_COMMITTER_DATE", "1600000000 +0000")
+                .status()
+                .unwrap();
+            assert!(status.success(), "git {args:?} failed");
+        };
+
+        // Outside of a git repository the Unix epoch is used
+        assert_eq!(
+            timestamp_of_build(Some(true), None, temp_dir.path()).unwrap(),
+            DateTime::from_timestamp(0, 0)
+        );
+
+        git(&["init", "--quiet"]);
+        fs_err::write(temp_dir.path().join("README.md"), "hello").unwrap();
+        git(&["add", "README.md"]);
+        git(&["commit", "--quiet", "--no-gpg-sign", "-m", "initial commit"]);
+
+        assert_eq!(
+            timestamp_of_build(Some(true), None, temp_dir.path()).unwrap(),
+            DateTime::from_timestamp(1_600_000_000, 0)
+        );
+    }
+
+    #[test]
+    fn test_export_source_date_epoch() {
+        let mut recipe = IntermediateRecipe {
+            cache: Some(Cache::default()),
+            ..Default::default()
+        };
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+        export_source_date_epoch(&mut recipe, timestamp);
+
+        assert_eq!(recipe.build.script.env[SOURCE_DATE_EPOCH], "1700000000");
+        assert_eq!(
+            recipe.cache.unwrap().build.script.env[SOURCE_DATE_EPOCH],
+            "1700000000"
+        );
+    }
+
+    #[test]
+    fn test_normalize_package() {
+        let timestamp = DateTime::from_timestamp(1_700_000_000, 0).unwrap();
+
+        // Packs the same files, but creates them in the given order with
+        // the given permissions and modification time
+        let package = |dir: &Path, order: &[&str], mode: u32, modified: SystemTime| {
+            let files = dir.join("files");
+            fs_err::create_dir_all(files.join("info")).unwrap();
+            fs_err::create_dir_all(files.join("bin")).unwrap();
+            for name in order {
+                let path = files.join(name);
+                fs_err::write(&path, format!("contents of {name}")).unwrap();
+                fs_err::OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .unwrap()
+                    .file()
+                    .set_modified(modified)
+                    .unwrap();
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    fs_err::set_permissions(&path, std::fs::Permissions::from_mode(mode)).unwrap();
+                }
+                #[cfg(not(unix))]
+                let _ = mode;
+            }
+            let mut paths = order
+                .iter()
+                .map(|name| files.join(name))
+                .collect::<Vec<_>>();
+            paths.reverse();
+
+            let path = dir.join("foo-1.0-0.conda");
+            write_conda_package(
+                fs_err::File::create(&path).unwrap(),
+                &files,
+                &paths,
+                CompressionLevel::Default,
+                None,
+                "foo-1.0-0",
+                None,
+                None,
+            )
+            .unwrap();
+            normalize_package(&path, CompressionLevel::Default, timestamp).unwrap();
+            fs_err::read(&path).unwrap()
+        };
+
+        let first = tempfile::tempdir().unwrap();
+        let second = tempfile::tempdir().unwrap();
+        let first = package(
+            first.path(),
+            &["bin/a", "info/index.json", "bin/b"],
+            0o600,
+            SystemTime::UNIX_EPOCH,
+        );
+        let second = package(
+            second.path(),
+            &["info/index.json", "bin/b", "bin/a"],
+            0o664,
+            SystemTime::now(),
+        );
+
+        assert!(first == second, "the normalized packages differ");
+    }
+}
@@ -0,0 +1,752 @@
+//! Target selector expressions.
+//!
+//! Besides a single platform (`linux-64`) or platform family (`unix`), a
+//! target selector can be an expression that combines platforms,
+//! architectures and variants:
+//!
+//! * `linux-*` matches all platforms that start with `linux-`.
+//! * `x86_64` matches all platforms with that architecture.
+//! * `cuda` matches if the variant contains the key `cuda`.
+//! * `python >= 3.12` matches if the `python` variant matches the version
+//!   spec, `blas_impl == 'mkl'` compares the variant as a string.
+//! * `and`, `or`, `not` and parentheses combine expressions, e.g.
+//!   `unix and not osx` or `win and x86_64`.
+
+use std::{collections::BTreeMap, fmt::Display, str::FromStr};
+
+use itertools::Itertools;
+use pixi_build_types::TargetSelectorV1;
+use rattler_build::{NormalizedKey, recipe::variable::Variable};
+use rattler_conda_types::{Arch, ParseStrictness, Platform, Version, VersionSpec};
+
+use crate::specs_conversion::PlatformKind;
+
+/// The variant a selector is evaluated against. A key can have several
+/// values before the variants are expanded, in that case a selector on the
+/// key matches if any of the values matches.
+pub type SelectorVariant = BTreeMap<NormalizedKey, Vec<Variable>>;
+
+/// The maximum number of variant conditions in a selector. Checking whether
+/// two selectors overlap tries every combination of their conditions.
+const MAX_VARIANT_CONDITIONS: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid target selector '{selector}': {reason}")]
+pub struct SelectorParseError {
+    selector: String,
+    reason: String,
+}
+
+/// A family of platforms.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlatformFamily {
+    Unix,
+    Linux,
+    Win,
+    Osx,
+}
+
+impl PlatformFamily {
+    fn from_keyword(keyword: &str) -> Option<Self> {
+        match keyword {
+            "unix" => Some(Self::Unix),
+            "linux" => Some(Self::Linux),
+            "win" | "windows" => Some(Self::Win),
+            "osx" | "macos" => Some(Self::Osx),
+            _ => None,
+        }
+    }
+
+    fn matches(self, platform: Platform) -> bool {
+        match self {
+            Self::Unix => platform.is_unix(),
+            Self::Linux => platform.is_linux(),
+            Self::Win => platform.is_windows(),
+            Self::Osx => platform.is_osx(),
+        }
+    }
+}
+
+impl Display for PlatformFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unix => write!(f, "unix"),
+            Self::Linux => write!(f, "linux"),
+            Self::Win => write!(f, "win"),
+            Self::Osx => write!(f, "osx"),
+        }
+    }
+}
+
+/// A comparison of a variant value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComparisonOperator {
+    Equal,
+    NotEqual,
+    GreaterEqual,
+    Greater,
+    LessEqual,
+    Less,
+}
+
+impl ComparisonOperator {
+    fn from_token(token: &str) -> Option<Self> {
+        match token {
+            "==" => Some(Self::Equal),
+            "!=" => Some(Self::NotEqual),
+            ">=" => Some(Self::GreaterEqual),
+            ">" => Some(Self::Greater),
+            "<=" => Some(Self::LessEqual),
+            "<" => Some(Self::Less),
+            _ => None,
+        }
+    }
+}
+
+impl Display for ComparisonOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Equal => write!(f, "=="),
+            Self::NotEqual => write!(f, "!="),
+            Self::GreaterEqual => write!(f, ">="),
+            Self::Greater => write!(f, ">"),
+            Self::LessEqual => write!(f, "<="),
+            Self::Less => write!(f, "<"),
+        }
+    }
+}
+
+/// A parsed target selector.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SelectorExpression {
+    /// A single platform, e.g. `linux-64`.
+    Platform(Platform),
+    /// A platform family, e.g. `unix`.
+    Family(PlatformFamily),
+    /// A platform pattern with `*` wildcards, e.g. `linux-*`.
+    PlatformGlob(String),
+    /// An architecture, e.g. `x86_64`.
+    Arch(Arch),
+    /// The variant contains the key, e.g. `cuda`.
+    Variant(NormalizedKey),
+    /// A comparison of a variant value, e.g. `python >= 3.12`.
+    Comparison {
+        key: NormalizedKey,
+        operator: ComparisonOperator,
+        value: String,
+    },
+    Not(Box<SelectorExpression>),
+    And(Box<SelectorExpression>, Box<SelectorExpression>),
+    Or(Box<SelectorExpression>, Box<SelectorExpression>),
+}
+
+impl FromStr for SelectorExpression {
+    type Err = SelectorParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |reason: String| SelectorParseError {
+            selector: s.to_string(),
+            reason,
+        };
+        let tokens = tokenize(s).map_err(error)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let expression = parser.parse_or().map_err(error)?;
+        if let Some(token) = parser.peek() {
+            return Err(error(format!("unexpected '{token}'")));
+        }
+        if expression.variant_conditions().len() > MAX_VARIANT_CONDITIONS {
+            return Err(error(format!(
+                "a selector can contain at most {MAX_VARIANT_CONDITIONS} variant conditions"
+            )));
+        }
+        Ok(expression)
+    }
+}
+
+impl TryFrom<&TargetSelectorV1> for SelectorExpression {
+    type Error = SelectorParseError;
+
+    fn try_from(selector: &TargetSelectorV1) -> Result<Self, Self::Error> {
+        match selector {
+            TargetSelectorV1::Unix => Ok(Self::Family(PlatformFamily::Unix)),
+            TargetSelectorV1::Linux => Ok(Self::Family(PlatformFamily::Linux)),
+            TargetSelectorV1::Win => Ok(Self::Family(PlatformFamily::Win)),
+            TargetSelectorV1::MacOs => Ok(Self::Family(PlatformFamily::Osx)),
+            TargetSelectorV1::Platform(selector) => selector.parse(),
+        }
+    }
+}
+
+impl SelectorExpression {
+    /// Returns true if the selector matches the platform and variant.
+    pub fn matches(&self, platform: Platform, variant: &SelectorVariant) -> bool {
+        self.evaluate(platform, &|condition| {
+            Some(match condition {
+                Self::Variant(key) => variant.contains_key(key),
+                Self::Comparison {
+                    key,
+                    operator,
+                    value,
+                } => variant.get(key).is_some_and(|values| {
+                    values
+                        .iter()
+                        .any(|variable| compare(&variable.to_string(), *operator, value))
+                }),
+                _ => unreachable!("only variant conditions are evaluated by the callback"),
+            })
+        })
+        .unwrap_or(false)
+    }
+
+    /// Returns true if there is a platform and variant that both selectors
+    /// match. The variant conditions are treated as independent of each
+    /// other, so `python >= 3.12` and `python < 3.10` are considered to
+    /// overlap while `cuda` and `not cuda` are not.
+    ///
+    /// The conditions are assigned one at a time, and an assignment is
+    /// abandoned as soon as one of the selectors can no longer match, so
+    /// most pairs are decided without trying every combination. Selectors
+    /// with more conditions than a parsed selector can have are assumed to
+    /// overlap, which bounds the search.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        let mut conditions = self.variant_conditions();
+        for condition in other.variant_conditions() {
+            if !conditions.contains(&condition) {
+                conditions.push(condition);
+            }
+        }
+        if conditions.len() > 2 * MAX_VARIANT_CONDITIONS {
+            return true;
+        }
+
+        Platform::all()
+            .any(|platform| self.both_match(other, platform, &conditions, &mut Vec::new()))
+    }
+
+    /// Returns true if the variant conditions that are not yet `assigned` can
+    /// be assigned such that both selectors match the platform. The values of
+    /// `assigned` belong to the first conditions of `conditions`.
+    fn both_match(
+        &self,
+        other: &Self,
+        platform: Platform,
+        conditions: &[Self],
+        assigned: &mut Vec<bool>,
+    ) -> bool {
+        let holds = |condition: &Self| {
+            let index = conditions.iter().position(|c| c == condition)?;
+            assigned.get(index).copied()
+        };
+        match (
+            self.evaluate(platform, &holds),
+            other.evaluate(platform, &holds),
+        ) {
+            (Some(false), _) | (_, Some(false)) => false,
+            (Some(true), Some(true)) => true,
+            _ => [true, false].into_iter().any(|value| {
+                assigned.push(value);
+                let matches = self.both_match(other, platform, conditions, assigned);
+                assigned.pop();
+                matches
+            }),
+        }
+    }
+
+    /// Evaluates the selector for the platform, the variant conditions are
+    /// decided by `variant_condition`. Returns `None` if the result depends
+    /// on a condition that `variant_condition` leaves undecided.
+    fn evaluate(
+        &self,
+        platform: Platform,
+        variant_condition: &dyn Fn(&Self) -> Option<bool>,
+    ) -> Option<bool> {
+        match self {
+            Self::Platform(p) => Some(*p == platform),
+            Self::Family(family) => Some(family.matches(platform)),
+            Self::PlatformGlob(pattern) => Some(glob_matches(pattern, platform.as_str())),
+            Self::Arch(arch) => Some(platform.arch() == Some(*arch)),
+            Self::Variant(_) | Self::Comparison { .. } => variant_condition(self),
+            Self::Not(expression) => expression
+                .evaluate(platform, variant_condition)
+                .map(|value| !value),
+            Self::And(lhs, rhs) => match (
+                lhs.evaluate(platform, variant_condition),
+                rhs.evaluate(platform, variant_condition),
+            ) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (Some(true), Some(true)) => Some(true),
+                _ => None,
+            },
+            Self::Or(lhs, rhs) => match (
+                lhs.evaluate(platform, variant_condition),
+                rhs.evaluate(platform, variant_condition),
+            ) {
+                (Some(true), _) | (_, Some(true)) => Some(true),
+                (Some(false), Some(false)) => Some(false),
+                _ => None,
+            },
+        }
+    }
+
+    /// Returns the variant conditions of the selector, e.g. `cuda` or
+    /// `python >= 3.12`.
+    fn variant_conditions(&self) -> Vec<Self> {
+        match self {
+            Self::Variant(_) | Self::Comparison { .. } => vec![self.clone()],
+            Self::Not(expression) => expression.variant_conditions(),
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                let mut conditions = lhs.variant_conditions();
+                conditions.extend(rhs.variant_conditions());
+                conditions
+            }
+            Self::Platform(_) | Self::Family(_) | Self::PlatformGlob(_) | Self::Arch(_) => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns the keys of the variants that the selector refers to.
+    pub fn variant_keys(&self) -> Vec<NormalizedKey> {
+        match self {
+            Self::Variant(key) | Self::Comparison { key, .. } => vec![key.clone()],
+            Self::Not(expression) => expression.variant_keys(),
+            Self::And(lhs, rhs) | Self::Or(lhs, rhs) => {
+                let mut keys = lhs.variant_keys();
+                keys.extend(rhs.variant_keys());
+                keys
+            }
+            Self::Platform(_) | Self::Family(_) | Self::PlatformGlob(_) | Self::Arch(_) => {
+                Vec::new()
+            }
+        }
+    }
+
+    /// Returns how specific the selector is. Targets are merged from the
+    /// least to the most specific selector: `unix`, then the other platform
+    /// families, then expressions and finally single platforms.
+    pub fn specificity(&self) -> u8 {
+        match self {
+            Self::Family(PlatformFamily::Unix) => 0,
+            Self::Family(_) => 1,
+            Self::Platform(_) => 3,
+            _ => 2,
+        }
+    }
+
+    /// Converts the selector to a rattler-build `if` condition on the
+    /// platform of the given kind.
+    pub fn to_rattler_build_condition(&self, platform_kind: &PlatformKind) -> String {
+        self.condition(platform_kind, true)
+    }
+
+    fn condition(&self, platform_kind: &PlatformKind, top_level: bool) -> String {
+        let condition = match self {
+            Self::Platform(platform) => return format!("{platform_kind}_platform == '{platform}'"),
+            Self::Family(family) => return family.to_string(),
+            Self::PlatformGlob(pattern) => platforms_condition(platform_kind, |platform| {
+                glob_matches(pattern, platform.as_str())
+            }),
+            Self::Arch(arch) => {
+                platforms_condition(platform_kind, |platform| platform.arch() == Some(*arch))
+            }
+            Self::Variant(key) => format!("{} is defined", key.normalize()),
+            Self::Comparison {
+                key,
+                operator,
+                value,
+            } => {
+                let key = key.normalize();
+                match operator {
+                    ComparisonOperator::Equal | ComparisonOperator::NotEqual => {
+                        format!("{key} is defined and {key} {operator} '{value}'")
+                    }
+                    _ => format!("{key} is defined and match({key}, '{operator}{value}')"),
+                }
+            }
+            Self::Not(expression) => format!("not {}", expression.condition(platform_kind, false)),
+            Self::And(lhs, rhs) => format!(
+                "{} and {}",
+                lhs.condition(platform_kind, false),
+                rhs.condition(platform_kind, false)
+            ),
+            Self::Or(lhs, rhs) => format!(
+                "{} or {}",
+                lhs.condition(platform_kind, false),
+                rhs.condition(platform_kind, false)
+            ),
+        };
+        if top_level {
+            condition
+        } else {
+            format!("({condition})")
+        }
+    }
+}
+
+/// A condition that matches any of the known platforms for which `filter`
+/// returns true.
+fn platforms_condition(platform_kind: &PlatformKind, filter: impl Fn(Platform) -> bool) -> String {
+    let condition = Platform::all()
+        .filter(|platform| filter(*platform))
+        .map(|platform| format!("{platform_kind}_platform == '{platform}'"))
+        .join(" or ");
+    if condition.is_empty() {
+        "false".to_string()
+    } else {
+        condition
+    }
+}
+
+/// Matches `value` against a pattern in which `*` matches any sequence of
+/// characters.
+fn glob_matches(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.collect_vec();
+    let Some(last) = parts.pop() else {
+        // The pattern contains no wildcard.
+        return rest.is_empty();
+    };
+    for part in parts {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+/// Compares a variant value. Equality compares the values as strings, the
+/// other operators compare them as versions.
+fn compare(variant_value: &str, operator: ComparisonOperator, value: &str) -> bool {
+    match operator {
+        ComparisonOperator::Equal => variant_value == value,
+        ComparisonOperator::NotEqual => variant_value != value,
+        _ => {
+            let Ok(spec) =
+                VersionSpec::from_str(&format!("{operator}{value}"), ParseStrictness::Lenient)
+            else {
+                return false;
+            };
+            Version::from_str(variant_value).is_ok_and(|version| spec.matches(&version))
+        }
+    }
+}
+
+fn tokenize(s: &str) -> Result<Vec<String>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' | ')' => tokens.push(c.to_string()),
+            '=' | '!' | '<' | '>' => {
+                let mut token = c.to_string();
+                if chars.peek() == Some(&'=') {
+                    token.push('=');
+                    chars.next();
+                }
+                if ComparisonOperator::from_token(&token).is_none() {
+                    return Err(format!("unknown operator '{token}'"));
+                }
+                tokens.push(token);
+            }
+            '\'' | '"' => {
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some(next) if next == c => break,
+                        Some(next) => token.push(next),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                // Quoted values are only used on the right side of a
+                // comparison, mark them so they are never read as keywords.
+                tokens.push(format!("{c}{token}"));
+            }
+            c if is_word_char(c) => {
+                let mut token = c.to_string();
+                while let Some(next) = chars.next_if(|next| is_word_char(*next)) {
+                    token.push(next);
+                }
+                tokens.push(token);
+            }
+            _ => return Err(format!("unexpected character '{c}'")),
+        }
+    }
+    Ok(tokens)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | '*')
+}
+
+/// Returns true if `token` can be the key of a variant, e.g. `cuda` or
+/// `cuda-version`. A word like `linx-64` or `osx-arm46`, with a segment that
+/// starts with a digit or that starts with the name of an operating system,
+/// is most likely a misspelled platform and is rejected.
+fn is_variant_key(token: &str) -> bool {
+    let is_identifier = token.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-'));
+    if !is_identifier {
+        return false;
+    }
+
+    let mut segments = token.split('-');
+    let first = segments.next().unwrap_or_default();
+    let is_platform_like = token.contains('-')
+        && Platform::all().any(|platform| platform.only_platform() == Some(first));
+    !is_platform_like && segments.all(|segment| segment.starts_with(|c: char| !c.is_ascii_digit()))
+}
+
+fn is_quoted(token: &str) -> bool {
+    token.starts_with('\'') || token.starts_with('"')
+}
+
+struct Parser {
+    tokens: Vec<String>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&str> {
+        self.tokens.get(self.pos).map(String::as_str)
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        if self.peek() == Some(token) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn parse_or(&mut self) -> Result<SelectorExpression, String> {
+        let mut expression = self.parse_and()?;
+        while self.eat("or") {
+            let rhs = self.parse_and()?;
+            expression = SelectorExpression::Or(Box::new(expression), Box::new(rhs));
+        }
+        Ok(expression)
+    }
+
+    fn parse_and(&mut self) -> Result<SelectorExpression, String> {
+        let mut expression = self.parse_not()?;
+        while self.eat("and") {
+            let rhs = self.parse_not()?;
+            expression = SelectorExpression::And(Box::new(expression), Box::new(rhs));
+        }
+        Ok(expression)
+    }
+
+    fn parse_not(&mut self) -> Result<SelectorExpression, String> {
+        if self.eat("not") {
+            return Ok(SelectorExpression::Not(Box::new(self.parse_not()?)));
+        }
+        self.parse_atom()
+    }
+
+    fn parse_atom(&mut self) -> Result<SelectorExpression, String> {
+        let Some(token) = self.next() else {
+            return Err("unexpected end of the selector".to_string());
+        };
+        if token == "(" {
+            let expression = self.parse_or()?;
+            if !self.eat(")") {
+                return Err("missing ')'".to_string());
+            }
+            return Ok(expression);
+        }
+        if token == ")" || is_quoted(&token) || ComparisonOperator::from_token(&token).is_some() {
+            return Err(format!("unexpected '{token}'"));
+        }
+
+        if let Some(operator) = self.peek().and_then(ComparisonOperator::from_token) {
+            if !is_variant_key(&token) {
+                return Err(format!("'{token}' is not a variant"));
+            }
+            self.pos += 1;
+            let value = match self.next() {
+                Some(value) if is_quoted(&value) => value[1..].to_string(),
+                Some(value) if value.chars().all(is_word_char) => value,
+                _ => return Err(format!("missing the value to compare '{token}' with")),
+            };
+            return Ok(SelectorExpression::Comparison {
+                key: NormalizedKey::from(token.as_str()),
+                operator,
+                value,
+            });
+        }
+
+        if let Some(family) = PlatformFamily::from_keyword(&token) {
+            Ok(SelectorExpression::Family(family))
+        } else if token.contains('*') {
+            Ok(SelectorExpression::PlatformGlob(token))
+        } else if let Ok(platform) = Platform::from_str(&token) {
+            Ok(SelectorExpression::Platform(platform))
+        } else if let Ok(arch) = Arch::from_str(&token) {
+            Ok(SelectorExpression::Arch(arch))
+        } else if is_variant_key(&token) {
+            Ok(SelectorExpression::Variant(NormalizedKey::from(
+                token.as_str(),
+            )))
+        } else {
+            Err(format!(
+                "'{token}' is not a platform, architecture or variant"
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(selector: &str) -> SelectorExpression {
+        selector.parse().unwrap()
+    }
+
+    fn variant(entries: &[(&str, &str)]) -> SelectorVariant {
+        entries
+            .iter()
+            .map(|(key, value)| {
+                (
+                    NormalizedKey::from(*key),
+                    vec![Variable::from_string(value)],
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_compound_selectors() {
+        let empty = SelectorVariant::new();
+
+        let selector = parse("unix and not osx");
+        assert!(selector.matches(Platform::Linux64, &empty));
+        assert!(!selector.matches(Platform::OsxArm64, &empty));
+        assert!(!selector.matches(Platform::Win64, &empty));
+
+        let selector = parse("linux-*");
+        assert!(selector.matches(Platform::LinuxAarch64, &empty));
+        assert!(!selector.matches(Platform::Osx64, &empty));
+
+        let selector = parse("win and x86_64");
+        assert!(selector.matches(Platform::Win64, &empty));
+        assert!(!selector.matches(Platform::WinArm64, &empty));
+        assert!(!selector.matches(Platform::Linux64, &empty));
+
+        let selector = parse("(osx or win) and not osx-64");
+        assert!(selector.matches(Platform::OsxArm64, &empty));
+        assert!(!selector.matches(Platform::Osx64, &empty));
+    }
+
+    #[test]
+    fn test_variant_selectors() {
+        let cuda = parse("cuda");
+        assert!(cuda.matches(Platform::Linux64, &variant(&[("cuda", "12.4")])));
+        assert!(!cuda.matches(Platform::Linux64, &variant(&[("python", "3.12")])));
+
+        let python = parse("linux and python >= 3.12");
+        assert!(python.matches(Platform::Linux64, &variant(&[("python", "3.13")])));
+        assert!(!python.matches(Platform::Linux64, &variant(&[("python", "3.11")])));
+        assert!(!python.matches(Platform::Win64, &variant(&[("python", "3.13")])));
+
+        let cuda_version = parse("cuda-version");
+        assert!(cuda_version.matches(Platform::Linux64, &variant(&[("cuda-version", "12.4")])));
+        let cuda_version = parse("cuda-version >= 12");
+        assert!(cuda_version.matches(Platform::Linux64, &variant(&[("cuda-version", "12.4")])));
+
+        let blas = parse("blas_impl == 'mkl'");
+        assert!(blas.matches(Platform::Linux64, &variant(&[("blas_impl", "mkl")])));
+        assert!(!blas.matches(Platform::Linux64, &variant(&[("blas_impl", "openblas")])));
+
+        assert_eq!(
+            parse("linux and (cuda or not python >= 3.12)").variant_keys(),
+            vec![NormalizedKey::from("cuda"), NormalizedKey::from("python")]
+        );
+        assert!(parse("unix and not osx-64").variant_keys().is_empty());
+    }
+
+    #[test]
+    fn test_overlapping_selectors() {
+        assert!(parse("linux-*").overlaps(&parse("cuda")));
+        assert!(parse("unix").overlaps(&parse("x86_64")));
+        assert!(parse("python >= 3.12").overlaps(&parse("python < 3.10")));
+        assert!(!parse("linux-64").overlaps(&parse("osx-64")));
+        assert!(!parse("cuda").overlaps(&parse("not cuda")));
+        assert!(!parse("linux and cuda").overlaps(&parse("win or not cuda")));
+        assert!(parse("cuda or python >= 3.12").overlaps(&parse("not cuda")));
+        assert!(!parse("linux-64 and cuda and mkl").overlaps(&parse("not mkl or win")));
+    }
+
+    #[test]
+    fn test_variant_conditions_limit() {
+        let conditions = |count: usize| (0..count).map(|i| format!("v{i}")).join(" and ");
+        let selector = parse(&conditions(MAX_VARIANT_CONDITIONS));
+        let other: SelectorExpression = format!("not ({})", conditions(MAX_VARIANT_CONDITIONS))
+            .parse()
+            .unwrap();
+        assert!(!selector.overlaps(&other));
+
+        assert!(
+            conditions(MAX_VARIANT_CONDITIONS + 1)
+                .parse::<SelectorExpression>()
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_invalid_selectors() {
+        for selector in [
+            "",
+            "unix and",
+            "(linux",
+            "python >=",
+            "linux = 64",
+            "linux-64)",
+            "linx-64",
+            "unix and osx-arm46",
+            "linx-64 == 1",
+            "cuda-",
+        ] {
+            assert!(
+                selector.parse::<SelectorExpression>().is_err(),
+                "'{selector}' should not parse"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rattler_build_condition() {
+        assert_eq!(
+            parse("linux-64").to_rattler_build_condition(&PlatformKind::Host),
+            "host_platform == 'linux-64'"
+        );
+        assert_eq!(
+            parse("unix and not osx").to_rattler_build_condition(&PlatformKind::Target),
+            "unix and (not osx)"
+        );
+        assert_eq!(
+            parse("cuda or python >= 3.12").to_rattler_build_condition(&PlatformKind::Build),
+            "(cuda is defined) or (python is defined and match(python, '>=3.12'))"
+        );
+        assert!(
+            parse("win and x86_64")
+                .to_rattler_build_condition(&PlatformKind::Target)
+                .contains("target_platform == 'win-64'")
+        );
+    }
+}
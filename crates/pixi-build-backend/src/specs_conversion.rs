@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use itertools::Itertools;
+use miette::{Context, IntoDiagnostic};
 use ordermap::OrderMap;
 use pixi_build_types::{
     BinaryPackageSpecV1, PackageSpecV1, SourcePackageSpecV1, TargetSelectorV1, TargetV1, TargetsV1,
@@ -20,7 +22,9 @@ use recipe_stage0::{
 };
 use url::Url;
 
-use crate::encoded_source_spec_url::EncodedSourceSpecUrl;
+use crate::{
+    TargetSelector, encoded_source_spec_url::EncodedSourceSpecUrl, selector::SelectorExpression,
+};
 
 pub fn from_source_url_to_source_package(source_url: Url) -> Option<SourcePackageSpecV1> {
     match source_url.scheme() {
@@ -58,16 +62,26 @@ pub fn to_rattler_build_selector(
     platform_kind: PlatformKind,
 ) -> String {
     match selector {
-        TargetSelectorV1::Platform(p) => format!("{platform_kind}_platform == '{p}'"),
+        TargetSelectorV1::Platform(p) => match SelectorExpression::try_from(selector) {
+            Ok(expression) => expression.to_rattler_build_condition(&platform_kind),
+            Err(_) => format!("{platform_kind}_platform == '{p}'"),
+        },
         _ => selector.to_string(),
     }
 }
 
+/// Converts the dependencies of the targets of the project model into the
+/// requirements of a recipe. Dependencies of a specific target are wrapped in
+/// a condition on its selector.
+///
+/// Run constraints of the project model are not supported: `TargetV1` has no
+/// field for them, so the run constraints are left empty. Only the
+/// `run-constraints` option of the configuration adds them, see
+/// [`run_constraints_to_conditional_list`].
 pub fn from_targets_v1_to_conditional_requirements(targets: &TargetsV1) -> ConditionalRequirements {
     let mut build_items = ConditionalList::new();
     let mut host_items = ConditionalList::new();
     let mut run_items = ConditionalList::new();
-    let run_constraints_items = ConditionalList::new();
 
     // Add default target
     if let Some(default_target) = &targets.default_target {
@@ -100,9 +114,12 @@ pub fn from_targets_v1_to_conditional_requirements(targets: &TargetsV1) -> Condi
         );
     }
 
-    // Add specific targets
+    // Add specific targets, from the least to the most specific selector
     if let Some(specific_targets) = &targets.targets {
-        for (selector, target) in specific_targets {
+        for (selector, target) in specific_targets
+            .iter()
+            .sorted_by_key(|(selector, _)| selector.specificity())
+        {
             let package_requirements = target_to_package_spec(target);
 
             // add the binary requirements
@@ -155,10 +172,87 @@ pub fn from_targets_v1_to_conditional_requirements(targets: &TargetsV1) -> Condi
         build: build_items,
         host: host_items,
         run: run_items,
-        run_constraints: run_constraints_items,
+        ..Default::default()
     }
 }
 
+/// Converts the `run-constraints` of a configuration and of its target
+/// configurations into the run constraints of a recipe.
+///
+/// The constraints of a target are wrapped in a condition on its selector,
+/// like the dependencies of a target, so that a `noarch` package doesn't get
+/// the constraints of the platform it was built on. A target replaces the
+/// constraints of the base configuration and of the targets before it, so
+/// its condition also requires that none of the targets after it match. The
+/// `targets` are ordered from the least to the most specific selector and
+/// only contain the targets that set constraints.
+pub fn run_constraints_to_conditional_list(
+    base: &[String],
+    targets: &[(&TargetSelectorV1, &[String])],
+) -> miette::Result<ConditionalList<PackageDependency>> {
+    if targets.is_empty() {
+        return base
+            .iter()
+            .map(|constraint| {
+                constraint
+                    .parse()
+                    .into_diagnostic()
+                    .with_context(|| format!("failed to parse '{constraint}'"))
+            })
+            .collect();
+    }
+
+    // Templates can't be part of a conditional list
+    let parse = |constraints: &[String]| -> miette::Result<Vec<PackageDependency>> {
+        constraints
+            .iter()
+            .map(|constraint| {
+                constraint
+                    .parse()
+                    .into_diagnostic()
+                    .with_context(|| format!("failed to parse '{constraint}'"))
+            })
+            .collect()
+    };
+    let conditions = targets
+        .iter()
+        .map(|(selector, _)| {
+            format!(
+                "({})",
+                to_rattler_build_selector(selector, PlatformKind::Target)
+            )
+        })
+        .collect_vec();
+
+    let mut items = ConditionalList::new();
+    let base = parse(base)?;
+    if !base.is_empty() {
+        items.push(Item::new_from_conditional(
+            format!("not ({})", conditions.iter().join(" or ")),
+            base,
+            Vec::new(),
+        ));
+    }
+    for (index, (_, constraints)) in targets.iter().enumerate() {
+        let overriding = &conditions[index + 1..];
+        let condition = if overriding.is_empty() {
+            conditions[index].clone()
+        } else {
+            format!(
+                "{} and not ({})",
+                conditions[index],
+                overriding.iter().join(" or ")
+            )
+        };
+        items.push(Item::new_from_conditional(
+            condition,
+            parse(constraints)?,
+            Vec::new(),
+        ));
+    }
+    Ok(items)
+}
+
 pub(crate) fn source_package_spec_to_package_dependency(
     name: PackageName,
     source_spec: SourcePackageSpecV1,
@@ -387,6 +481,59 @@ mod test {
         assert_eq!(match_spec.to_string(), "foobar 3.12.*");
     }
 
+    #[test]
+    fn test_run_constraints_of_targets_are_conditional() {
+        let base = vec!["numpy >=2".to_string()];
+        let unix = TargetSelectorV1::Unix;
+        let unix_constraints = vec!["numpy >=2".to_string(), "openssl >=3".to_string()];
+        let linux = TargetSelectorV1::Platform("linux-64".to_string());
+        let linux_constraints = vec!["glibc >=2.28".to_string()];
+
+        // Without targets the constraints apply unconditionally
+        let items = run_constraints_to_conditional_list(&base, &[]).unwrap();
+        assert_eq!(
+            items,
+            vec![Item::from(PackageDependency::from("numpy >=2"))]
+        );
+
+        // A target replaces the constraints of the less specific ones
+        let items = run_constraints_to_conditional_list(
+            &base,
+            &[
+                (&unix, unix_constraints.as_slice()),
+                (&linux, linux_constraints.as_slice()),
+            ],
+        )
+        .unwrap();
+        let conditions = items
+            .iter()
+            .map(|item| match item {
+                Item::Conditional(conditional) => {
+                    (conditional.condition.as_str(), conditional.then.0.len())
+                }
+                Item::Value(_) => panic!("expected a conditional, got {item}"),
+            })
+            .collect_vec();
+        assert_eq!(
+            conditions,
+            vec![
+                ("not ((unix) or (target_platform == 'linux-64'))", 1),
+                ("(unix) and not ((target_platform == 'linux-64'))", 2),
+                ("(target_platform == 'linux-64')", 1),
+            ]
+        );
+
+        // Templates can't be conditional
+        let template = vec!["${{ pin_subpackage('foo') }}".to_string()];
+        assert!(
+            run_constraints_to_conditional_list(
+                &template,
+                &[(&linux, linux_constraints.as_slice())]
+            )
+            .is_err()
+        );
+    }
+
     #[test]
     fn test_binary_package_conversion_any_is_treated_as_none() {
         let name = PackageName::new_unchecked("python");
@@ -10,13 +10,31 @@ use itertools::{Either, Itertools};
 use pixi_build_types::{PackageSpecV1, SourcePackageName};
 use rattler_conda_types::Platform;
 
-use crate::PackageSpec;
+use crate::{
+    PackageSpec,
+    selector::{SelectorExpression, SelectorVariant},
+};
 use pixi_build_types::{self as pbt};
 
 /// A trait that extend the target selector with additional functionality.
 pub trait TargetSelector {
     /// Does the target selector match the platform?
+    ///
+    /// Without a variant a selector that refers to a variant cannot be
+    /// decided, so such a selector never matches, not even `not cuda`. Use
+    /// [`TargetSelector::matches_variant`] to match it against a variant.
     fn matches(&self, platform: Platform) -> bool;
+
+    /// Does the target selector match the platform and variant?
+    fn matches_variant(&self, platform: Platform, _variant: &SelectorVariant) -> bool {
+        self.matches(platform)
+    }
+
+    /// How specific the selector is, targets of more specific selectors take
+    /// precedence. See [`SelectorExpression::specificity`].
+    fn specificity(&self) -> u8 {
+        0
+    }
 }
 
 #[derive(Debug)]
@@ -101,19 +119,17 @@ pub trait Targets {
         platform: Option<Platform>,
     ) -> IndexMap<&SourcePackageName, &Self::Spec>;
 
-    /// Resolve the target for the given platform.
+    /// Resolve the target for the given platform. The default target comes
+    /// first, followed by all matching targets from the least to the most
+    /// specific selector.
     fn resolve(&self, platform: Option<Platform>) -> impl Iterator<Item = &Self::Target> {
         if let Some(platform) = platform {
-            let iter = self
-                .default_target()
-                .into_iter()
-                .chain(self.targets().filter_map(move |(selector, target)| {
-                    if selector.matches(platform) {
-                        Some(target)
-                    } else {
-                        None
-                    }
-                }));
+            let iter = self.default_target().into_iter().chain(
+                self.targets()
+                    .filter(move |(selector, _)| selector.matches(platform))
+                    .sorted_by_key(|(selector, _)| selector.specificity())
+                    .map(|(_, target)| target),
+            );
             Either::Right(iter)
         } else {
             Either::Left(self.default_target().into_iter())
@@ -124,13 +140,19 @@ pub trait Targets {
 // === Below here are the implementations for v1 ===
 impl TargetSelector for pbt::TargetSelectorV1 {
     fn matches(&self, platform: Platform) -> bool {
-        match self {
-            pbt::TargetSelectorV1::Platform(p) => p == &platform.to_string(),
-            pbt::TargetSelectorV1::Linux => platform.is_linux(),
-            pbt::TargetSelectorV1::Unix => platform.is_unix(),
-            pbt::TargetSelectorV1::Win => platform.is_windows(),
-            pbt::TargetSelectorV1::MacOs => platform.is_osx(),
-        }
+        SelectorExpression::try_from(self).is_ok_and(|expression| {
+            expression.variant_keys().is_empty()
+                && expression.matches(platform, &SelectorVariant::new())
+        })
+    }
+
+    fn matches_variant(&self, platform: Platform, variant: &SelectorVariant) -> bool {
+        SelectorExpression::try_from(self)
+            .is_ok_and(|expression| expression.matches(platform, variant))
+    }
+
+    fn specificity(&self) -> u8 {
+        SelectorExpression::try_from(self).map_or(0, |expression| expression.specificity())
     }
 }
 
@@ -199,3 +221,26 @@ impl Targets for pbt::TargetsV1 {
         Dependencies::new(run_deps, host_deps, build_deps)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use rattler_build::{NormalizedKey, recipe::variable::Variable};
+
+    use super::*;
+
+    #[test]
+    fn test_variant_selectors_only_match_a_variant() {
+        let selector = pbt::TargetSelectorV1::Platform("linux-64 and not cuda".to_string());
+        assert!(!selector.matches(Platform::Linux64));
+        assert!(selector.matches_variant(Platform::Linux64, &SelectorVariant::new()));
+        let cuda = SelectorVariant::from([(
+            NormalizedKey::from("cuda"),
+            vec![Variable::from_string("12")],
+        )]);
+        assert!(!selector.matches_variant(Platform::Linux64, &cuda));
+
+        let selector = pbt::TargetSelectorV1::Platform("linux-*".to_string());
+        assert!(selector.matches(Platform::Linux64));
+        assert!(!selector.matches(Platform::Win64));
+    }
+}
@@ -1,9 +1,19 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, sync::Arc};
 
 use crate::common::model::{convert_test_model_to_project_model_v1, load_project_model_from_json};
 use imp::TestGenerateRecipe;
-use pixi_build_backend::{intermediate_backend::IntermediateBackend, protocol::Protocol};
-use pixi_build_types::procedures::conda_build_v1::{CondaBuildV1Output, CondaBuildV1Params};
+use ordermap::OrderMap;
+use pixi_build_backend::{
+    intermediate_backend::{IntermediateBackend, PackageTestError},
+    protocol::Protocol,
+};
+use pixi_build_types::{
+    TargetSelectorV1,
+    procedures::{
+        conda_build_v1::{CondaBuildV1Output, CondaBuildV1Params},
+        conda_outputs::CondaOutputsParams,
+    },
+};
 use rattler_build::console_utils::LoggingOutputHandler;
 use rattler_conda_types::{ChannelUrl, Platform};
 use serde_json::json;
@@ -13,22 +23,27 @@ use url::Url;
 #[cfg(test)]
 mod imp {
     use miette::IntoDiagnostic;
-    use pixi_build_backend::generated_recipe::{
-        BackendConfig, DefaultMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams,
+    use pixi_build_backend::{
+        backend_config,
+        common::CommonOptions,
+        generated_recipe::{
+            BackendConfig, DefaultMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams,
+        },
     };
     use rattler_conda_types::ChannelUrl;
-    use serde::{Deserialize, Serialize};
     use std::{
         collections::HashSet,
         path::{Path, PathBuf},
     };
 
-    #[derive(Debug, Default, Serialize, Deserialize, Clone)]
-    #[serde(rename_all = "kebab-case")]
-    pub struct TestBackendConfig {
-        /// If set, internal state will be logged as files in that directory
-        #[serde(alias = "debug_dir")]
-        pub debug_dir: Option<PathBuf>,
+    backend_config! {
+        #[derive(Debug, Default, Clone)]
+        #[serde(rename_all = "kebab-case")]
+        pub struct TestBackendConfig {
+            /// If set, internal state will be logged as files in that directory
+            #[serde(alias = "debug_dir")]
+            pub debug_dir: Option<PathBuf>,
+        }
     }
 
     #[cfg(test)]
@@ -40,6 +55,10 @@ mod imp {
             self.debug_dir.as_deref()
         }
 
+        fn common_options(&self) -> Option<&CommonOptions> {
+            Some(&self.common)
+        }
+
         fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
             if target_config.debug_dir.is_some() {
                 miette::bail!("`debug_dir` cannot have a target specific value");
@@ -47,6 +66,7 @@ mod imp {
 
             Ok(Self {
                 debug_dir: self.debug_dir.clone(),
+                common: self.common.merge(&target_config.common),
             })
         }
     }
@@ -142,3 +162,422 @@ async fn test_conda_build_v1() {
 
     assert!(build_dir.join("debug").join("recipe.yaml").exists());
 }
+
+#[tokio::test]
+async fn test_conda_build_v1_reproducible() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+    let build_dir = tmp_dir_path.join("build");
+
+    let original_model = load_project_model_from_json("minimal_project_model_for_build.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({ "reproducible": true }),
+        Default::default(),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    // Build the same package twice in different work and output directories,
+    // so that the installed files differ in their modification times.
+    let mut packages = Vec::new();
+    for output_dir in ["first", "second"] {
+        let build_params = CondaBuildV1Params {
+            channels: vec![ChannelUrl::from(
+                Url::parse("https://prefix.dev/conda-forge").unwrap(),
+            )],
+            build_prefix: None,
+            host_prefix: None,
+            run_constraints: None,
+            run_dependencies: None,
+            run_exports: None,
+            output: CondaBuildV1Output {
+                name: "minimal-package".parse().unwrap(),
+                version: None,
+                build: None,
+                subdir: Platform::current(),
+                variant: Default::default(),
+            },
+            work_directory: build_dir.join(output_dir),
+            output_directory: Some(tmp_dir_path.join(output_dir)),
+            editable: None,
+        };
+
+        let conda_build_result = intermediate_backend
+            .conda_build_v1(build_params)
+            .await
+            .unwrap();
+        packages.push(fs_err::read(&conda_build_result.output_file).unwrap());
+    }
+
+    assert!(
+        packages[0] == packages[1],
+        "the packages of a reproducible build differ"
+    );
+
+    // The timestamp is exported to the build script.
+    let recipe = fs_err::read_to_string(build_dir.join("second/debug/recipe.yaml")).unwrap();
+    assert!(recipe.contains("SOURCE_DATE_EPOCH"));
+}
+
+#[tokio::test]
+async fn test_conda_build_v1_templated_build_string() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+    let build_dir = tmp_dir_path.join("build");
+
+    let original_model = load_project_model_from_json("minimal_project_model_for_build.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({ "build-string": "${{ hash }}_nightly" }),
+        Default::default(),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    // The frontend requests the build string that `conda_outputs` reported,
+    // with the template already resolved.
+    let outputs = intermediate_backend
+        .conda_outputs(CondaOutputsParams {
+            channels: vec![],
+            host_platform: Platform::current(),
+            build_platform: Platform::current(),
+            variant_configuration: None,
+            variant_files: None,
+            work_directory: build_dir.clone(),
+        })
+        .await
+        .unwrap();
+    let build_string = outputs.outputs[0].metadata.build.clone();
+    assert!(build_string.ends_with("_nightly"), "{build_string}");
+    assert!(!build_string.contains("${{"), "{build_string}");
+
+    let build_params = CondaBuildV1Params {
+        channels: vec![ChannelUrl::from(
+            Url::parse("https://prefix.dev/conda-forge").unwrap(),
+        )],
+        build_prefix: None,
+        host_prefix: None,
+        run_constraints: None,
+        run_dependencies: None,
+        run_exports: None,
+        output: CondaBuildV1Output {
+            name: "minimal-package".parse().unwrap(),
+            version: None,
+            build: Some(build_string.clone()),
+            subdir: Platform::current(),
+            variant: Default::default(),
+        },
+        work_directory: build_dir.clone(),
+        output_directory: None,
+        editable: None,
+    };
+
+    let conda_build_result = intermediate_backend
+        .conda_build_v1(build_params)
+        .await
+        .unwrap();
+    assert_eq!(conda_build_result.build, build_string);
+}
+
+#[tokio::test]
+async fn test_conda_outputs_git_commit_build_string() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+    let build_dir = tmp_dir_path.join("build");
+
+    let original_model = load_project_model_from_json("minimal_project_model_for_build.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    // A git repository with a single commit on `main`
+    let git_dir = tmp_dir_path.join(".git");
+    fs_err::create_dir_all(git_dir.join("refs/heads")).unwrap();
+    fs_err::write(git_dir.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+    fs_err::write(
+        git_dir.join("refs/heads/main"),
+        "0123456789abcdef0123456789abcdef01234567\n",
+    )
+    .unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({ "build-string": "${{ hash }}_${{ git_commit }}" }),
+        Default::default(),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    let outputs = intermediate_backend
+        .conda_outputs(CondaOutputsParams {
+            channels: vec![],
+            host_platform: Platform::current(),
+            build_platform: Platform::current(),
+            variant_configuration: None,
+            variant_files: None,
+            work_directory: build_dir.clone(),
+        })
+        .await
+        .unwrap();
+    let build_string = &outputs.outputs[0].metadata.build;
+    assert!(build_string.ends_with("_0123456"), "{build_string}");
+    assert!(!build_string.contains("${{"), "{build_string}");
+
+    // Another commit changes the build string
+    assert!(outputs.input_globs.contains(".git/HEAD"));
+    assert!(outputs.input_globs.contains(".git/refs/**"));
+}
+
+#[tokio::test]
+async fn test_conda_build_v1_failing_tests() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+    let build_dir = tmp_dir_path.join("build");
+
+    let original_model = load_project_model_from_json("minimal_project_model_for_build.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    // A test of the recipe that can't succeed, the package is empty
+    fs_err::write(
+        tmp_dir_path.join("recipe.patch.yaml"),
+        "tests:\n  - package_contents:\n      files:\n        - bin/does-not-exist\n",
+    )
+    .unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({ "run-tests": true, "recipe-overlay": "recipe.patch.yaml" }),
+        Default::default(),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    let build_params = CondaBuildV1Params {
+        channels: vec![ChannelUrl::from(
+            Url::parse("https://prefix.dev/conda-forge").unwrap(),
+        )],
+        build_prefix: None,
+        host_prefix: None,
+        run_constraints: None,
+        run_dependencies: None,
+        run_exports: None,
+        output: CondaBuildV1Output {
+            name: "minimal-package".parse().unwrap(),
+            version: None,
+            build: None,
+            subdir: Platform::current(),
+            variant: Default::default(),
+        },
+        work_directory: build_dir.clone(),
+        output_directory: None,
+        editable: None,
+    };
+
+    let err = intermediate_backend
+        .conda_build_v1(build_params)
+        .await
+        .unwrap_err();
+    let test_error = err
+        .downcast_ref::<PackageTestError>()
+        .unwrap_or_else(|| panic!("expected a test failure, got {err:?}"));
+    assert!(test_error.package.starts_with("minimal-package-"));
+}
+
+#[tokio::test]
+async fn test_conda_outputs_resolves_target_config_per_variant() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+
+    let original_model = load_project_model_from_json("project_model_with_variant.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({}),
+        OrderMap::from([(
+            TargetSelectorV1::Platform("boltons == '2'".to_string()),
+            json!({ "build-number": 7 }),
+        )]),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    let outputs = intermediate_backend
+        .conda_outputs(CondaOutputsParams {
+            channels: vec![],
+            host_platform: Platform::current(),
+            build_platform: Platform::current(),
+            variant_configuration: Some(BTreeMap::from([(
+                "boltons".to_string(),
+                vec!["1".to_string(), "2".to_string()],
+            )])),
+            variant_files: None,
+            work_directory: tmp_dir_path.join("build"),
+        })
+        .await
+        .unwrap();
+
+    // The target configuration only applies to the output of its variant
+    assert_eq!(outputs.outputs.len(), 2);
+    for output in outputs.outputs {
+        let boltons = output.metadata.variant.get("boltons").map(String::as_str);
+        let expected_build_number = if boltons == Some("2") { 7 } else { 0 };
+        assert_eq!(
+            output.metadata.build_number, expected_build_number,
+            "boltons {boltons:?}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_conda_outputs_missing_output_of_variant() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+
+    let original_model = load_project_model_from_json("project_model_with_variant.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    // The target configuration of one variant renames the package, so the
+    // output of the other variant is missing from its recipe.
+    fs_err::write(
+        tmp_dir_path.join("recipe.patch.yaml"),
+        "package:\n  name: renamed-package\n",
+    )
+    .unwrap();
+
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({}),
+        OrderMap::from([(
+            TargetSelectorV1::Platform("boltons == '2'".to_string()),
+            json!({ "recipe-overlay": "recipe.patch.yaml" }),
+        )]),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    let err = intermediate_backend
+        .conda_outputs(CondaOutputsParams {
+            channels: vec![],
+            host_platform: Platform::current(),
+            build_platform: Platform::current(),
+            variant_configuration: Some(BTreeMap::from([(
+                "boltons".to_string(),
+                vec!["1".to_string(), "2".to_string()],
+            )])),
+            variant_files: None,
+            work_directory: tmp_dir_path.join("build"),
+        })
+        .await
+        .unwrap_err();
+
+    // The output is reported instead of being omitted
+    let message = err.to_string();
+    assert!(message.contains("'renamed-package'"), "{message}");
+    assert!(message.contains("boltons=1"), "{message}");
+}
+
+#[tokio::test]
+async fn test_conda_outputs_conditional_run_constraints() {
+    let tmp_dir = TempDir::new().unwrap();
+    let tmp_dir_path = tmp_dir.path().to_path_buf();
+
+    let pixi_manifest = tmp_dir_path.join("pixi.toml");
+
+    let original_model = load_project_model_from_json("minimal_project_model_for_build.json");
+    let project_model_v1 = convert_test_model_to_project_model_v1(original_model);
+    fs_err::write(&pixi_manifest, toml::to_string(&project_model_v1).unwrap()).unwrap();
+
+    let other_platform = if Platform::current() == Platform::Win64 {
+        Platform::Linux64
+    } else {
+        Platform::Win64
+    };
+    let intermediate_backend: IntermediateBackend<TestGenerateRecipe> = IntermediateBackend::new(
+        pixi_manifest.clone(),
+        Some(tmp_dir_path.clone()),
+        project_model_v1,
+        Arc::default(),
+        json!({ "run-constraints": ["numpy >=2"] }),
+        OrderMap::from([
+            (
+                TargetSelectorV1::Platform(Platform::current().to_string()),
+                json!({ "run-constraints": ["openssl >=3"] }),
+            ),
+            (
+                TargetSelectorV1::Platform(other_platform.to_string()),
+                json!({ "run-constraints": ["mkl >=2024"] }),
+            ),
+        ]),
+        LoggingOutputHandler::default(),
+        None,
+    )
+    .unwrap();
+
+    let outputs = intermediate_backend
+        .conda_outputs(CondaOutputsParams {
+            channels: vec![],
+            host_platform: Platform::current(),
+            build_platform: Platform::current(),
+            variant_configuration: None,
+            variant_files: None,
+            work_directory: tmp_dir_path.join("build"),
+        })
+        .await
+        .unwrap();
+
+    // Only the constraints of the target of the platform apply, they replace
+    // the constraints of the base configuration
+    let constraints = outputs.outputs[0]
+        .run_dependencies
+        .constraints
+        .iter()
+        .map(|constraint| constraint.name.clone())
+        .collect::<Vec<_>>();
+    assert_eq!(constraints, vec!["openssl".to_string()]);
+}
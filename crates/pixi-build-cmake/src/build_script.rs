@@ -1,6 +1,8 @@
 use minijinja::Environment;
 use serde::Serialize;
 
+use crate::{config::Generator, presets::SelectedPreset};
+
 #[derive(Serialize)]
 pub struct BuildScriptContext {
     pub build_platform: BuildPlatform,
@@ -10,6 +12,24 @@ pub struct BuildScriptContext {
     /// This is used to determine if the build script
     /// should include Python-related logic.
     pub has_host_python: bool,
+    /// The compiler cache executable (e.g. `sccache` or `ccache`) that should
+    /// be used as the C and C++ compiler launcher, if any.
+    pub compiler_launcher: Option<String>,
+    /// The CMake presets to configure and build with. When set the default
+    /// CMake arguments are not used, but the project is still built in the
+    /// `build` directory of the work directory.
+    pub preset: Option<SelectedPreset>,
+    /// The CMake generator, defaults to Ninja when not using a preset.
+    pub generator: Option<Generator>,
+    /// The value of `CMAKE_BUILD_TYPE`, defaults to `Release` when not using
+    /// a preset.
+    pub build_type: Option<String>,
+    /// The value of `BUILD_SHARED_LIBS`, defaults to `ON` when not using a
+    /// preset.
+    pub build_shared_libs: Option<String>,
+    /// Additional `-D<KEY>="<VALUE>"` arguments, with the values quoted by
+    /// [`BuildPlatform::quote`].
+    pub cmake_defines: Vec<String>,
 }
 
 #[derive(Copy, Clone, Serialize)]
@@ -21,6 +41,52 @@ pub enum BuildPlatform {
     Unix,
 }
 
+impl BuildPlatform {
+    /// Quotes `value` as a single argument of the shell that runs the build
+    /// script on this platform.
+    ///
+    /// On Windows `cmd.exe` expands `%`, and CMake splits its command line by
+    /// the rules of the Microsoft C runtime, where a quote is escaped with a
+    /// backslash and backslashes are only special in front of a quote.
+    pub fn quote(self, value: &str) -> String {
+        let mut quoted = String::from("\"");
+        match self {
+            BuildPlatform::Unix => {
+                for c in value.chars() {
+                    if matches!(c, '\\' | '"' | '$' | '`') {
+                        quoted.push('\\');
+                    }
+                    quoted.push(c);
+                }
+            }
+            BuildPlatform::Windows => {
+                let mut backslashes = 0;
+                for c in value.chars() {
+                    if c == '\\' {
+                        backslashes += 1;
+                        continue;
+                    }
+                    let escaped = if c == '"' {
+                        2 * backslashes + 1
+                    } else {
+                        backslashes
+                    };
+                    quoted.push_str(&"\\".repeat(escaped));
+                    backslashes = 0;
+                    match c {
+                        '%' => quoted.push_str("%%"),
+                        _ => quoted.push(c),
+                    }
+                }
+                // The closing quote must not be escaped
+                quoted.push_str(&"\\".repeat(2 * backslashes));
+            }
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
 impl BuildScriptContext {
     pub fn render(&self) -> String {
         let env = Environment::new();
@@ -37,6 +103,23 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn test_quote() {
+        let value = r#"say "hi" to $USER at 100% in C:\temp\"#;
+        assert_eq!(
+            BuildPlatform::Unix.quote(value),
+            r#""say \"hi\" to \$USER at 100% in C:\\temp\\""#
+        );
+        assert_eq!(
+            BuildPlatform::Windows.quote(value),
+            r#""say \"hi\" to $USER at 100%% in C:\temp\\""#
+        );
+        assert_eq!(
+            BuildPlatform::Windows.quote(r#"C:\temp\"quoted""#),
+            r#""C:\temp\\\"quoted\"""#
+        );
+    }
+
     #[rstest]
     fn test_build_script(
         #[values(BuildPlatform::Windows, BuildPlatform::Unix)] build_platform: BuildPlatform,
@@ -48,6 +131,12 @@ mod test {
             source_dir: String::from("my-prefix-dir"),
             extra_args: extra_args.clone(),
             has_host_python,
+            compiler_launcher: None,
+            preset: None,
+            generator: None,
+            build_type: None,
+            build_shared_libs: None,
+            cmake_defines: vec![],
         };
         let script = context.render();
 
@@ -70,4 +159,86 @@ mod test {
             insta::assert_snapshot!(script);
         });
     }
+
+    #[rstest]
+    fn test_compiler_launcher(
+        #[values(BuildPlatform::Windows, BuildPlatform::Unix)] build_platform: BuildPlatform,
+    ) {
+        let context = BuildScriptContext {
+            build_platform,
+            source_dir: String::from("my-prefix-dir"),
+            extra_args: vec![],
+            has_host_python: false,
+            compiler_launcher: Some(String::from("sccache")),
+            preset: None,
+            generator: None,
+            build_type: None,
+            build_shared_libs: None,
+            cmake_defines: vec![],
+        };
+        let script = context.render();
+
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_suffix(build_platform.to_string());
+        settings.bind(|| {
+            insta::assert_snapshot!(script);
+        });
+    }
+
+    #[rstest]
+    fn test_preset(
+        #[values(BuildPlatform::Windows, BuildPlatform::Unix)] build_platform: BuildPlatform,
+    ) {
+        let context = BuildScriptContext {
+            build_platform,
+            source_dir: String::from("my-prefix-dir"),
+            extra_args: vec![String::from("test-arg")],
+            has_host_python: true,
+            compiler_launcher: None,
+            preset: Some(SelectedPreset {
+                configure: String::from("release"),
+                configuration: Some(String::from("Release")),
+            }),
+            generator: None,
+            build_type: None,
+            build_shared_libs: None,
+            cmake_defines: vec![],
+        };
+        let script = context.render();
+
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_suffix(build_platform.to_string());
+        settings.bind(|| {
+            insta::assert_snapshot!(script);
+        });
+    }
+
+    #[rstest]
+    fn test_build_options(
+        #[values(BuildPlatform::Windows, BuildPlatform::Unix)] build_platform: BuildPlatform,
+    ) {
+        let generator = match build_platform {
+            BuildPlatform::Windows => Generator::NMakeMakefiles,
+            BuildPlatform::Unix => Generator::UnixMakefiles,
+        };
+        let context = BuildScriptContext {
+            build_platform,
+            source_dir: String::from("my-prefix-dir"),
+            extra_args: vec![],
+            has_host_python: false,
+            compiler_launcher: None,
+            preset: None,
+            generator: Some(generator),
+            build_type: Some(String::from("Debug")),
+            build_shared_libs: Some(String::from("OFF")),
+            cmake_defines: vec![String::from(r#"-DMY_OPTION="ON""#)],
+        };
+        let script = context.render();
+
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_suffix(build_platform.to_string());
+        settings.bind(|| {
+            insta::assert_snapshot!(script);
+        });
+    }
 }
@@ -1,28 +1,69 @@
 use std::path::{Path, PathBuf};
 
 use indexmap::IndexMap;
-use pixi_build_backend::generated_recipe::BackendConfig;
+use pixi_build_backend::{backend_config, common::CommonOptions, generated_recipe::BackendConfig};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone)]
-#[serde(rename_all = "kebab-case", deny_unknown_fields)]
-pub struct CMakeBackendConfig {
-    /// Extra args for CMake invocation
-    #[serde(default)]
-    pub extra_args: Vec<String>,
-    /// Environment Variables
-    #[serde(default)]
-    pub env: IndexMap<String, String>,
-    /// Deprecated. Setting this has no effect; debug data is always written to
-    /// the `debug` subdirectory of the work directory.
-    #[serde(alias = "debug_dir")]
-    pub debug_dir: Option<PathBuf>,
-    /// Extra input globs to include in addition to the default ones
-    #[serde(default)]
-    pub extra_input_globs: Vec<String>,
-    /// List of compilers to use (e.g., ["c", "cxx", "cuda"])
-    /// If not specified, a default will be used
-    pub compilers: Option<Vec<String>>,
+backend_config! {
+    #[derive(Debug, Default, Clone)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    pub struct CMakeBackendConfig {
+        /// Extra args for CMake invocation
+        #[serde(default)]
+        pub extra_args: Vec<String>,
+        /// Environment Variables
+        #[serde(default)]
+        pub env: IndexMap<String, String>,
+        /// Deprecated. Setting this has no effect; debug data is always written to
+        /// the `debug` subdirectory of the work directory.
+        #[serde(alias = "debug_dir")]
+        pub debug_dir: Option<PathBuf>,
+        /// Extra input globs to include in addition to the default ones
+        #[serde(default)]
+        pub extra_input_globs: Vec<String>,
+        /// List of compilers to use (e.g., ["c", "cxx", "cuda"])
+        /// If not specified, a default will be used
+        pub compilers: Option<Vec<String>>,
+        /// Name of a configure preset from `CMakePresets.json` or
+        /// `CMakeUserPresets.json` to configure and build the project with.
+        pub preset: Option<String>,
+        /// The value of `CMAKE_BUILD_TYPE`, defaults to `Release`
+        pub build_type: Option<String>,
+        /// The value of `BUILD_SHARED_LIBS`, defaults to `true`
+        pub build_shared_libs: Option<bool>,
+        /// The CMake generator to use, defaults to `Ninja`
+        pub generator: Option<Generator>,
+        /// Additional CMake cache entries passed as `-D<KEY>="<VALUE>"`
+        #[serde(default)]
+        pub cmake_defines: IndexMap<String, String>,
+        /// An installed executable that the test of `run-tests` runs instead
+        /// of `ctest`
+        pub test_executable: Option<String>,
+    }
+}
+
+/// The CMake generators that are supported by the backend.
+#[derive(Debug, Default, Deserialize, Serialize, Clone, Copy, PartialEq, Eq)]
+pub enum Generator {
+    #[default]
+    #[serde(rename = "Ninja")]
+    Ninja,
+    #[serde(rename = "Unix Makefiles")]
+    UnixMakefiles,
+    #[serde(rename = "NMake Makefiles")]
+    NMakeMakefiles,
+}
+
+impl Generator {
+    /// The build tool that needs to be part of the build requirements to use
+    /// this generator. NMake is part of the MSVC toolchain.
+    pub fn build_tool(&self) -> Option<&'static str> {
+        match self {
+            Generator::Ninja => Some("ninja"),
+            Generator::UnixMakefiles => Some("make"),
+            Generator::NMakeMakefiles => None,
+        }
+    }
 }
 
 impl BackendConfig for CMakeBackendConfig {
@@ -30,6 +71,10 @@ impl BackendConfig for CMakeBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn common_options(&self) -> Option<&CommonOptions> {
+        Some(&self.common)
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values override base values using the following rules:
     /// - extra_args: Platform-specific completely replaces base
@@ -37,6 +82,13 @@ impl BackendConfig for CMakeBackendConfig {
     /// - debug_dir: Not allowed to have target specific value
     /// - extra_input_globs: Platform-specific completely replaces base
     /// - compilers: Platform-specific completely replaces base
+    /// - preset: Platform-specific overrides base
+    /// - build_type: Platform-specific overrides base
+    /// - build_shared_libs: Platform-specific overrides base
+    /// - generator: Platform-specific overrides base
+    /// - cmake_defines: Platform defines override base, others merge
+    /// - test_executable: Platform-specific overrides base
+    /// - common options: see [`CommonOptions::merge`]
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
@@ -63,17 +115,36 @@ impl BackendConfig for CMakeBackendConfig {
                 .compilers
                 .clone()
                 .or_else(|| self.compilers.clone()),
+            preset: target_config.preset.clone().or_else(|| self.preset.clone()),
+            build_type: target_config
+                .build_type
+                .clone()
+                .or_else(|| self.build_type.clone()),
+            build_shared_libs: target_config.build_shared_libs.or(self.build_shared_libs),
+            generator: target_config.generator.or(self.generator),
+            cmake_defines: {
+                let mut merged_defines = self.cmake_defines.clone();
+                merged_defines.extend(target_config.cmake_defines.clone());
+                merged_defines
+            },
+            test_executable: target_config
+                .test_executable
+                .clone()
+                .or_else(|| self.test_executable.clone()),
+            common: self.common.merge(&target_config.common),
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use pixi_build_backend::generated_recipe::BackendConfig;
+    use pixi_build_backend::{common::CommonOptions, generated_recipe::BackendConfig};
     use serde_json::json;
     use std::path::PathBuf;
 
-    use super::CMakeBackendConfig;
+    use indexmap::IndexMap;
+
+    use super::{CMakeBackendConfig, Generator};
 
     #[test]
     fn test_ensure_deseralize_from_empty() {
@@ -81,6 +152,52 @@ mod tests {
         serde_json::from_value::<CMakeBackendConfig>(json_data).unwrap();
     }
 
+    #[test]
+    fn test_deserialize_build_options() {
+        let json_data = json!({
+            "build-type": "Debug",
+            "build-shared-libs": false,
+            "generator": "Unix Makefiles",
+            "cmake-defines": { "MY_OPTION": "ON" }
+        });
+        let config = serde_json::from_value::<CMakeBackendConfig>(json_data).unwrap();
+        assert_eq!(config.build_type, Some("Debug".to_string()));
+        assert_eq!(config.build_shared_libs, Some(false));
+        assert_eq!(config.generator, Some(Generator::UnixMakefiles));
+        assert_eq!(
+            config.cmake_defines.get("MY_OPTION"),
+            Some(&"ON".to_string())
+        );
+
+        let json_data = json!({ "generator": "Visual Studio 17 2022" });
+        assert!(serde_json::from_value::<CMakeBackendConfig>(json_data).is_err());
+    }
+
+    #[test]
+    fn test_deserialize_run_tests() {
+        let config =
+            serde_json::from_value::<CMakeBackendConfig>(json!({ "run-tests": true })).unwrap();
+        assert_eq!(config.common.run_tests, Some(true));
+        assert!(config.run_tests());
+
+        let config = serde_json::from_value::<CMakeBackendConfig>(
+            json!({ "run-tests": true, "test-executable": "foo-tests" }),
+        )
+        .unwrap();
+        assert_eq!(config.test_executable, Some("foo-tests".to_string()));
+        assert!(config.run_tests());
+
+        // The executable is not part of the common option
+        assert!(
+            serde_json::from_value::<CMakeBackendConfig>(json!({ "run-tests": "foo-tests" }))
+                .is_err()
+        );
+
+        let config =
+            serde_json::from_value::<CMakeBackendConfig>(json!({ "run-tests": false })).unwrap();
+        assert!(!config.run_tests());
+    }
+
     #[test]
     fn test_merge_with_target_config() {
         let mut base_env = indexmap::IndexMap::new();
@@ -93,6 +210,20 @@ mod tests {
             debug_dir: Some(PathBuf::from("/base/debug")),
             extra_input_globs: vec!["*.base".to_string()],
             compilers: Some(vec!["cxx".to_string()]),
+            preset: Some("release".to_string()),
+            build_type: Some("Release".to_string()),
+            build_shared_libs: Some(true),
+            generator: None,
+            cmake_defines: IndexMap::from([
+                ("BASE_DEFINE".to_string(), "base".to_string()),
+                ("SHARED_DEFINE".to_string(), "base".to_string()),
+            ]),
+            test_executable: Some("base-tests".to_string()),
+            common: CommonOptions {
+                recipe_overlay: Some(PathBuf::from("recipe.patch.yaml")),
+                files: vec!["bin/*".to_string()],
+                ..Default::default()
+            },
         };
 
         let mut target_env = indexmap::IndexMap::new();
@@ -105,6 +236,16 @@ mod tests {
             debug_dir: None,
             extra_input_globs: vec!["*.target".to_string()],
             compilers: Some(vec!["c".to_string(), "cuda".to_string()]),
+            preset: Some("linux-release".to_string()),
+            build_type: Some("Debug".to_string()),
+            build_shared_libs: None,
+            generator: Some(Generator::UnixMakefiles),
+            cmake_defines: IndexMap::from([("SHARED_DEFINE".to_string(), "target".to_string())]),
+            test_executable: None,
+            common: CommonOptions {
+                files: vec!["lib/*".to_string()],
+                ..Default::default()
+            },
         };
 
         let merged = base_config
@@ -136,6 +277,32 @@ mod tests {
             merged.compilers,
             Some(vec!["c".to_string(), "cuda".to_string()])
         );
+
+        // preset should be overridden by target
+        assert_eq!(merged.preset, Some("linux-release".to_string()));
+
+        // build options should be overridden by target if set
+        assert_eq!(merged.build_type, Some("Debug".to_string()));
+        assert_eq!(merged.build_shared_libs, Some(true));
+        assert_eq!(merged.generator, Some(Generator::UnixMakefiles));
+
+        // cmake_defines should merge with target taking precedence
+        assert_eq!(
+            merged.cmake_defines,
+            IndexMap::from([
+                ("BASE_DEFINE".to_string(), "base".to_string()),
+                ("SHARED_DEFINE".to_string(), "target".to_string()),
+            ])
+        );
+
+        // test_executable should use base value if target is not set
+        assert_eq!(merged.test_executable, Some("base-tests".to_string()));
+
+        // common options are merged by CommonOptions::merge
+        assert_eq!(
+            merged.common,
+            base_config.common.merge(&target_config.common)
+        );
     }
 
     #[test]
@@ -149,6 +316,13 @@ mod tests {
             debug_dir: Some(PathBuf::from("/base/debug")),
             extra_input_globs: vec!["*.base".to_string()],
             compilers: Some(vec!["cxx".to_string()]),
+            preset: Some("release".to_string()),
+            build_type: Some("RelWithDebInfo".to_string()),
+            build_shared_libs: Some(false),
+            generator: Some(Generator::Ninja),
+            cmake_defines: IndexMap::from([("BASE_DEFINE".to_string(), "base".to_string())]),
+            test_executable: Some("base-tests".to_string()),
+            common: CommonOptions::default(),
         };
 
         let empty_target_config = CMakeBackendConfig::default();
@@ -163,6 +337,14 @@ mod tests {
         assert_eq!(merged.debug_dir, Some(PathBuf::from("/base/debug")));
         assert_eq!(merged.extra_input_globs, vec!["*.base".to_string()]);
         assert_eq!(merged.compilers, Some(vec!["cxx".to_string()]));
+        assert_eq!(merged.preset, Some("release".to_string()));
+        assert_eq!(merged.build_type, Some("RelWithDebInfo".to_string()));
+        assert_eq!(merged.build_shared_libs, Some(false));
+        assert_eq!(merged.generator, Some(Generator::Ninja));
+        assert_eq!(
+            merged.cmake_defines.get("BASE_DEFINE"),
+            Some(&"base".to_string())
+        );
     }
 
     #[test]
@@ -0,0 +1,337 @@
+//! Reads the replies of the [CMake File API](https://cmake.org/cmake/help/latest/manual/cmake-file-api.7.html)
+//! to determine the exact set of files that are used by a build.
+//!
+//! The build script places queries for the `codemodel` and `cmakeFiles`
+//! objects in the build directory before CMake configures the project. CMake
+//! then writes the replies next to them, which are read after the build.
+
+use std::{
+    collections::BTreeSet,
+    path::{Component, Path, PathBuf},
+};
+
+use miette::{Context, IntoDiagnostic};
+use serde::{Deserialize, de::DeserializeOwned};
+
+/// The directory, relative to the build directory, that contains the replies.
+const REPLY_DIR: &str = ".cmake/api/v1/reply";
+
+#[derive(Debug, Deserialize)]
+struct Index {
+    objects: Vec<IndexObject>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexObject {
+    kind: String,
+    json_file: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Paths {
+    source: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Codemodel {
+    paths: Paths,
+    configurations: Vec<CodemodelConfiguration>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CodemodelConfiguration {
+    #[serde(default)]
+    targets: Vec<CodemodelTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CodemodelTarget {
+    json_file: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Target {
+    #[serde(default)]
+    sources: Vec<TargetSource>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TargetSource {
+    path: PathBuf,
+    #[serde(default)]
+    is_generated: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CMakeFiles {
+    paths: Paths,
+    inputs: Vec<CMakeFilesInput>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CMakeFilesInput {
+    path: PathBuf,
+    #[serde(default)]
+    is_generated: bool,
+    #[serde(default)]
+    is_external: bool,
+    #[serde(default)]
+    is_c_make: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompileCommand {
+    directory: PathBuf,
+    file: PathBuf,
+}
+
+/// Returns the source files and CMake scripts of the project that was
+/// configured in `build_dir`, relative to the source directory.
+///
+/// The replies of the CMake File API are preferred. If they don't exist,
+/// `compile_commands.json` is used instead, which only contains the compiled
+/// sources. Returns `None` if neither is available, e.g. because the project
+/// was never configured.
+pub fn read_input_files(build_dir: &Path) -> miette::Result<Option<BTreeSet<String>>> {
+    if let Some(files) = read_file_api_reply(build_dir)? {
+        return Ok(Some(files));
+    }
+    read_compile_commands(build_dir)
+}
+
+fn read_json<T: DeserializeOwned>(path: &Path) -> miette::Result<T> {
+    let contents = fs_err::read_to_string(path).into_diagnostic()?;
+    serde_json::from_str(&contents)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("failed to parse '{}'", path.display()))
+}
+
+/// Reads the files from the latest reply of the CMake File API.
+fn read_file_api_reply(build_dir: &Path) -> miette::Result<Option<BTreeSet<String>>> {
+    let reply_dir = build_dir.join(REPLY_DIR);
+    if !reply_dir.is_dir() {
+        return Ok(None);
+    }
+
+    // The index file with the largest name is the most recent one.
+    let Some(index_path) = fs_err::read_dir(&reply_dir)
+        .into_diagnostic()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("index-") && name.ends_with(".json"))
+        })
+        .max()
+    else {
+        return Ok(None);
+    };
+    let index: Index = read_json(&index_path)?;
+
+    let object = |kind: &str| index.objects.iter().find(|object| object.kind == kind);
+    let (Some(codemodel), Some(cmake_files)) = (object("codemodel"), object("cmakeFiles")) else {
+        return Ok(None);
+    };
+
+    let mut files = BTreeSet::new();
+
+    let codemodel: Codemodel = read_json(&reply_dir.join(&codemodel.json_file))?;
+    for target in codemodel
+        .configurations
+        .iter()
+        .flat_map(|configuration| configuration.targets.iter())
+    {
+        let target: Target = read_json(&reply_dir.join(&target.json_file))?;
+        files.extend(
+            target
+                .sources
+                .iter()
+                .filter(|source| !source.is_generated)
+                .filter_map(|source| relative_to_source(&codemodel.paths.source, &source.path)),
+        );
+    }
+
+    let cmake_files: CMakeFiles = read_json(&reply_dir.join(&cmake_files.json_file))?;
+    files.extend(
+        cmake_files
+            .inputs
+            .iter()
+            .filter(|input| !input.is_generated && !input.is_external && !input.is_c_make)
+            .filter_map(|input| relative_to_source(&cmake_files.paths.source, &input.path)),
+    );
+
+    Ok(Some(files))
+}
+
+/// Reads the compiled files from `compile_commands.json`. The source
+/// directory is taken from the `CMakeCache.txt`.
+fn read_compile_commands(build_dir: &Path) -> miette::Result<Option<BTreeSet<String>>> {
+    let compile_commands_path = build_dir.join("compile_commands.json");
+    let cache_path = build_dir.join("CMakeCache.txt");
+    if !compile_commands_path.is_file() || !cache_path.is_file() {
+        return Ok(None);
+    }
+
+    let cache = fs_err::read_to_string(&cache_path).into_diagnostic()?;
+    let Some(source_dir) = cache
+        .lines()
+        .find_map(|line| line.strip_prefix("CMAKE_HOME_DIRECTORY:INTERNAL="))
+        .map(PathBuf::from)
+    else {
+        return Ok(None);
+    };
+
+    let compile_commands: Vec<CompileCommand> = read_json(&compile_commands_path)?;
+    Ok(Some(
+        compile_commands
+            .iter()
+            .filter_map(|command| {
+                relative_to_source(&source_dir, &command.directory.join(&command.file))
+            })
+            .collect(),
+    ))
+}
+
+/// Converts a path reported by CMake to a glob relative to the source
+/// directory. Files outside of the source directory, including relative
+/// paths that leave it through `..`, are not part of the package sources and
+/// are ignored.
+///
+/// Glob metacharacters in the path are escaped so that the glob only matches
+/// the file itself.
+fn relative_to_source(source_dir: &Path, path: &Path) -> Option<String> {
+    let relative = if path.is_absolute() {
+        path.strip_prefix(source_dir).ok()?
+    } else {
+        path
+    };
+    if !relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_) | Component::CurDir))
+    {
+        return None;
+    }
+    Some(globset::escape(
+        &relative.display().to_string().replace('\\', "/"),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_json(path: &Path, value: serde_json::Value) {
+        fs_err::create_dir_all(path.parent().unwrap()).unwrap();
+        fs_err::write(path, value.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_read_file_api_reply() {
+        let build_dir = tempfile::tempdir().unwrap();
+        let reply_dir = build_dir.path().join(REPLY_DIR);
+
+        write_json(
+            &reply_dir.join("index-2024-01-01T00-00-00-0000.json"),
+            serde_json::json!({ "objects": [] }),
+        );
+        write_json(
+            &reply_dir.join("index-2025-01-01T00-00-00-0000.json"),
+            serde_json::json!({
+                "cmake": {},
+                "objects": [
+                    { "kind": "codemodel", "version": { "major": 2, "minor": 7 }, "jsonFile": "codemodel-v2.json" },
+                    { "kind": "cmakeFiles", "version": { "major": 1, "minor": 1 }, "jsonFile": "cmakeFiles-v1.json" }
+                ]
+            }),
+        );
+        write_json(
+            &reply_dir.join("codemodel-v2.json"),
+            serde_json::json!({
+                "paths": { "source": "/src/project", "build": "/work/build" },
+                "configurations": [{
+                    "name": "Release",
+                    "targets": [{ "name": "foo", "jsonFile": "target-foo.json" }]
+                }]
+            }),
+        );
+        write_json(
+            &reply_dir.join("target-foo.json"),
+            serde_json::json!({
+                "name": "foo",
+                "sources": [
+                    { "path": "src/foo.cpp" },
+                    { "path": "include/foo.hpp" },
+                    { "path": "src/foo[1].cpp" },
+                    { "path": "gen/{a}.c" },
+                    { "path": "../outside/foo.h" },
+                    { "path": "/src/project/../outside/bar.h" },
+                    { "path": "/work/build/generated.cpp", "isGenerated": true }
+                ]
+            }),
+        );
+        write_json(
+            &reply_dir.join("cmakeFiles-v1.json"),
+            serde_json::json!({
+                "paths": { "source": "/src/project", "build": "/work/build" },
+                "inputs": [
+                    { "path": "CMakeLists.txt" },
+                    { "path": "cmake/FindBar.cmake" },
+                    { "path": "/usr/share/cmake/Modules/CMakeCXXInformation.cmake", "isCMake": true, "isExternal": true },
+                    { "path": "/work/build/CMakeFiles/3.30.0/CMakeSystem.cmake", "isGenerated": true }
+                ]
+            }),
+        );
+
+        let files = read_input_files(build_dir.path()).unwrap().unwrap();
+        assert_eq!(
+            files.into_iter().collect::<Vec<_>>(),
+            vec![
+                "CMakeLists.txt",
+                "cmake/FindBar.cmake",
+                "gen/[{]a[}].c",
+                "include/foo.hpp",
+                "src/foo.cpp",
+                "src/foo[[]1[]].cpp",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_read_compile_commands() {
+        let build_dir = tempfile::tempdir().unwrap();
+        let source_dir = tempfile::tempdir().unwrap();
+
+        fs_err::write(
+            build_dir.path().join("CMakeCache.txt"),
+            format!(
+                "# This is the CMakeCache file.\nCMAKE_HOME_DIRECTORY:INTERNAL={}\n",
+                source_dir.path().display()
+            ),
+        )
+        .unwrap();
+        write_json(
+            &build_dir.path().join("compile_commands.json"),
+            serde_json::json!([
+                {
+                    "directory": build_dir.path(),
+                    "command": "c++ -c main.cpp",
+                    "file": source_dir.path().join("src").join("main.cpp")
+                }
+            ]),
+        );
+
+        let files = read_input_files(build_dir.path()).unwrap().unwrap();
+        assert_eq!(files.into_iter().collect::<Vec<_>>(), vec!["src/main.cpp"]);
+    }
+
+    #[test]
+    fn test_no_reply() {
+        let build_dir = tempfile::tempdir().unwrap();
+        assert!(read_input_files(build_dir.path()).unwrap().is_none());
+    }
+}
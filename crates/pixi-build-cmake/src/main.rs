@@ -1,26 +1,42 @@
 mod build_script;
 mod config;
+mod file_api;
+mod metadata;
+mod presets;
 
 use build_script::{BuildPlatform, BuildScriptContext};
 use config::CMakeBackendConfig;
+use metadata::CMakeListsMetadataProvider;
 use miette::IntoDiagnostic;
 use pixi_build_backend::{
+    cache::{CompilerCache, add_compiler_cache_to_requirements, detect_compiler_cache},
     compilers::add_compilers_and_stdlib_to_requirements,
-    generated_recipe::{DefaultMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams},
+    generated_recipe::{
+        BackendConfig, ChainedMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams,
+    },
+    git_metadata::GitVersionMetadataProvider,
+    input_globs::input_globs_respecting_ignores,
     intermediate_backend::IntermediateBackendInstantiator,
 };
 use pixi_build_types::ProjectModelV1;
+use presets::{CMakePresets, SelectedPreset};
 use rattler_build::{NormalizedKey, recipe::variable::Variable};
 use rattler_conda_types::{ChannelUrl, PackageName, Platform};
-use recipe_stage0::recipe::{ConditionalRequirements, Script};
+use recipe_stage0::recipe::{
+    ConditionalRequirements, Item, Script, Test, TestFiles, TestRequirements, Value,
+};
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::{
-    collections::{BTreeMap, BTreeSet},
+    collections::{BTreeMap, BTreeSet, HashMap},
     path::Path,
     sync::Arc,
 };
 
+/// Matches the header files of the project. Headers are usually not listed
+/// by the CMake File API, so they are always matched by this wildcard.
+const HEADER_GLOB: &str = "**/*.{h,hh,hpp,hxx,inl,ipp,tpp,cuh}";
+
 #[derive(Default, Clone)]
 pub struct CMakeGenerator {}
 
@@ -37,9 +53,14 @@ impl GenerateRecipe for CMakeGenerator {
         variants: &HashSet<NormalizedKey>,
         _channels: Vec<ChannelUrl>,
     ) -> miette::Result<GeneratedRecipe> {
-        let mut generated_recipe =
-            GeneratedRecipe::from_model(model.clone(), &mut DefaultMetadataProvider)
-                .into_diagnostic()?;
+        let mut cmake_metadata = CMakeListsMetadataProvider::new(&manifest_root);
+        let mut git_metadata = GitVersionMetadataProvider::new(&manifest_root);
+
+        let mut generated_recipe = GeneratedRecipe::from_model(
+            model.clone(),
+            &mut ChainedMetadataProvider::new(&mut cmake_metadata, &mut git_metadata),
+        )
+        .into_diagnostic()?;
 
         // we need to add compilers
 
@@ -68,8 +89,9 @@ impl GenerateRecipe for CMakeGenerator {
             variants,
         );
 
-        // add necessary build tools
-        for tool in ["cmake", "ninja"] {
+        // add necessary build tools, the build tool depends on the generator
+        let generator = config.generator.unwrap_or_default();
+        for tool in std::iter::once("cmake").chain(generator.build_tool()) {
             let tool_name = PackageName::new_unchecked(tool);
             if !resolved_requirements.build.contains_key(&tool_name) {
                 requirements.build.push(tool.parse().into_diagnostic()?);
@@ -81,44 +103,136 @@ impl GenerateRecipe for CMakeGenerator {
         // executable
         let has_host_python = resolved_requirements.contains(&PackageName::new_unchecked("python"));
 
+        // Use a compiler cache as the compiler launcher if the user configured one
+        let system_env_vars = std::env::vars().collect::<HashMap<String, String>>();
+        let compiler_cache = detect_compiler_cache(
+            &[CompilerCache::Sccache, CompilerCache::Ccache],
+            &config.env,
+            &system_env_vars,
+        );
+        if let Some(compiler_cache) = &compiler_cache {
+            add_compiler_cache_to_requirements(compiler_cache.cache, &mut requirements.build)?;
+        }
+
+        // Validate the selected preset before we try to build with it
+        let (preset, preset_globs) = match config.preset.as_deref() {
+            Some(name) => {
+                let presets = CMakePresets::from_source_dir(&manifest_root)?;
+                (
+                    Some(presets.select(name)?),
+                    presets.input_globs(&manifest_root),
+                )
+            }
+            None => (None, BTreeSet::new()),
+        };
+        let test = tests_from_config(config, preset.as_ref())?;
+
+        // Typed options take precedence over the same keys in `cmake-defines`,
+        // either way they replace the default value instead of being appended.
+        let mut cmake_defines = config.cmake_defines.clone();
+        let build_type_define = cmake_defines.shift_remove("CMAKE_BUILD_TYPE");
+        let build_shared_libs_define = cmake_defines.shift_remove("BUILD_SHARED_LIBS");
+        let build_type = config.build_type.clone().or(build_type_define);
+        let build_shared_libs = config
+            .build_shared_libs
+            .map(|shared| if shared { "ON" } else { "OFF" }.to_string())
+            .or(build_shared_libs_define);
+
+        let build_platform = if Platform::current().is_windows() {
+            BuildPlatform::Windows
+        } else {
+            BuildPlatform::Unix
+        };
+
         let build_script = BuildScriptContext {
-            build_platform: if Platform::current().is_windows() {
-                BuildPlatform::Windows
-            } else {
-                BuildPlatform::Unix
-            },
+            build_platform,
             source_dir: manifest_root.display().to_string(),
             extra_args: config.extra_args.clone(),
             has_host_python,
+            compiler_launcher: compiler_cache
+                .as_ref()
+                .map(|compiler_cache| compiler_cache.cache.executable().to_string()),
+            preset,
+            generator: config.generator,
+            build_type,
+            build_shared_libs,
+            cmake_defines: cmake_defines
+                .into_iter()
+                .map(|(key, value)| format!("-D{key}={}", build_platform.quote(&value)))
+                .collect(),
         }
         .render();
 
         generated_recipe.recipe.build.script = Script {
             content: build_script,
             env: config.env.clone(),
-            ..Default::default()
+            secrets: compiler_cache
+                .map(|compiler_cache| compiler_cache.secrets)
+                .unwrap_or_default(),
         };
 
+        generated_recipe.recipe.tests.extend(test);
+
+        // Add the input globs from the CMakeLists.txt metadata provider
+        generated_recipe
+            .metadata_input_globs
+            .extend(cmake_metadata.input_globs());
+        // The selected preset is validated against the preset files
+        generated_recipe.metadata_input_globs.extend(preset_globs);
+        generated_recipe
+            .metadata_input_globs
+            .extend(git_metadata.input_globs());
+
         Ok(generated_recipe)
     }
 
-    fn extract_input_globs_from_build(
+    /// Determines the input globs of the build.
+    ///
+    /// If the project was configured by the build script, the CMake File API
+    /// reply in the build directory lists the exact source files and CMake
+    /// scripts of the project. Headers are usually not part of that list so
+    /// they are still matched by [`HEADER_GLOB`]. Without a reply the backend falls
+    /// back to wildcards for all sources and CMake files.
+    fn extract_input_globs_from_source(
         &self,
         config: &Self::Config,
-        _workdir: impl AsRef<Path>,
+        source_dir: impl AsRef<Path>,
+        workdir: impl AsRef<Path>,
         _editable: bool,
     ) -> miette::Result<BTreeSet<String>> {
-        Ok([
-            // Source files
-            "**/*.{c,cc,cxx,cpp,h,hpp,hxx}",
-            // CMake files
-            "**/*.{cmake,cmake.in}",
-            "**/CMakeFiles.txt",
-        ]
-        .iter()
-        .map(|s: &&str| s.to_string())
-        .chain(config.extra_input_globs.clone())
-        .collect())
+        let build_dir = workdir.as_ref().join("work").join("build");
+        let source_globs = match file_api::read_input_files(&build_dir)? {
+            Some(files) => std::iter::once(HEADER_GLOB.to_string())
+                .chain(files)
+                .collect::<Vec<_>>(),
+            None => [
+                // Source files
+                "**/*.{c,cc,cxx,cpp}",
+                HEADER_GLOB,
+                // CMake files
+                "**/*.{cmake,cmake.in}",
+                "**/CMakeLists.txt",
+            ]
+            .iter()
+            .map(|s| s.to_string())
+            .collect(),
+        };
+
+        // The preset files, including the ones they include
+        let preset_globs = match &config.preset {
+            Some(_) => {
+                CMakePresets::from_source_dir(source_dir.as_ref())?.input_globs(source_dir.as_ref())
+            }
+            None => BTreeSet::new(),
+        };
+
+        Ok(input_globs_respecting_ignores(
+            source_dir.as_ref(),
+            source_globs.into_iter().chain(preset_globs),
+            config.extra_input_globs.clone(),
+            // The default build directory of a local CMake build
+            &["build"],
+        ))
     }
 
     fn default_variants(
@@ -139,6 +253,55 @@ impl GenerateRecipe for CMakeGenerator {
     }
 }
 
+/// Creates the test that runs the tests of the project if the `run-tests`
+/// option is enabled, either with `ctest` or with the `test-executable`.
+///
+/// `ctest` needs `cmake` in the test environment. Only the `CTestTestfile.cmake`
+/// files of the build tree are copied into the test directory, they run the
+/// test executables from the build tree in the work directory by their
+/// absolute paths. An installed test executable is expected to be found on
+/// the `PATH` of the test environment.
+fn tests_from_config(
+    config: &CMakeBackendConfig,
+    preset: Option<&SelectedPreset>,
+) -> miette::Result<Option<Test>> {
+    if !config.run_tests() {
+        return Ok(None);
+    }
+    match &config.test_executable {
+        None => {
+            let mut ctest = String::from("ctest --test-dir build --output-on-failure");
+            if let Some(configuration) = preset.and_then(|preset| preset.configuration.as_ref()) {
+                ctest.push_str(&format!(" -C {configuration}"));
+            }
+            Ok(Some(Test {
+                script: Some(Script {
+                    content: ctest,
+                    ..Script::default()
+                }),
+                requirements: Some(TestRequirements {
+                    run: vec!["cmake".parse().into_diagnostic()?],
+                    ..TestRequirements::default()
+                }),
+                files: Some(TestFiles {
+                    source: vec![Item::Value(Value::Concrete(String::from(
+                        "build/**/CTestTestfile.cmake",
+                    )))],
+                    ..TestFiles::default()
+                }),
+                ..Test::default()
+            }))
+        }
+        Some(executable) => Ok(Some(Test {
+            script: Some(Script {
+                content: executable.clone(),
+                ..Script::default()
+            }),
+            ..Test::default()
+        })),
+    }
+}
+
 #[tokio::main]
 pub async fn main() {
     if let Err(err) = pixi_build_backend::cli::main(|log| {
@@ -157,17 +320,22 @@ mod tests {
 
     use indexmap::IndexMap;
     use pixi_build_backend::{
-        protocol::ProtocolInstantiator, utils::test::intermediate_conda_outputs,
+        common::CommonOptions, protocol::ProtocolInstantiator,
+        utils::test::intermediate_conda_outputs,
     };
     use pixi_build_types::{
         ProjectModelV1,
         procedures::{conda_outputs::CondaOutputsParams, initialize::InitializeParams},
     };
     use rattler_build::console_utils::LoggingOutputHandler;
-    use recipe_stage0::recipe::{Item, Value};
+    use recipe_stage0::{
+        matchspec::PackageDependency,
+        recipe::{Item, Value},
+    };
     use tokio::fs;
 
     use super::*;
+    use crate::config::Generator;
 
     #[test]
     fn test_input_globs_includes_extra_globs() {
@@ -178,7 +346,13 @@ mod tests {
 
         let generator = CMakeGenerator::default();
 
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
 
         insta::assert_debug_snapshot!(result);
     }
@@ -192,6 +366,174 @@ mod tests {
         };
     }
 
+    #[test]
+    fn test_input_globs_from_file_api() {
+        let workdir = tempfile::tempdir().unwrap();
+        let reply_dir = workdir.path().join("work/build/.cmake/api/v1/reply");
+        fs_err::create_dir_all(&reply_dir).unwrap();
+        let write = |name: &str, value: serde_json::Value| {
+            fs_err::write(reply_dir.join(name), value.to_string()).unwrap();
+        };
+        write(
+            "index-1.json",
+            serde_json::json!({ "objects": [
+                { "kind": "codemodel", "jsonFile": "codemodel.json" },
+                { "kind": "cmakeFiles", "jsonFile": "cmakeFiles.json" }
+            ]}),
+        );
+        write(
+            "codemodel.json",
+            serde_json::json!({
+                "paths": { "source": "/src" },
+                "configurations": [{ "targets": [{ "jsonFile": "target.json" }] }]
+            }),
+        );
+        write(
+            "target.json",
+            serde_json::json!({ "sources": [{ "path": "src/main.cpp" }] }),
+        );
+        write(
+            "cmakeFiles.json",
+            serde_json::json!({
+                "paths": { "source": "/src" },
+                "inputs": [{ "path": "CMakeLists.txt" }]
+            }),
+        );
+
+        let config = CMakeBackendConfig {
+            extra_input_globs: vec!["custom/*.c".to_string()],
+            ..Default::default()
+        };
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = CMakeGenerator::default()
+            .extract_input_globs_from_source(&config, source_dir.path(), workdir.path(), false)
+            .unwrap();
+
+        assert_eq!(
+            result,
+            BTreeSet::from([
+                "!.git/**".to_string(),
+                "!.pixi/**".to_string(),
+                "!build/**".to_string(),
+                HEADER_GLOB.to_string(),
+                "CMakeLists.txt".to_string(),
+                "custom/*.c".to_string(),
+                "src/main.cpp".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_input_globs_includes_presets() {
+        let config = CMakeBackendConfig {
+            preset: Some("release".to_string()),
+            ..Default::default()
+        };
+
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = CMakeGenerator::default()
+            .extract_input_globs_from_source(&config, source_dir.path(), PathBuf::new(), false)
+            .unwrap();
+
+        assert!(result.contains("CMakePresets.json"));
+        assert!(result.contains("CMakeUserPresets.json"));
+    }
+
+    #[test]
+    fn test_metadata_input_globs() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let source_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            source_dir.path().join("CMakePresets.json"),
+            r#"{ "version": 4, "include": ["presets/release.json"] }"#,
+        )
+        .unwrap();
+        fs_err::create_dir_all(source_dir.path().join("presets")).unwrap();
+        fs_err::write(
+            source_dir.path().join("presets/release.json"),
+            r#"{ "version": 4, "configurePresets": [{ "name": "release" }] }"#,
+        )
+        .unwrap();
+
+        let generate = |config: &CMakeBackendConfig| {
+            CMakeGenerator::default()
+                .generate_recipe(
+                    &project_model,
+                    config,
+                    source_dir.path().to_path_buf(),
+                    Platform::Linux64,
+                    None,
+                    &HashSet::new(),
+                    vec![],
+                )
+                .unwrap()
+        };
+
+        // The metadata that the project model doesn't define, e.g. the
+        // description, is read from the CMakeLists.txt, so it is an input even
+        // if it doesn't exist yet.
+        let generated_recipe = generate(&CMakeBackendConfig::default());
+        assert_eq!(
+            generated_recipe.metadata_input_globs,
+            BTreeSet::from(["CMakeLists.txt".to_string()])
+        );
+
+        let generated_recipe = generate(&CMakeBackendConfig {
+            preset: Some("release".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            generated_recipe.metadata_input_globs,
+            BTreeSet::from([
+                "CMakeLists.txt".to_string(),
+                "CMakePresets.json".to_string(),
+                "CMakeUserPresets.json".to_string(),
+                "presets/release.json".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_unknown_preset_is_an_error() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let source_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            source_dir.path().join("CMakePresets.json"),
+            r#"{ "version": 3, "configurePresets": [{ "name": "debug" }] }"#,
+        )
+        .unwrap();
+
+        let result = CMakeGenerator::default().generate_recipe(
+            &project_model,
+            &CMakeBackendConfig {
+                preset: Some("release".to_string()),
+                ..Default::default()
+            },
+            source_dir.path().to_path_buf(),
+            Platform::Linux64,
+            None,
+            &HashSet::new(),
+            vec![],
+        );
+
+        let err = result.unwrap_err();
+        assert!(err.to_string().contains("'release' does not exist"));
+    }
+
     #[test]
     fn test_cxx_is_in_build_requirements() {
         let project_model = project_fixture!({
@@ -269,6 +611,216 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_compiler_cache_is_used_as_launcher() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let env = IndexMap::from([("SCCACHE_DIR".to_string(), "/tmp/sccache".to_string())]);
+
+        let generated_recipe = CMakeGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &CMakeBackendConfig {
+                    env,
+                    ..Default::default()
+                },
+                PathBuf::from("."),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        let script = &generated_recipe.recipe.build.script.content;
+        assert!(script.contains("-DCMAKE_C_COMPILER_LAUNCHER=sccache"));
+        assert!(script.contains("-DCMAKE_CXX_COMPILER_LAUNCHER=sccache"));
+
+        let sccache: Item<PackageDependency> = "sccache".parse().unwrap();
+        assert!(
+            generated_recipe
+                .recipe
+                .requirements
+                .build
+                .contains(&sccache)
+        );
+    }
+
+    #[test]
+    fn test_generator_build_tool_and_defines() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let generated_recipe = CMakeGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &CMakeBackendConfig {
+                    generator: Some(Generator::UnixMakefiles),
+                    build_type: Some("Debug".to_string()),
+                    cmake_defines: IndexMap::from([
+                        ("CMAKE_BUILD_TYPE".to_string(), "Release".to_string()),
+                        ("BUILD_SHARED_LIBS".to_string(), "OFF".to_string()),
+                        ("MY_OPTION".to_string(), "ON".to_string()),
+                        ("MY_DATA_DIR".to_string(), "/opt/my data".to_string()),
+                    ]),
+                    ..Default::default()
+                },
+                PathBuf::from("."),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        let build_requirements = &generated_recipe.recipe.requirements.build;
+        let make: Item<PackageDependency> = "make".parse().unwrap();
+        let ninja: Item<PackageDependency> = "ninja".parse().unwrap();
+        assert!(build_requirements.contains(&make));
+        assert!(!build_requirements.contains(&ninja));
+
+        // The typed option wins and the defaults are replaced, not duplicated
+        let script = &generated_recipe.recipe.build.script.content;
+        assert_eq!(script.matches("-DCMAKE_BUILD_TYPE=").count(), 1);
+        assert!(script.contains("-DCMAKE_BUILD_TYPE=Debug"));
+        assert_eq!(script.matches("-DBUILD_SHARED_LIBS=").count(), 1);
+        assert!(script.contains("-DBUILD_SHARED_LIBS=OFF"));
+        assert!(script.contains(r#"-DMY_OPTION="ON""#));
+        assert!(script.contains(r#"-DMY_DATA_DIR="/opt/my data""#));
+    }
+
+    #[test]
+    fn test_run_tests_adds_ctest_test() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let source_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            source_dir.path().join("CMakePresets.json"),
+            r#"{
+                "version": 3,
+                "configurePresets": [{ "name": "release" }],
+                "buildPresets": [{ "name": "release", "configurePreset": "release", "configuration": "Release" }]
+            }"#,
+        )
+        .unwrap();
+
+        let generate = |config: CMakeBackendConfig| {
+            CMakeGenerator::default()
+                .generate_recipe(
+                    &project_model,
+                    &config,
+                    source_dir.path().to_path_buf(),
+                    Platform::Linux64,
+                    None,
+                    &HashSet::new(),
+                    vec![],
+                )
+                .unwrap()
+        };
+        let run_tests = || CommonOptions {
+            run_tests: Some(true),
+            ..Default::default()
+        };
+
+        let generated_recipe = generate(CMakeBackendConfig::default());
+        assert!(generated_recipe.recipe.tests.is_empty());
+
+        let generated_recipe = generate(CMakeBackendConfig {
+            common: run_tests(),
+            ..Default::default()
+        });
+        let test = &generated_recipe.recipe.tests[0];
+        assert_eq!(
+            test.script.as_ref().unwrap().content,
+            "ctest --test-dir build --output-on-failure"
+        );
+        let cmake: Item<PackageDependency> = "cmake".parse().unwrap();
+        assert!(test.requirements.as_ref().unwrap().run.contains(&cmake));
+        assert_eq!(
+            test.files.as_ref().unwrap().source,
+            vec![Item::Value(Value::Concrete(
+                "build/**/CTestTestfile.cmake".to_string()
+            ))]
+        );
+
+        // The configuration of the build preset is tested
+        let generated_recipe = generate(CMakeBackendConfig {
+            common: run_tests(),
+            preset: Some("release".to_string()),
+            ..Default::default()
+        });
+        let test = &generated_recipe.recipe.tests[0];
+        assert_eq!(
+            test.script.as_ref().unwrap().content,
+            "ctest --test-dir build --output-on-failure -C Release"
+        );
+
+        let generated_recipe = generate(CMakeBackendConfig {
+            common: run_tests(),
+            test_executable: Some("foobar-tests".to_string()),
+            ..Default::default()
+        });
+        let test = &generated_recipe.recipe.tests[0];
+        assert_eq!(test.script.as_ref().unwrap().content, "foobar-tests");
+        assert!(test.requirements.is_none());
+    }
+
+    #[test]
+    fn test_metadata_is_read_from_cmakelists() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let source_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            source_dir.path().join("CMakeLists.txt"),
+            r#"project(foobar VERSION 1.2.3 DESCRIPTION "Foo bar" LANGUAGES CXX)"#,
+        )
+        .unwrap();
+
+        let generated_recipe = CMakeGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &CMakeBackendConfig::default(),
+                source_dir.path().to_path_buf(),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        assert_eq!(
+            generated_recipe.recipe.package.version,
+            Value::Concrete("1.2.3".to_string())
+        );
+        assert!(
+            generated_recipe
+                .metadata_input_globs
+                .contains("CMakeLists.txt")
+        );
+    }
+
     #[test]
     fn test_has_python_is_set_in_build_script() {
         let project_model = project_fixture!({
@@ -0,0 +1,404 @@
+use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+
+use miette::Diagnostic;
+use once_cell::unsync::OnceCell;
+use pixi_build_backend::generated_recipe::MetadataProvider;
+use rattler_conda_types::{ParseVersionError, Version};
+
+#[derive(Debug, thiserror::Error, Diagnostic)]
+pub enum MetadataError {
+    #[error("failed to parse version from CMakeLists.txt, {0}")]
+    ParseVersion(ParseVersionError),
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+/// The arguments of the `project()` command of a `CMakeLists.txt` file that
+/// are relevant for the package metadata.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ProjectCommand {
+    pub name: Option<String>,
+    pub version: Option<String>,
+    pub description: Option<String>,
+    pub homepage_url: Option<String>,
+}
+
+impl ProjectCommand {
+    /// Finds the first `project()` command in the contents of a
+    /// `CMakeLists.txt` file and extracts its arguments.
+    ///
+    /// Arguments that reference variables (e.g. `${PROJECT_VERSION}`) cannot
+    /// be evaluated without running CMake and are therefore ignored.
+    pub fn parse(contents: &str) -> Option<Self> {
+        let arguments = commands(contents)
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("project"))
+            .map(|(_, arguments)| arguments)?;
+
+        let mut arguments = arguments.into_iter();
+        let mut project = ProjectCommand {
+            name: arguments.next(),
+            ..ProjectCommand::default()
+        };
+
+        while let Some(keyword) = arguments.next() {
+            let field = match keyword.as_str() {
+                "VERSION" => &mut project.version,
+                "DESCRIPTION" => &mut project.description,
+                "HOMEPAGE_URL" => &mut project.homepage_url,
+                _ => continue,
+            };
+            *field = arguments.next();
+        }
+
+        let evaluated = |value: Option<String>| value.filter(|v| !v.contains("${"));
+        Some(ProjectCommand {
+            name: evaluated(project.name),
+            version: evaluated(project.version),
+            description: evaluated(project.description),
+            homepage_url: evaluated(project.homepage_url),
+        })
+    }
+}
+
+/// Splits the contents of a CMake file into commands and their arguments.
+///
+/// This is not a complete implementation of the CMake language, but it
+/// handles comments, quoted arguments and bracket arguments, which is enough
+/// to reliably find the `project()` command.
+fn commands(contents: &str) -> Vec<(String, Vec<String>)> {
+    let chars: Vec<char> = contents.chars().collect();
+    let mut commands = Vec::new();
+    let mut pos = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        if c == '#' {
+            pos = skip_comment(&chars, pos);
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = pos;
+            while pos < chars.len() && (chars[pos].is_ascii_alphanumeric() || chars[pos] == '_') {
+                pos += 1;
+            }
+            let name: String = chars[start..pos].iter().collect();
+            while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+                pos += 1;
+            }
+            if pos < chars.len() && chars[pos] == '(' {
+                let (arguments, end) = parse_arguments(&chars, pos + 1);
+                commands.push((name, arguments));
+                pos = end;
+            }
+        } else {
+            pos += 1;
+        }
+    }
+
+    commands
+}
+
+/// Skips a line comment or a bracket comment starting at `pos`.
+fn skip_comment(chars: &[char], pos: usize) -> usize {
+    if let Some(end) = skip_bracket(chars, pos + 1) {
+        return end;
+    }
+    let mut pos = pos;
+    while pos < chars.len() && chars[pos] != '\n' {
+        pos += 1;
+    }
+    pos
+}
+
+/// If a bracket argument (`[=[ ... ]=]`) starts at `pos`, returns its content
+/// and the position after it.
+fn parse_bracket(chars: &[char], pos: usize) -> Option<(String, usize)> {
+    if chars.get(pos) != Some(&'[') {
+        return None;
+    }
+    let mut level = 0;
+    while chars.get(pos + 1 + level) == Some(&'=') {
+        level += 1;
+    }
+    if chars.get(pos + 1 + level) != Some(&'[') {
+        return None;
+    }
+
+    let close: Vec<char> = std::iter::once(']')
+        .chain(std::iter::repeat_n('=', level))
+        .chain(std::iter::once(']'))
+        .collect();
+    let start = pos + 2 + level;
+    let end = (start..=chars.len().saturating_sub(close.len()))
+        .find(|&i| chars[i..].starts_with(&close))
+        .unwrap_or(chars.len());
+    let content = chars[start..end].iter().collect::<String>();
+    // A newline directly after the opening bracket is ignored.
+    let content = content
+        .strip_prefix('\n')
+        .map(str::to_string)
+        .unwrap_or(content);
+    Some((content, (end + close.len()).min(chars.len())))
+}
+
+fn skip_bracket(chars: &[char], pos: usize) -> Option<usize> {
+    parse_bracket(chars, pos).map(|(_, end)| end)
+}
+
+/// Parses the arguments of a command, starting right after the opening
+/// parenthesis. Returns the arguments and the position after the closing
+/// parenthesis.
+fn parse_arguments(chars: &[char], mut pos: usize) -> (Vec<String>, usize) {
+    let mut arguments = Vec::new();
+    let mut depth = 0;
+
+    while pos < chars.len() {
+        let c = chars[pos];
+        match c {
+            c if c.is_whitespace() => pos += 1,
+            '#' => pos = skip_comment(chars, pos),
+            ')' if depth == 0 => return (arguments, pos + 1),
+            '(' => {
+                depth += 1;
+                pos += 1;
+            }
+            ')' => {
+                depth -= 1;
+                pos += 1;
+            }
+            '"' => {
+                let mut value = String::new();
+                pos += 1;
+                while pos < chars.len() && chars[pos] != '"' {
+                    if chars[pos] == '\\' && pos + 1 < chars.len() {
+                        pos += 1;
+                        match chars[pos] {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            // An escaped newline continues the line.
+                            '\n' => {}
+                            other => value.push(other),
+                        }
+                    } else {
+                        value.push(chars[pos]);
+                    }
+                    pos += 1;
+                }
+                arguments.push(value);
+                pos += 1;
+            }
+            '[' if parse_bracket(chars, pos).is_some() => {
+                let (value, end) = parse_bracket(chars, pos).expect("checked above");
+                arguments.push(value);
+                pos = end;
+            }
+            _ => {
+                let mut value = String::new();
+                while pos < chars.len()
+                    && !chars[pos].is_whitespace()
+                    && !matches!(chars[pos], '(' | ')' | '"' | '#')
+                {
+                    if chars[pos] == '\\' && pos + 1 < chars.len() {
+                        pos += 1;
+                    }
+                    value.push(chars[pos]);
+                    pos += 1;
+                }
+                arguments.push(value);
+            }
+        }
+    }
+
+    (arguments, pos)
+}
+
+/// An implementation of [`MetadataProvider`] that reads metadata from the
+/// `project()` command in the top-level `CMakeLists.txt` file.
+pub struct CMakeListsMetadataProvider {
+    manifest_root: PathBuf,
+    project: OnceCell<Option<ProjectCommand>>,
+}
+
+impl CMakeListsMetadataProvider {
+    /// Constructs a new `CMakeListsMetadataProvider` with the given manifest
+    /// root.
+    ///
+    /// # Arguments
+    ///
+    /// * `manifest_root` - The directory that contains the `CMakeLists.txt`
+    ///   file
+    pub fn new(manifest_root: impl Into<PathBuf>) -> Self {
+        Self {
+            manifest_root: manifest_root.into(),
+            project: OnceCell::default(),
+        }
+    }
+
+    /// Ensures that the `CMakeLists.txt` is loaded and returns the arguments
+    /// of its `project()` command.
+    ///
+    /// Returns `None` if there is no `CMakeLists.txt` or it does not contain
+    /// a `project()` command.
+    fn ensure_project(&self) -> Result<Option<&ProjectCommand>, MetadataError> {
+        self.project
+            .get_or_try_init(move || {
+                let path = self.manifest_root.join("CMakeLists.txt");
+                if !path.is_file() {
+                    return Ok(None);
+                }
+                let contents = fs_err::read_to_string(path)?;
+                Ok(ProjectCommand::parse(&contents))
+            })
+            .map(Option::as_ref)
+    }
+
+    /// Returns the set of globs that match files that influence the metadata of
+    /// this package.
+    ///
+    /// # Returns
+    ///
+    /// A `BTreeSet` of glob patterns as strings. Common patterns include:
+    /// - `"CMakeLists.txt"` - The top-level CMake file
+    pub fn input_globs(&self) -> BTreeSet<String> {
+        let mut input_globs = BTreeSet::new();
+
+        let Some(_) = self.project.get() else {
+            return input_globs;
+        };
+
+        // Add the CMakeLists.txt file itself.
+        input_globs.insert(String::from("CMakeLists.txt"));
+
+        input_globs
+    }
+}
+
+impl MetadataProvider for CMakeListsMetadataProvider {
+    type Error = MetadataError;
+
+    /// Returns the name of the project from the `project()` command.
+    fn name(&mut self) -> Result<Option<String>, Self::Error> {
+        Ok(self.ensure_project()?.and_then(|p| p.name.clone()))
+    }
+
+    /// Returns the `VERSION` of the `project()` command parsed into a
+    /// `rattler_conda_types::Version`.
+    fn version(&mut self) -> Result<Option<Version>, Self::Error> {
+        let Some(version) = self.ensure_project()?.and_then(|p| p.version.as_ref()) else {
+            return Ok(None);
+        };
+        Ok(Some(
+            Version::from_str(version).map_err(MetadataError::ParseVersion)?,
+        ))
+    }
+
+    /// Returns the `DESCRIPTION` of the `project()` command.
+    fn description(&mut self) -> Result<Option<String>, Self::Error> {
+        Ok(self.ensure_project()?.and_then(|p| p.description.clone()))
+    }
+
+    /// Returns the first sentence of the `DESCRIPTION` of the `project()`
+    /// command.
+    fn summary(&mut self) -> Result<Option<String>, Self::Error> {
+        Ok(self
+            .description()?
+            .map(|description| first_sentence(&description)))
+    }
+
+    /// Returns the `HOMEPAGE_URL` of the `project()` command.
+    fn homepage(&mut self) -> Result<Option<String>, Self::Error> {
+        Ok(self.ensure_project()?.and_then(|p| p.homepage_url.clone()))
+    }
+}
+
+/// Returns the text up to and including the first full stop that is followed
+/// by a space, but at most the first line.
+fn first_sentence(text: &str) -> String {
+    let text = text.trim();
+    let end = text
+        .find(". ")
+        .map(|idx| idx + 1)
+        .into_iter()
+        .chain(text.find('\n'))
+        .min()
+        .unwrap_or(text.len());
+    text[..end].trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_project_command() {
+        let contents = r#"
+# project(commented VERSION 0.0.1)
+#[[
+project(bracket-commented VERSION 0.0.2)
+]]
+cmake_minimum_required(VERSION 3.20)
+project(my_project
+    VERSION 1.2.3
+    DESCRIPTION "A \"quoted\" description"
+    HOMEPAGE_URL [=[https://example.com]=]
+    LANGUAGES CXX)
+project(second VERSION 2.0.0)
+"#;
+
+        assert_eq!(
+            ProjectCommand::parse(contents),
+            Some(ProjectCommand {
+                name: Some("my_project".to_string()),
+                version: Some("1.2.3".to_string()),
+                description: Some("A \"quoted\" description".to_string()),
+                homepage_url: Some("https://example.com".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_project_command_variables() {
+        let contents = "PROJECT(foo VERSION ${FOO_VERSION} LANGUAGES C)";
+
+        assert_eq!(
+            ProjectCommand::parse(contents),
+            Some(ProjectCommand {
+                name: Some("foo".to_string()),
+                ..ProjectCommand::default()
+            })
+        );
+        assert_eq!(ProjectCommand::parse("add_library(foo foo.c)"), None);
+    }
+
+    #[test]
+    fn test_metadata_provider() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        fs_err::write(
+            temp_dir.path().join("CMakeLists.txt"),
+            r#"project(foo VERSION 0.4.1 DESCRIPTION "Foo library")"#,
+        )
+        .unwrap();
+
+        let mut provider = CMakeListsMetadataProvider::new(temp_dir.path());
+        assert_eq!(provider.name().unwrap(), Some("foo".to_string()));
+        assert_eq!(
+            provider.version().unwrap(),
+            Some(Version::from_str("0.4.1").unwrap())
+        );
+        assert_eq!(provider.summary().unwrap(), Some("Foo library".to_string()));
+        assert_eq!(provider.homepage().unwrap(), None);
+        assert_eq!(
+            provider.input_globs(),
+            BTreeSet::from([String::from("CMakeLists.txt")])
+        );
+    }
+
+    #[test]
+    fn test_summary_is_first_sentence() {
+        assert_eq!(first_sentence("Foo library"), "Foo library");
+        assert_eq!(
+            first_sentence("Foo library. Supports v1.2 of the bar format."),
+            "Foo library."
+        );
+        assert_eq!(first_sentence("Foo library\nfor bar"), "Foo library");
+    }
+}
@@ -0,0 +1,422 @@
+//! Support for [CMake presets](https://cmake.org/cmake/help/latest/manual/cmake-presets.7.html).
+//!
+//! Only the information that is required to validate the preset that was
+//! selected in the backend configuration is read. Resolving the configure
+//! presets themselves (inheritance, macros, conditions) is left to CMake.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::{Component, Path, PathBuf},
+};
+
+use miette::{Context, IntoDiagnostic};
+use serde::{Deserialize, Serialize};
+
+/// The names of the files that CMake reads presets from.
+pub const PRESET_FILES: [&str; 2] = ["CMakePresets.json", "CMakeUserPresets.json"];
+
+/// The fields of a build preset that `cmake --build --preset` would use, but
+/// that the backend cannot honor because it builds the install target itself.
+const UNSUPPORTED_BUILD_PRESET_FIELDS: [&str; 8] = [
+    "targets",
+    "jobs",
+    "cleanFirst",
+    "verbose",
+    "nativeToolOptions",
+    "resolvePackageReferences",
+    "environment",
+    "inheritConfigureEnvironment",
+];
+
+/// The relevant subset of a `CMakePresets.json` or `CMakeUserPresets.json`
+/// file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PresetsFile {
+    #[serde(default)]
+    include: Vec<PathBuf>,
+    #[serde(default)]
+    configure_presets: Vec<Preset>,
+    #[serde(default)]
+    build_presets: Vec<Preset>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Preset {
+    name: String,
+    #[serde(default)]
+    hidden: bool,
+    #[serde(default)]
+    inherits: Inherits,
+    configure_preset: Option<String>,
+    configuration: Option<String>,
+    /// All other fields of the preset.
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+/// The `inherits` field of a preset, which is either a single name or a list
+/// of names.
+#[derive(Debug, Default, Deserialize)]
+#[serde(untagged)]
+enum Inherits {
+    #[default]
+    None,
+    Single(String),
+    Multiple(Vec<String>),
+}
+
+impl Inherits {
+    fn names(&self) -> &[String] {
+        match self {
+            Inherits::None => &[],
+            Inherits::Single(name) => std::slice::from_ref(name),
+            Inherits::Multiple(names) => names,
+        }
+    }
+}
+
+/// All presets that are defined for a project.
+#[derive(Debug, Default)]
+pub struct CMakePresets {
+    configure_presets: Vec<Preset>,
+    build_presets: Vec<Preset>,
+    /// The preset files that were read, including the included ones.
+    files: Vec<PathBuf>,
+}
+
+/// The presets that are used to configure and build a project.
+///
+/// The project is not built with `cmake --build --preset`, because that
+/// builds in the binary directory of the configure preset, which the backend
+/// replaces with its own build directory. Only the `configuration` of the
+/// build preset is used instead, and a build preset that sets any of
+/// [`UNSUPPORTED_BUILD_PRESET_FIELDS`] is rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SelectedPreset {
+    /// The name of the configure preset, passed to `cmake --preset`.
+    pub configure: String,
+    /// The configuration of the build preset, if one exists, passed to
+    /// `cmake --build --config` for multi-config generators.
+    pub configuration: Option<String>,
+}
+
+impl CMakePresets {
+    /// Reads the presets of the project located in `source_dir`.
+    ///
+    /// Both `CMakePresets.json` and `CMakeUserPresets.json` are read if they
+    /// exist, including any files they `include`.
+    pub fn from_source_dir(source_dir: &Path) -> miette::Result<Self> {
+        let mut presets = Self::default();
+        for file_name in PRESET_FILES {
+            let path = source_dir.join(file_name);
+            if path.is_file() {
+                presets.read_file(&path)?;
+            }
+        }
+        Ok(presets)
+    }
+
+    fn read_file(&mut self, path: &Path) -> miette::Result<()> {
+        let path = normalize_path(path);
+        if self.files.contains(&path) {
+            return Ok(());
+        }
+        self.files.push(path.clone());
+        let path = path.as_path();
+
+        let contents = fs_err::read_to_string(path).into_diagnostic()?;
+        let file: PresetsFile = serde_json::from_str(&contents)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("failed to parse '{}'", path.display()))?;
+
+        let parent = path.parent().unwrap_or(Path::new("."));
+        for include in &file.include {
+            self.read_file(&parent.join(include))?;
+        }
+
+        self.configure_presets.extend(file.configure_presets);
+        self.build_presets.extend(file.build_presets);
+        Ok(())
+    }
+
+    /// Returns the input globs of the presets, relative to `source_dir`.
+    ///
+    /// These are the preset files that CMake looks for, even if they don't
+    /// exist yet, and every file that they include. Included files outside
+    /// of `source_dir` are skipped.
+    pub fn input_globs(&self, source_dir: &Path) -> BTreeSet<String> {
+        let source_dir = normalize_path(source_dir);
+        let included = self.files.iter().filter_map(|path| {
+            let relative = path.strip_prefix(&source_dir).ok()?;
+            Some(globset::escape(
+                &relative.display().to_string().replace('\\', "/"),
+            ))
+        });
+        PRESET_FILES
+            .map(String::from)
+            .into_iter()
+            .chain(included)
+            .collect()
+    }
+
+    /// Validates that `name` refers to a configure preset and determines the
+    /// build configuration from the build preset that belongs to it.
+    ///
+    /// A build preset with the same name is preferred, otherwise the first
+    /// build preset that references the configure preset is used. Without a
+    /// build preset the default configuration of the generator is built.
+    ///
+    /// The fields of the build preset are resolved through `inherits`, and
+    /// an error is returned if it sets a field that the backend ignores.
+    pub fn select(&self, name: &str) -> miette::Result<SelectedPreset> {
+        let visible = |presets: &[Preset]| -> Vec<String> {
+            presets
+                .iter()
+                .filter(|p| !p.hidden)
+                .map(|p| p.name.clone())
+                .collect()
+        };
+
+        if !self
+            .configure_presets
+            .iter()
+            .any(|p| p.name == name && !p.hidden)
+        {
+            let available = visible(&self.configure_presets);
+            return Err(miette::miette!(
+                help = if available.is_empty() {
+                    String::from("no configure presets are defined")
+                } else {
+                    format!("available configure presets: {}", available.join(", "))
+                },
+                "the CMake configure preset '{name}' does not exist"
+            ));
+        }
+
+        let build = self
+            .build_presets
+            .iter()
+            .filter(|p| !p.hidden)
+            .find(|p| p.name == name)
+            .or_else(|| {
+                self.build_presets
+                    .iter()
+                    .filter(|p| !p.hidden)
+                    .find(|p| p.configure_preset.as_deref() == Some(name))
+            });
+
+        let ancestors = build
+            .map(|build| self.build_preset_ancestors(build))
+            .unwrap_or_default();
+        for preset in &ancestors {
+            if let Some(field) = UNSUPPORTED_BUILD_PRESET_FIELDS
+                .into_iter()
+                .find(|field| preset.other.contains_key(*field))
+            {
+                return Err(miette::miette!(
+                    help = format!(
+                        "the backend only uses the `configuration` of a build preset, remove `{field}` from it"
+                    ),
+                    "the CMake build preset '{}' sets `{field}`, which is not supported",
+                    preset.name
+                ));
+            }
+        }
+
+        Ok(SelectedPreset {
+            configure: name.to_string(),
+            configuration: ancestors
+                .iter()
+                .find_map(|preset| preset.configuration.clone()),
+        })
+    }
+
+    /// Returns the build preset followed by the presets it inherits from, in
+    /// the order in which CMake looks up their fields. Every preset is
+    /// returned once, even if it is inherited from multiple times.
+    fn build_preset_ancestors<'a>(&'a self, preset: &'a Preset) -> Vec<&'a Preset> {
+        let mut ancestors = vec![preset];
+        let mut index = 0;
+        while let Some(current) = ancestors.get(index) {
+            let parents = current
+                .inherits
+                .names()
+                .iter()
+                .filter_map(|name| self.build_presets.iter().find(|p| &p.name == name))
+                .filter(|parent| !ancestors.iter().any(|p| std::ptr::eq(*p, *parent)))
+                .collect::<Vec<_>>();
+            ancestors.extend(parents);
+            index += 1;
+        }
+        ancestors
+    }
+}
+
+/// Resolves the `.` and `..` components of a path without accessing the
+/// file system, so that a file that is included twice is only read once.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_presets(dir: &Path, file_name: &str, contents: serde_json::Value) {
+        fs_err::write(dir.join(file_name), contents.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_select_preset() {
+        let dir = tempfile::tempdir().unwrap();
+        write_presets(
+            dir.path(),
+            "CMakePresets.json",
+            serde_json::json!({
+                "version": 3,
+                "configurePresets": [
+                    { "name": "base", "hidden": true },
+                    { "name": "release", "inherits": "base" },
+                    { "name": "debug", "inherits": "base" }
+                ],
+                "buildPresets": [
+                    { "name": "release", "configurePreset": "release" },
+                    { "name": "build-debug", "configurePreset": "debug", "inherits": "with-debug" },
+                    { "name": "with-debug", "hidden": true, "configuration": "Debug" }
+                ]
+            }),
+        );
+        write_presets(
+            dir.path(),
+            "CMakeUserPresets.json",
+            serde_json::json!({
+                "version": 3,
+                "configurePresets": [{ "name": "local", "inherits": "release" }]
+            }),
+        );
+
+        let presets = CMakePresets::from_source_dir(dir.path()).unwrap();
+
+        assert_eq!(
+            presets.select("release").unwrap(),
+            SelectedPreset {
+                configure: "release".to_string(),
+                configuration: None,
+            }
+        );
+        assert_eq!(
+            presets.select("debug").unwrap(),
+            SelectedPreset {
+                configure: "debug".to_string(),
+                configuration: Some("Debug".to_string()),
+            }
+        );
+
+        // Hidden and unknown presets cannot be selected
+        let err = presets.select("base").unwrap_err();
+        assert!(err.to_string().contains("'base' does not exist"));
+        assert!(presets.select("missing").is_err());
+
+        // User presets are read, a build preset is optional
+        assert_eq!(
+            presets.select("local").unwrap(),
+            SelectedPreset {
+                configure: "local".to_string(),
+                configuration: None,
+            }
+        );
+    }
+
+    #[test]
+    fn test_unsupported_build_preset_fields_are_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        write_presets(
+            dir.path(),
+            "CMakePresets.json",
+            serde_json::json!({
+                "version": 3,
+                "configurePresets": [{ "name": "release" }, { "name": "docs" }],
+                "buildPresets": [
+                    { "name": "parallel", "hidden": true, "jobs": 8 },
+                    { "name": "release", "inherits": ["parallel"], "configuration": "Release" },
+                    { "name": "docs", "targets": ["docs"] }
+                ]
+            }),
+        );
+
+        let presets = CMakePresets::from_source_dir(dir.path()).unwrap();
+
+        // Also when the field is inherited
+        let err = presets.select("release").unwrap_err();
+        assert!(err.to_string().contains("'parallel' sets `jobs`"));
+        let err = presets.select("docs").unwrap_err();
+        assert!(err.to_string().contains("'docs' sets `targets`"));
+    }
+
+    #[test]
+    fn test_included_presets_are_read() {
+        let dir = tempfile::tempdir().unwrap();
+        write_presets(
+            dir.path(),
+            "CMakePresets.json",
+            serde_json::json!({
+                "version": 4,
+                "include": ["presets/ci.json"]
+            }),
+        );
+        fs_err::create_dir_all(dir.path().join("presets")).unwrap();
+        write_presets(
+            &dir.path().join("presets"),
+            "ci.json",
+            serde_json::json!({
+                "version": 4,
+                "include": ["../presets/common.json"],
+                "configurePresets": [{ "name": "ci", "inherits": "common" }],
+                "buildPresets": [{ "name": "ci", "configurePreset": "ci", "configuration": "Release" }]
+            }),
+        );
+        write_presets(
+            &dir.path().join("presets"),
+            "common.json",
+            serde_json::json!({
+                "version": 4,
+                "include": ["ci.json"],
+                "configurePresets": [{ "name": "common", "hidden": true }]
+            }),
+        );
+
+        let presets = CMakePresets::from_source_dir(dir.path()).unwrap();
+        assert_eq!(
+            presets.select("ci").unwrap().configuration,
+            Some("Release".to_string())
+        );
+
+        // Every file that was read is an input, each of them once
+        assert_eq!(
+            presets.input_globs(dir.path()),
+            BTreeSet::from([
+                "CMakePresets.json".to_string(),
+                "CMakeUserPresets.json".to_string(),
+                "presets/ci.json".to_string(),
+                "presets/common.json".to_string(),
+            ])
+        );
+    }
+}
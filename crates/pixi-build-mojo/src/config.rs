@@ -5,34 +5,36 @@ use std::{
 
 use indexmap::IndexMap;
 use miette::Error;
-use pixi_build_backend::generated_recipe::BackendConfig;
+use pixi_build_backend::{backend_config, common::CommonOptions, generated_recipe::BackendConfig};
 use serde::{Deserialize, Serialize};
 
-/// Top level config struct for the Mojo backend.
-#[derive(Debug, Default, Deserialize, Clone)]
-#[serde(rename_all = "kebab-case")]
-pub struct MojoBackendConfig {
-    /// Environment Variables
-    #[serde(default)]
-    pub env: IndexMap<String, String>,
+backend_config! {
+    /// Top level config struct for the Mojo backend.
+    #[derive(Debug, Default, Clone)]
+    #[serde(rename_all = "kebab-case")]
+    pub struct MojoBackendConfig {
+        /// Environment Variables
+        #[serde(default)]
+        pub env: IndexMap<String, String>,
 
-    /// Dir that can be specified for outputting pixi debug state.
-    #[serde(alias = "debug_dir")]
-    pub debug_dir: Option<PathBuf>,
+        /// Dir that can be specified for outputting pixi debug state.
+        #[serde(alias = "debug_dir")]
+        pub debug_dir: Option<PathBuf>,
 
-    /// Extra input globs to include in addition to the default ones.
-    #[serde(default)]
-    pub extra_input_globs: Vec<String>,
+        /// Extra input globs to include in addition to the default ones.
+        #[serde(default)]
+        pub extra_input_globs: Vec<String>,
 
-    /// Binary executables to produce.
-    pub bins: Option<Vec<MojoBinConfig>>,
+        /// Binary executables to produce.
+        pub bins: Option<Vec<MojoBinConfig>>,
 
-    /// Packages to produce.
-    pub pkg: Option<MojoPkgConfig>,
+        /// Packages to produce.
+        pub pkg: Option<MojoPkgConfig>,
 
-    /// List of compilers to use (e.g., ["mojo", "c", "cxx"])
-    /// If not specified, defaults to ["mojo"]
-    pub compilers: Option<Vec<String>>,
+        /// List of compilers to use (e.g., ["mojo", "c", "cxx"])
+        /// If not specified, defaults to ["mojo"]
+        pub compilers: Option<Vec<String>>,
+    }
 }
 
 impl BackendConfig for MojoBackendConfig {
@@ -40,6 +42,10 @@ impl BackendConfig for MojoBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn common_options(&self) -> Option<&CommonOptions> {
+        Some(&self.common)
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values override base values using the following rules:
     ///
@@ -50,6 +56,7 @@ impl BackendConfig for MojoBackendConfig {
     ///   Any set-settings on the platform specific pkg override base
     ///   Any bins found only in target_config will be kept
     /// - pkg: Any set-settings on the platform specific pkg override base
+    /// - common options: see [`CommonOptions::merge`]
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
@@ -128,6 +135,7 @@ impl BackendConfig for MojoBackendConfig {
                 .compilers
                 .clone()
                 .or_else(|| self.compilers.clone()),
+            common: self.common.merge(&target_config.common),
         })
     }
 }
@@ -4,16 +4,17 @@ mod config;
 use build_script::BuildScriptContext;
 use config::{MojoBackendConfig, clean_project_name};
 use miette::{Error, IntoDiagnostic};
-use pixi_build_backend::generated_recipe::DefaultMetadataProvider;
 use pixi_build_backend::{
     compilers::add_compilers_and_stdlib_to_requirements,
     generated_recipe::{GenerateRecipe, GeneratedRecipe, PythonParams},
+    git_metadata::GitVersionMetadataProvider,
+    input_globs::input_globs_respecting_ignores,
     intermediate_backend::IntermediateBackendInstantiator,
 };
 use pixi_build_types::ProjectModelV1;
 use rattler_build::NormalizedKey;
 use rattler_conda_types::{ChannelUrl, PackageName, Platform};
-use recipe_stage0::recipe::{ConditionalRequirements, Script};
+use recipe_stage0::recipe::{ConditionalRequirements, PackageContents, Script, Test};
 use std::collections::HashSet;
 use std::path::PathBuf;
 use std::{collections::BTreeSet, path::Path, sync::Arc};
@@ -34,9 +35,11 @@ impl GenerateRecipe for MojoGenerator {
         variants: &HashSet<NormalizedKey>,
         _channels: Vec<ChannelUrl>,
     ) -> miette::Result<GeneratedRecipe> {
+        // The version is derived from the git tags of the source directory if
+        // the project model doesn't define one.
+        let mut git_metadata = GitVersionMetadataProvider::new(&manifest_root);
         let mut generated_recipe =
-            GeneratedRecipe::from_model(model.clone(), &mut DefaultMetadataProvider)
-                .into_diagnostic()?;
+            GeneratedRecipe::from_model(model.clone(), &mut git_metadata).into_diagnostic()?;
 
         let cleaned_project_name = clean_project_name(
             generated_recipe
@@ -50,6 +53,30 @@ impl GenerateRecipe for MojoGenerator {
         // Auto-derive bins and pkg fields/configs if needed
         let (bins, pkg) = config.auto_derive(&manifest_root, &cleaned_project_name)?;
 
+        // Make sure that everything we build ends up in the package
+        let installed_files = bins
+            .iter()
+            .flatten()
+            .filter_map(|bin| bin.name.as_ref())
+            .map(|name| format!("bin/{name}"))
+            .chain(
+                pkg.iter()
+                    .filter_map(|pkg| pkg.name.as_ref())
+                    .map(|name| format!("lib/mojo/{name}.mojopkg")),
+            )
+            .map(|file| file.parse())
+            .collect::<Result<Vec<_>, _>>()
+            .into_diagnostic()?;
+        if !installed_files.is_empty() {
+            generated_recipe.recipe.tests.push(Test {
+                package_contents: Some(PackageContents {
+                    files: Some(installed_files),
+                    ..PackageContents::default()
+                }),
+                ..Test::default()
+            });
+        }
+
         // Add compiler
         let requirements = &mut generated_recipe.recipe.requirements;
         let resolved_requirements = ConditionalRequirements::resolve(
@@ -111,23 +138,46 @@ impl GenerateRecipe for MojoGenerator {
         };
 
         generated_recipe.build_input_globs = Self::globs().collect::<BTreeSet<_>>();
+        generated_recipe
+            .metadata_input_globs
+            .extend(Self::auto_derive_globs(&cleaned_project_name));
+        generated_recipe
+            .metadata_input_globs
+            .extend(git_metadata.input_globs());
 
         Ok(generated_recipe)
     }
 
-    fn extract_input_globs_from_build(
+    fn extract_input_globs_from_source(
         &self,
         config: &Self::Config,
+        source_dir: impl AsRef<Path>,
         _workdir: impl AsRef<Path>,
         _editable: bool,
     ) -> miette::Result<BTreeSet<String>> {
-        Ok(Self::globs()
-            .chain(config.extra_input_globs.clone())
-            .collect())
+        // The packages are built directly into the prefix, the backend has no
+        // output directory in the sources.
+        Ok(input_globs_respecting_ignores(
+            source_dir.as_ref(),
+            Self::globs(),
+            config.extra_input_globs.clone(),
+            &[],
+        ))
     }
 }
 
 impl MojoGenerator {
+    /// The files from which [`MojoBackendConfig::auto_derive`] derives the
+    /// binary and the package of the project.
+    fn auto_derive_globs(project_name: &str) -> impl Iterator<Item = String> {
+        [
+            String::from("main.{mojo,🔥}"),
+            format!("{project_name}/__init__.{{mojo,🔥}}"),
+            String::from("src/__init__.{mojo,🔥}"),
+        ]
+        .into_iter()
+    }
+
     fn globs() -> impl Iterator<Item = String> {
         [
             // Source files
@@ -170,7 +220,13 @@ mod tests {
 
         let generator = MojoGenerator::default();
 
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
 
         insta::assert_debug_snapshot!(result);
     }
@@ -226,6 +282,43 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_metadata_input_globs() {
+        let project_model = project_fixture!({
+            "name": "foo-bar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let manifest_root = tempfile::tempdir().unwrap();
+        std::fs::write(manifest_root.path().join("main.mojo"), "").unwrap();
+
+        let generated_recipe = MojoGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &MojoBackendConfig::default(),
+                manifest_root.path().to_path_buf(),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        // Adding or removing one of these files changes the auto-derived
+        // binary and package.
+        assert_eq!(
+            generated_recipe.metadata_input_globs,
+            BTreeSet::from([
+                "foo_bar/__init__.{mojo,🔥}".to_string(),
+                "main.{mojo,🔥}".to_string(),
+                "src/__init__.{mojo,🔥}".to_string(),
+            ])
+        );
+    }
+
     #[test]
     fn test_mojo_pkg_is_set() {
         let project_model = project_fixture!({
@@ -13,6 +13,9 @@ pub struct BuildScriptContext {
     pub editable: bool,
     pub extra_args: Vec<String>,
     pub manifest_root: PathBuf,
+    /// The compiler cache executable (e.g. `sccache` or `ccache`) to prefix the
+    /// C and C++ compilers with, if any.
+    pub compiler_launcher: Option<String>,
 }
 
 #[derive(Default, Serialize)]
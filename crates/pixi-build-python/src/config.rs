@@ -1,34 +1,35 @@
 use indexmap::IndexMap;
-use pixi_build_backend::generated_recipe::BackendConfig;
-use serde::{Deserialize, Serialize};
+use pixi_build_backend::{backend_config, common::CommonOptions, generated_recipe::BackendConfig};
 use std::path::{Path, PathBuf};
 
-#[derive(Debug, Default, Deserialize, Serialize, Clone)]
-#[serde(rename_all = "kebab-case", deny_unknown_fields)]
-pub struct PythonBackendConfig {
-    /// True if the package should be build as a python noarch package. Defaults
-    /// to `true`.
-    #[serde(default)]
-    pub noarch: Option<bool>,
-    /// Extra args to pass to pip
-    #[serde(default)]
-    pub extra_args: Vec<String>,
-    /// Environment Variables
-    #[serde(default)]
-    pub env: IndexMap<String, String>,
-    /// Deprecated. Setting this has no effect; debug data is always written to
-    /// the `debug` subdirectory of the work directory.
-    #[serde(alias = "debug_dir")]
-    pub debug_dir: Option<PathBuf>,
-    /// Extra input globs to include in addition to the default ones
-    #[serde(default)]
-    pub extra_input_globs: Vec<String>,
-    /// List of compilers to use (e.g., ["c", "cxx", "rust"])
-    /// If not specified, no compilers are added (since most Python packages are pure Python)
-    pub compilers: Option<Vec<String>>,
-    /// Ignore the pyproject.toml manifest and rely only on the project model.
-    #[serde(default)]
-    pub ignore_pyproject_manifest: Option<bool>,
+backend_config! {
+    #[derive(Debug, Default, Clone)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    pub struct PythonBackendConfig {
+        /// True if the package should be build as a python noarch package. Defaults
+        /// to `true`.
+        #[serde(default)]
+        pub noarch: Option<bool>,
+        /// Extra args to pass to pip
+        #[serde(default)]
+        pub extra_args: Vec<String>,
+        /// Environment Variables
+        #[serde(default)]
+        pub env: IndexMap<String, String>,
+        /// Deprecated. Setting this has no effect; debug data is always written to
+        /// the `debug` subdirectory of the work directory.
+        #[serde(alias = "debug_dir")]
+        pub debug_dir: Option<PathBuf>,
+        /// Extra input globs to include in addition to the default ones
+        #[serde(default)]
+        pub extra_input_globs: Vec<String>,
+        /// List of compilers to use (e.g., ["c", "cxx", "rust"])
+        /// If not specified, no compilers are added (since most Python packages are pure Python)
+        pub compilers: Option<Vec<String>>,
+        /// Ignore the pyproject.toml manifest and rely only on the project model.
+        #[serde(default)]
+        pub ignore_pyproject_manifest: Option<bool>,
+    }
 }
 
 impl PythonBackendConfig {
@@ -53,6 +54,10 @@ impl BackendConfig for PythonBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn common_options(&self) -> Option<&CommonOptions> {
+        Some(&self.common)
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values override base values using the following rules:
     /// - noarch: Platform-specific takes precedence (critical for cross-platform)
@@ -60,6 +65,7 @@ impl BackendConfig for PythonBackendConfig {
     /// - extra_args: Platform-specific completely replaces base
     /// - debug_dir: Not allowed to have target specific value
     /// - extra_input_globs: Platform-specific completely replaces base
+    /// - common options: see [`CommonOptions::merge`]
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
@@ -90,6 +96,7 @@ impl BackendConfig for PythonBackendConfig {
             ignore_pyproject_manifest: target_config
                 .ignore_pyproject_manifest
                 .or(self.ignore_pyproject_manifest),
+            common: self.common.merge(&target_config.common),
         })
     }
 }
@@ -97,7 +104,7 @@ impl BackendConfig for PythonBackendConfig {
 #[cfg(test)]
 mod tests {
     use super::PythonBackendConfig;
-    use pixi_build_backend::generated_recipe::BackendConfig;
+    use pixi_build_backend::{common::CommonOptions, generated_recipe::BackendConfig};
     use serde_json::json;
     use std::path::PathBuf;
 
@@ -121,6 +128,11 @@ mod tests {
             extra_input_globs: vec!["*.base".to_string()],
             compilers: Some(vec!["c".to_string()]),
             ignore_pyproject_manifest: Some(true),
+            common: CommonOptions {
+                recipe_overlay: Some(PathBuf::from("recipe.patch.yaml")),
+                files: vec!["bin/*".to_string()],
+                ..Default::default()
+            },
         };
 
         let mut target_env = indexmap::IndexMap::new();
@@ -135,6 +147,10 @@ mod tests {
             extra_input_globs: vec!["*.target".to_string()],
             compilers: Some(vec!["cxx".to_string(), "rust".to_string()]),
             ignore_pyproject_manifest: Some(false),
+            common: CommonOptions {
+                recipe_overlay: Some(PathBuf::from("recipe.linux.patch.yaml")),
+                ..Default::default()
+            },
         };
 
         let merged = base_config
@@ -168,6 +184,12 @@ mod tests {
         );
         // ignore_pyproject_manifest should use target value
         assert_eq!(merged.ignore_pyproject_manifest, Some(false));
+
+        // common options are merged by CommonOptions::merge
+        assert_eq!(
+            merged.common,
+            base_config.common.merge(&target_config.common)
+        );
     }
 
     #[test]
@@ -183,6 +205,7 @@ mod tests {
             extra_input_globs: vec!["*.base".to_string()],
             compilers: None,
             ignore_pyproject_manifest: Some(true),
+            common: CommonOptions::default(),
         };
 
         let empty_target_config = PythonBackendConfig::default();
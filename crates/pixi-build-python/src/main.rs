@@ -7,18 +7,23 @@ use config::PythonBackendConfig;
 use miette::IntoDiagnostic;
 use pixi_build_backend::variants::NormalizedKey;
 use pixi_build_backend::{
+    cache::{CompilerCache, add_compiler_cache_to_requirements, detect_compiler_cache},
     compilers::add_compilers_and_stdlib_to_requirements,
-    generated_recipe::{GenerateRecipe, GeneratedRecipe, PythonParams},
+    generated_recipe::{ChainedMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams},
+    git_metadata::GitVersionMetadataProvider,
+    input_globs::input_globs_respecting_ignores,
     intermediate_backend::IntermediateBackendInstantiator,
 };
 use pixi_build_types::ProjectModelV1;
 use pyproject_toml::PyProjectToml;
 use rattler_conda_types::{ChannelUrl, PackageName, Platform, package::EntryPoint};
 use recipe_stage0::matchspec::PackageDependency;
-use recipe_stage0::recipe::{self, ConditionalRequirements, NoArchKind, Python, Script};
+use recipe_stage0::recipe::{
+    self, ConditionalRequirements, NoArchKind, PackageContents, Python, PythonTest, Script, Test,
+};
 use std::collections::HashSet;
 use std::{
-    collections::BTreeSet,
+    collections::{BTreeSet, HashMap},
     path::{Path, PathBuf},
     str::FromStr,
     sync::Arc,
@@ -47,6 +52,31 @@ impl PythonGenerator {
             })
             .collect()
     }
+
+    /// Determines the top-level module of the package from its name.
+    ///
+    /// The module is only returned if it exists in the `src` layout or the
+    /// flat layout, because the name of a distribution does not necessarily
+    /// match the name of the module it provides.
+    pub(crate) fn top_level_module(manifest_root: &Path, package_name: &str) -> Option<String> {
+        let module = package_name.to_lowercase().replace(['-', '.'], "_");
+        PythonGenerator::top_level_module_globs(package_name)
+            .iter()
+            .any(|path| manifest_root.join(path).is_file())
+            .then_some(module)
+    }
+
+    /// Returns the paths, relative to the manifest root, at which
+    /// [`PythonGenerator::top_level_module`] looks for the module.
+    pub(crate) fn top_level_module_globs(package_name: &str) -> [String; 4] {
+        let module = package_name.to_lowercase().replace(['-', '.'], "_");
+        [
+            format!("src/{module}/__init__.py"),
+            format!("src/{module}.py"),
+            format!("{module}/__init__.py"),
+            format!("{module}.py"),
+        ]
+    }
 }
 
 impl GenerateRecipe for PythonGenerator {
@@ -70,10 +100,13 @@ impl GenerateRecipe for PythonGenerator {
                 .ignore_pyproject_manifest
                 .is_some_and(|ignore| ignore),
         );
+        let mut git_metadata = GitVersionMetadataProvider::new(&manifest_root);
 
-        let mut generated_recipe =
-            GeneratedRecipe::from_model(model.clone(), &mut pyproject_metadata_provider)
-                .into_diagnostic()?;
+        let mut generated_recipe = GeneratedRecipe::from_model(
+            model.clone(),
+            &mut ChainedMetadataProvider::new(&mut pyproject_metadata_provider, &mut git_metadata),
+        )
+        .into_diagnostic()?;
 
         let requirements = &mut generated_recipe.recipe.requirements;
 
@@ -139,6 +172,23 @@ impl GenerateRecipe for PythonGenerator {
 
         let build_platform = Platform::current();
 
+        // Use a compiler cache for extension modules if the user configured one.
+        // The build script can only put the cache in front of the compilers
+        // through `CC` and `CXX`, which are not used on Windows.
+        let system_env_vars = std::env::vars().collect::<HashMap<String, String>>();
+        let compiler_cache = if compilers.is_empty() || build_platform.is_windows() {
+            None
+        } else {
+            detect_compiler_cache(
+                &[CompilerCache::Sccache, CompilerCache::Ccache],
+                &config.env,
+                &system_env_vars,
+            )
+        };
+        if let Some(compiler_cache) = &compiler_cache {
+            add_compiler_cache_to_requirements(compiler_cache.cache, &mut requirements.build)?;
+        }
+
         // TODO: remove this env var override as soon as we have profiles
         let editable = std::env::var("BUILD_EDITABLE_PYTHON")
             .map(|val| val == "true")
@@ -154,6 +204,9 @@ impl GenerateRecipe for PythonGenerator {
             editable,
             extra_args: config.extra_args.clone(),
             manifest_root: manifest_root.clone(),
+            compiler_launcher: compiler_cache
+                .as_ref()
+                .map(|compiler_cache| compiler_cache.cache.executable().to_string()),
         }
         .render();
 
@@ -175,6 +228,12 @@ impl GenerateRecipe for PythonGenerator {
             Some(NoArchKind::Python)
         };
 
+        // The entry points are read from the pyproject.toml, adding it also
+        // changes them so it is an input even if it doesn't exist yet.
+        generated_recipe
+            .metadata_input_globs
+            .insert(String::from("pyproject.toml"));
+
         // read pyproject.toml content if it exists
         let pyproject_manifest_path = manifest_root.join("pyproject.toml");
         let pyproject_manifest = if pyproject_manifest_path.exists() {
@@ -187,8 +246,9 @@ impl GenerateRecipe for PythonGenerator {
         };
 
         // Construct python specific settings
+        let entry_points = PythonGenerator::entry_points(pyproject_manifest);
         let python = Python {
-            entry_points: PythonGenerator::entry_points(pyproject_manifest),
+            entry_points: entry_points.clone(),
         };
 
         generated_recipe.recipe.build.python = python;
@@ -197,13 +257,71 @@ impl GenerateRecipe for PythonGenerator {
         generated_recipe.recipe.build.script = Script {
             content: build_script,
             env: config.env.clone(),
-            ..Script::default()
+            secrets: compiler_cache
+                .map(|compiler_cache| compiler_cache.secrets)
+                .unwrap_or_default(),
         };
 
+        // Check that the top-level module of the package is installed and can be
+        // imported
+        let package_name = generated_recipe.recipe.package.name.concrete().cloned();
+        if let Some(package_name) = &package_name {
+            generated_recipe
+                .metadata_input_globs
+                .extend(PythonGenerator::top_level_module_globs(package_name));
+        }
+        if let Some(module) = package_name
+            .as_deref()
+            .and_then(|name| PythonGenerator::top_level_module(&manifest_root, name))
+        {
+            generated_recipe.recipe.tests.push(Test {
+                package_contents: Some(PackageContents {
+                    site_packages: Some(vec![module.parse().into_diagnostic()?]),
+                    ..PackageContents::default()
+                }),
+                ..Test::default()
+            });
+            generated_recipe.recipe.tests.push(Test {
+                python: Some(PythonTest {
+                    imports: vec![module.parse().into_diagnostic()?],
+                    pip_check: Some(false),
+                }),
+                ..Test::default()
+            });
+        }
+
+        // Check that the entry points are installed. A noarch package only gets
+        // its entry points when it is installed, so they are not part of it.
+        if generated_recipe.recipe.build.noarch.is_none() && !entry_points.is_empty() {
+            generated_recipe.recipe.tests.push(Test {
+                package_contents: Some(PackageContents {
+                    files: Some(
+                        entry_points
+                            .iter()
+                            .map(|entry_point| {
+                                if host_platform.is_windows() {
+                                    format!("Scripts/{}.exe", entry_point.command)
+                                } else {
+                                    format!("bin/{}", entry_point.command)
+                                }
+                                .parse()
+                            })
+                            .collect::<Result<_, _>>()
+                            .into_diagnostic()?,
+                    ),
+                    ..PackageContents::default()
+                }),
+                ..Test::default()
+            });
+        }
+
         // Add the metadata input globs from the MetadataProvider
         generated_recipe
             .metadata_input_globs
             .extend(pyproject_metadata_provider.input_globs());
+        generated_recipe
+            .metadata_input_globs
+            .extend(git_metadata.input_globs());
 
         Ok(generated_recipe)
     }
@@ -213,9 +331,10 @@ impl GenerateRecipe for PythonGenerator {
     /// has a different way of determining the input globs than hatch etc.
     ///
     /// However, lets take everything in the directory as input for now
-    fn extract_input_globs_from_build(
+    fn extract_input_globs_from_source(
         &self,
         config: &Self::Config,
+        source_dir: impl AsRef<Path>,
         _workdir: impl AsRef<Path>,
         editable: bool,
     ) -> miette::Result<BTreeSet<String>> {
@@ -248,13 +367,17 @@ impl GenerateRecipe for PythonGenerator {
             Vec::from(["**/*.py", "**/*.pyx"])
         };
 
-        Ok(base_globs
-            .iter()
-            .chain(python_globs.iter())
-            .chain(compiler_based_globs.iter())
-            .map(|s| s.to_string())
-            .chain(config.extra_input_globs.clone())
-            .collect())
+        Ok(input_globs_respecting_ignores(
+            source_dir.as_ref(),
+            base_globs
+                .iter()
+                .chain(python_globs.iter())
+                .chain(compiler_based_globs.iter())
+                .map(|s| s.to_string()),
+            config.extra_input_globs.clone(),
+            // The build directory of setuptools
+            &["build"],
+        ))
     }
 }
 
@@ -290,7 +413,13 @@ mod tests {
 
         let generator = PythonGenerator::default();
 
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
 
         insta::assert_debug_snapshot!(result);
     }
@@ -303,7 +432,13 @@ mod tests {
         };
 
         let generator = PythonGenerator::default();
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), true);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            true,
+        );
 
         insta::assert_debug_snapshot!(result);
     }
@@ -627,6 +762,246 @@ version = "0.1.0"
         );
     }
 
+    #[test]
+    fn test_compiler_cache_is_used_for_extensions() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let env = IndexMap::from([("SCCACHE_DIR".to_string(), "/tmp/sccache".to_string())]);
+
+        let generate = |compilers: Option<Vec<String>>| {
+            PythonGenerator::default()
+                .generate_recipe(
+                    &project_model,
+                    &PythonBackendConfig {
+                        env: env.clone(),
+                        compilers,
+                        ignore_pyproject_manifest: Some(true),
+                        ..Default::default()
+                    },
+                    PathBuf::from("."),
+                    Platform::Linux64,
+                    None,
+                    &HashSet::new(),
+                    vec![],
+                )
+                .expect("Failed to generate recipe")
+        };
+
+        let sccache: Item<PackageDependency> = "sccache".parse().unwrap();
+
+        // Pure python packages don't need a compiler cache
+        let generated_recipe = generate(None);
+        assert!(
+            !generated_recipe
+                .recipe
+                .requirements
+                .build
+                .contains(&sccache)
+        );
+
+        let generated_recipe = generate(Some(vec!["c".to_string()]));
+        if Platform::current().is_windows() {
+            // The build script has no way to use the cache on Windows
+            assert!(
+                !generated_recipe
+                    .recipe
+                    .requirements
+                    .build
+                    .contains(&sccache)
+            );
+        } else {
+            assert!(
+                generated_recipe
+                    .recipe
+                    .requirements
+                    .build
+                    .contains(&sccache)
+            );
+            // Only the compilers that are set are prefixed with the cache
+            let script = &generated_recipe.recipe.build.script.content;
+            assert!(script.contains(r#"if [ -n "${CC:-}" ]; then export CC="sccache $CC"; fi"#));
+            assert!(script.contains(r#"if [ -n "${CXX:-}" ]; then export CXX="sccache $CXX"; fi"#));
+        }
+    }
+
+    #[test]
+    fn test_import_test_for_top_level_module() {
+        let project_model = project_fixture!({
+            "name": "foo-bar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let manifest_root = tempfile::tempdir().unwrap();
+        let generate = || {
+            PythonGenerator::default()
+                .generate_recipe(
+                    &project_model,
+                    &PythonBackendConfig::default_with_ignore_pyproject_manifest(),
+                    manifest_root.path().to_path_buf(),
+                    Platform::Linux64,
+                    None,
+                    &HashSet::new(),
+                    vec![],
+                )
+                .expect("Failed to generate recipe")
+        };
+
+        // Without a matching module no test is added
+        assert!(generate().recipe.tests.is_empty());
+
+        let module_dir = manifest_root.path().join("src").join("foo_bar");
+        fs_err::create_dir_all(&module_dir).unwrap();
+        fs_err::write(module_dir.join("__init__.py"), "").unwrap();
+
+        let generated_recipe = generate();
+        let package_contents = generated_recipe.recipe.tests[0]
+            .package_contents
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            package_contents.site_packages,
+            Some(vec![Item::Value(Value::Concrete("foo_bar".to_string()))])
+        );
+        let python_test = generated_recipe.recipe.tests[1].python.as_ref().unwrap();
+        assert_eq!(
+            python_test.imports,
+            vec![Item::Value(Value::Concrete("foo_bar".to_string()))]
+        );
+        assert_eq!(python_test.pip_check, Some(false));
+    }
+
+    #[test]
+    fn test_package_contents_for_entry_points() {
+        let project_model = project_fixture!({
+            "name": "foo-bar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let manifest_root = tempfile::tempdir().unwrap();
+        fs_err::write(
+            manifest_root.path().join("pyproject.toml"),
+            "[project]\nname = \"foo-bar\"\n\n[project.scripts]\nfoo = \"foo_bar:main\"\n",
+        )
+        .unwrap();
+
+        let generate = |noarch: bool, host_platform: Platform| {
+            PythonGenerator::default()
+                .generate_recipe(
+                    &project_model,
+                    &PythonBackendConfig {
+                        noarch: Some(noarch),
+                        ..PythonBackendConfig::default_with_ignore_pyproject_manifest()
+                    },
+                    manifest_root.path().to_path_buf(),
+                    host_platform,
+                    None,
+                    &HashSet::new(),
+                    vec![],
+                )
+                .expect("Failed to generate recipe")
+        };
+
+        // The entry points of a noarch package are created on installation
+        assert!(generate(true, Platform::Linux64).recipe.tests.is_empty());
+
+        let generated_recipe = generate(false, Platform::Linux64);
+        let package_contents = generated_recipe.recipe.tests[0]
+            .package_contents
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            package_contents.files,
+            Some(vec![Item::Value(Value::Concrete("bin/foo".to_string()))])
+        );
+
+        let generated_recipe = generate(false, Platform::Win64);
+        let package_contents = generated_recipe.recipe.tests[0]
+            .package_contents
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            package_contents.files,
+            Some(vec![Item::Value(Value::Concrete(
+                "Scripts/foo.exe".to_string()
+            ))])
+        );
+    }
+
+    #[test]
+    fn test_metadata_input_globs() {
+        let project_model = project_fixture!({
+            "name": "foo-bar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let manifest_root = tempfile::tempdir().unwrap();
+        fs_err::write(
+            manifest_root.path().join("pyproject.toml"),
+            "[project]\nname = \"foo-bar\"\n\n[project.scripts]\nfoo = \"foo_bar:main\"\n",
+        )
+        .unwrap();
+
+        let generated_recipe = PythonGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &PythonBackendConfig::default_with_ignore_pyproject_manifest(),
+                manifest_root.path().to_path_buf(),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        // The entry points and the import test depend on these files, even if
+        // the metadata comes from the project model.
+        assert_eq!(
+            generated_recipe.metadata_input_globs,
+            BTreeSet::from([
+                "foo_bar.py".to_string(),
+                "foo_bar/__init__.py".to_string(),
+                "pyproject.toml".to_string(),
+                "src/foo_bar.py".to_string(),
+                "src/foo_bar/__init__.py".to_string(),
+            ])
+        );
+
+        // Creating the pyproject.toml changes the entry points, so it is an
+        // input before it exists.
+        fs_err::remove_file(manifest_root.path().join("pyproject.toml")).unwrap();
+        let generated_recipe = PythonGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &PythonBackendConfig::default_with_ignore_pyproject_manifest(),
+                manifest_root.path().to_path_buf(),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+        assert!(
+            generated_recipe
+                .metadata_input_globs
+                .contains("pyproject.toml")
+        );
+    }
+
     #[test]
     fn test_default_no_compilers_when_not_specified() {
         let project_model = project_fixture!({
@@ -777,7 +1152,13 @@ version = "0.1.0"
             ..Default::default()
         };
         let generator = PythonGenerator::default();
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
         insta::assert_debug_snapshot!(result);
     }
 
@@ -789,7 +1170,13 @@ version = "0.1.0"
             ..Default::default()
         };
         let generator = PythonGenerator::default();
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
         insta::assert_debug_snapshot!(result);
     }
 
@@ -801,7 +1188,13 @@ version = "0.1.0"
             ..Default::default()
         };
         let generator = PythonGenerator::default();
-        let result = generator.extract_input_globs_from_build(&config, PathBuf::new(), false);
+        let source_dir = tempfile::tempdir().unwrap();
+        let result = generator.extract_input_globs_from_source(
+            &config,
+            source_dir.path(),
+            PathBuf::new(),
+            false,
+        );
         insta::assert_debug_snapshot!(result);
     }
 }
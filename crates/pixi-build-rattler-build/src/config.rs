@@ -1,4 +1,7 @@
-use pixi_build_backend::generated_recipe::BackendConfig;
+use pixi_build_backend::{
+    common::{ArchiveTypeConfig, CompressionLevelConfig, PackagingConfig},
+    generated_recipe::BackendConfig,
+};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
@@ -12,6 +15,13 @@ pub struct RattlerBuildBackendConfig {
     /// Extra input globs to include in addition to the default ones
     #[serde(default)]
     pub extra_input_globs: Vec<String>,
+    /// The format of the package archive, defaults to `conda`
+    pub archive_type: Option<ArchiveTypeConfig>,
+    /// The compression level of the package archive
+    pub compression_level: Option<CompressionLevelConfig>,
+    /// Builds with a fixed timestamp so that the package is reproducible,
+    /// enabled by `SOURCE_DATE_EPOCH` if not set.
+    pub reproducible: Option<bool>,
 }
 
 impl BackendConfig for RattlerBuildBackendConfig {
@@ -19,10 +29,24 @@ impl BackendConfig for RattlerBuildBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn packaging(&self) -> PackagingConfig {
+        PackagingConfig {
+            archive_type: self.archive_type,
+            compression_level: self.compression_level,
+        }
+    }
+
+    fn reproducible(&self) -> Option<bool> {
+        self.reproducible
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values override base values using the following rules:
     /// - debug_dir: Not allowed to have target specific value
     /// - extra_input_globs: Platform-specific completely replaces base
+    /// - archive_type: Platform-specific overrides base
+    /// - compression_level: Platform-specific overrides base
+    /// - reproducible: Platform-specific overrides base
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
@@ -35,13 +59,16 @@ impl BackendConfig for RattlerBuildBackendConfig {
             } else {
                 target_config.extra_input_globs.clone()
             },
+            archive_type: target_config.archive_type.or(self.archive_type),
+            compression_level: target_config.compression_level.or(self.compression_level),
+            reproducible: target_config.reproducible.or(self.reproducible),
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::RattlerBuildBackendConfig;
+    use super::{ArchiveTypeConfig, RattlerBuildBackendConfig};
     use pixi_build_backend::generated_recipe::BackendConfig;
     use serde_json::json;
     use std::path::PathBuf;
@@ -57,11 +84,17 @@ mod tests {
         let base_config = RattlerBuildBackendConfig {
             debug_dir: Some(PathBuf::from("/base/debug")),
             extra_input_globs: vec!["*.base".to_string()],
+            archive_type: None,
+            compression_level: None,
+            reproducible: None,
         };
 
         let target_config = RattlerBuildBackendConfig {
             debug_dir: None,
             extra_input_globs: vec!["*.target".to_string()],
+            archive_type: Some(ArchiveTypeConfig::TarBz2),
+            compression_level: None,
+            reproducible: None,
         };
 
         let merged = base_config
@@ -73,6 +106,9 @@ mod tests {
 
         // extra_input_globs should be completely overridden
         assert_eq!(merged.extra_input_globs, vec!["*.target".to_string()]);
+
+        // archive_type should be overridden by target
+        assert_eq!(merged.archive_type, Some(ArchiveTypeConfig::TarBz2));
     }
 
     #[test]
@@ -80,6 +116,9 @@ mod tests {
         let base_config = RattlerBuildBackendConfig {
             debug_dir: Some(PathBuf::from("/base/debug")),
             extra_input_globs: vec!["*.base".to_string()],
+            archive_type: None,
+            compression_level: None,
+            reproducible: None,
         };
 
         let empty_target_config = RattlerBuildBackendConfig::default();
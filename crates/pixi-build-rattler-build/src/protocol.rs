@@ -8,9 +8,12 @@ use crate::{config::RattlerBuildBackendConfig, rattler_build::RattlerBuildBacken
 use miette::{Context, IntoDiagnostic};
 use pixi_build_backend::specs_conversion::from_build_v1_args_to_finalized_dependencies;
 use pixi_build_backend::{
+    common::platforms_with_virtual_packages,
     dependencies::{convert_binary_dependencies, convert_dependencies},
+    generated_recipe::BackendConfig,
     intermediate_backend::{conda_build_v1_directories, find_matching_output},
     protocol::{Protocol, ProtocolInstantiator},
+    reproducible::{normalize_package, reproducible_timestamp},
     tools::LoadedVariantConfig,
 };
 use pixi_build_types::{
@@ -29,14 +32,14 @@ use rattler_build::{
     build::{WorkingDirectoryBehavior, run_build},
     console_utils::LoggingOutputHandler,
     hash::HashInfo,
-    metadata::{BuildConfiguration, Debug, Output, PlatformWithVirtualPackages},
+    metadata::{BuildConfiguration, Debug, Output},
     recipe::{Jinja, ParsingError, Recipe, parser::find_outputs_from_src, variable::Variable},
     selectors::SelectorConfig,
     tool_configuration::Configuration,
-    types::{PackageIdentifier, PackagingSettings},
+    types::PackageIdentifier,
     variant_config::{ParseErrors, VariantConfig},
 };
-use rattler_conda_types::{Platform, compression_level::CompressionLevel, package::ArchiveType};
+use rattler_conda_types::Platform;
 use tracing::warn;
 pub struct RattlerBuildBackendInstantiator {
     logging_output_handler: LoggingOutputHandler,
@@ -332,30 +335,30 @@ impl Protocol for RattlerBuildBackend {
             .with_environments_externally_managed(true)
             .finish();
 
+        // Use the same virtual packages that the build and host environments
+        // were solved with.
+        let (build_platform, host_platform) =
+            platforms_with_virtual_packages(build_platform, host_platform)?;
+
+        // In reproducible mode the build uses a fixed timestamp instead of the
+        // current time.
+        let timestamp = reproducible_timestamp(self.config.reproducible(), &self.manifest_root)?;
+
         let output = Output {
             recipe: discovered_output.recipe,
             build_configuration: BuildConfiguration {
                 target_platform: discovered_output.target_platform,
-                host_platform: PlatformWithVirtualPackages {
-                    platform: host_platform,
-                    virtual_packages: vec![],
-                },
-                build_platform: PlatformWithVirtualPackages {
-                    platform: build_platform,
-                    virtual_packages: vec![],
-                },
+                host_platform,
+                build_platform,
                 hash: discovered_output.hash,
                 variant: discovered_output.used_vars.clone(),
                 directories,
-                channels: vec![],
+                channels: params.channels,
                 channel_priority: Default::default(),
                 solve_strategy: Default::default(),
-                timestamp: chrono::Utc::now(),
+                timestamp: timestamp.unwrap_or_else(chrono::Utc::now),
                 subpackages: BTreeMap::new(),
-                packaging_settings: PackagingSettings::from_args(
-                    ArchiveType::Conda,
-                    CompressionLevel::default(),
-                ),
+                packaging_settings: self.config.packaging().packaging_settings()?,
                 store_recipe: false,
                 force_colors: true,
                 sandbox_config: None,
@@ -381,6 +384,16 @@ impl Protocol for RattlerBuildBackend {
             // rattler-build requires a clean work dir
             run_build(output, &tool_config, WorkingDirectoryBehavior::Cleanup).await?;
 
+        // Normalize the metadata of the files in the archive, which
+        // rattler-build takes from the installed files.
+        if let Some(timestamp) = timestamp {
+            normalize_package(
+                &output_path,
+                self.config.packaging().compression_level()?,
+                timestamp,
+            )?;
+        }
+
         Ok(CondaBuildV1Result {
             output_file: output_path,
             input_globs: build_input_globs(
@@ -1,6 +1,8 @@
-use minijinja::Environment;
+use minijinja::{Environment, Error, ErrorKind};
 use serde::Serialize;
 
+use crate::config::InstallExtra;
+
 #[derive(Serialize)]
 pub struct BuildScriptContext {
     /// The location of the source
@@ -17,22 +19,74 @@ pub struct BuildScriptContext {
 
     /// The platform that is running the build.
     pub is_bash: bool,
+
+    /// Additional files to copy into the prefix after installation.
+    pub install_extra: Vec<InstallExtra>,
 }
 
 impl BuildScriptContext {
-    pub fn render(&self) -> String {
-        let env = Environment::new();
+    /// Renders the build script, this fails if a path of `install_extra`
+    /// cannot be used in the script.
+    pub fn render(&self) -> Result<String, Error> {
+        let mut env = Environment::new();
+        env.add_filter("escape_bash", escape_bash);
+        env.add_filter("quote_glob", quote_glob);
+        env.add_filter("escape_cmd", escape_cmd);
         let template = env
             .template_from_str(include_str!("build_script.j2"))
             .unwrap();
-        template.render(self).unwrap().trim().to_string()
+        Ok(template.render(self)?.trim().to_string())
     }
 }
 
+/// Escapes the characters that bash expands inside double quotes.
+fn escape_bash(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '\\' | '"' | '$' | '`') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Quotes a path for bash while leaving the glob characters `*` and `?`
+/// unquoted, so that they are still expanded by the shell.
+fn quote_glob(path: &str) -> String {
+    let mut quoted = String::new();
+    for part in path.split_inclusive(['*', '?']) {
+        let (literal, wildcard) = match part.strip_suffix(['*', '?']) {
+            Some(literal) => (literal, &part[literal.len()..]),
+            None => (part, ""),
+        };
+        if !literal.is_empty() {
+            quoted.push_str(&format!("\"{}\"", escape_bash(literal)));
+        }
+        quoted.push_str(wildcard);
+    }
+    quoted
+}
+
+/// Converts a path to a Windows path that can be used inside double quotes in
+/// `cmd.exe`, where `%` has to be doubled. A `"` cannot be escaped, and is
+/// not allowed in Windows paths anyway.
+fn escape_cmd(path: &str) -> Result<String, Error> {
+    if path.contains('"') {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("the path '{path}' of `install-extra` contains a `\"`"),
+        ));
+    }
+    Ok(path.replace('/', "\\").replace('%', "%%"))
+}
+
 #[cfg(test)]
 mod test {
     use rstest::*;
 
+    use crate::config::InstallExtra;
+
     #[rstest]
     fn test_build_script(#[values(true, false)] is_bash: bool) {
         let context = super::BuildScriptContext {
@@ -41,8 +95,9 @@ mod test {
             has_openssl: false,
             has_sccache: false,
             is_bash,
+            install_extra: vec![],
         };
-        let script = context.render();
+        let script = context.render().unwrap();
 
         let mut settings = insta::Settings::clone_current();
         settings.set_snapshot_suffix(if is_bash { "bash" } else { "cmdexe" });
@@ -59,8 +114,9 @@ mod test {
             has_openssl: false,
             has_sccache: true,
             is_bash,
+            install_extra: vec![],
         };
-        let script = context.render();
+        let script = context.render().unwrap();
 
         let mut settings = insta::Settings::clone_current();
         settings.set_snapshot_suffix(if is_bash { "bash" } else { "cmdexe" });
@@ -77,8 +133,85 @@ mod test {
             has_openssl: true,
             has_sccache: false,
             is_bash,
+            install_extra: vec![],
+        };
+        let script = context.render().unwrap();
+
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_suffix(if is_bash { "bash" } else { "cmdexe" });
+        settings.bind(|| {
+            insta::assert_snapshot!(script);
+        });
+    }
+
+    #[test]
+    fn test_quote_glob() {
+        assert_eq!(
+            super::quote_glob("target/man/my-tool.1"),
+            r#""target/man/my-tool.1""#
+        );
+        assert_eq!(
+            super::quote_glob("target/shell completions/*.bash"),
+            r#""target/shell completions/"*".bash""#
+        );
+        assert_eq!(super::quote_glob("*"), "*");
+        assert_eq!(
+            super::quote_glob("target/$HOME/`id`\\\"*"),
+            r#""target/\$HOME/\`id\`\\\""*"#
+        );
+    }
+
+    #[test]
+    fn test_escape_cmd() {
+        assert_eq!(
+            super::escape_cmd("share/%PATH%/man1").unwrap(),
+            r"share\%%PATH%%\man1"
+        );
+        assert!(super::escape_cmd(r#"share/"man1"#).is_err());
+    }
+
+    #[rstest]
+    fn test_install_extra(#[values(true, false)] is_bash: bool) {
+        let context = super::BuildScriptContext {
+            source_dir: String::from("my-prefix-dir"),
+            extra_args: vec![],
+            has_openssl: false,
+            has_sccache: false,
+            is_bash,
+            install_extra: vec![
+                InstallExtra {
+                    from: String::from("target/completions/*"),
+                    to: String::from("share/bash-completion/completions"),
+                },
+                InstallExtra {
+                    from: String::from("target/man/my-tool.1"),
+                    to: String::from("share/man/man1"),
+                },
+            ],
+        };
+        let script = context.render().unwrap();
+
+        let mut settings = insta::Settings::clone_current();
+        settings.set_snapshot_suffix(if is_bash { "bash" } else { "cmdexe" });
+        settings.bind(|| {
+            insta::assert_snapshot!(script);
+        });
+    }
+
+    #[rstest]
+    fn test_install_extra_directory(#[values(true, false)] is_bash: bool) {
+        let context = super::BuildScriptContext {
+            source_dir: String::from("my-prefix-dir"),
+            extra_args: vec![],
+            has_openssl: false,
+            has_sccache: false,
+            is_bash,
+            install_extra: vec![InstallExtra {
+                from: String::from("target/doc"),
+                to: String::from("share/doc/my-tool"),
+            }],
         };
-        let script = context.render();
+        let script = context.render().unwrap();
 
         let mut settings = insta::Settings::clone_current();
         settings.set_snapshot_suffix(if is_bash { "bash" } else { "cmdexe" });
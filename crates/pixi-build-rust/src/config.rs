@@ -1,31 +1,50 @@
 use indexmap::IndexMap;
-use pixi_build_backend::generated_recipe::BackendConfig;
+use pixi_build_backend::{backend_config, common::CommonOptions, generated_recipe::BackendConfig};
 use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+backend_config! {
+    #[derive(Debug, Clone, Default)]
+    #[serde(rename_all = "kebab-case", deny_unknown_fields)]
+    pub struct RustBackendConfig {
+        /// Extra args to pass for cargo
+        #[serde(default)]
+        pub extra_args: Vec<String>,
+        /// Environment Variables
+        #[serde(default)]
+        pub env: IndexMap<String, String>,
+        /// Deprecated. Setting this has no effect; debug data is always written to
+        /// the `debug` subdirectory of the work directory.
+        #[serde(alias = "debug_dir")]
+        pub debug_dir: Option<PathBuf>,
+        /// Extra input globs to include in addition to the default ones
+        #[serde(default)]
+        pub extra_input_globs: Vec<String>,
+        /// Ignore the cargo manifest and depend only on the project model.
+        #[serde(default)]
+        pub ignore_cargo_manifest: Option<bool>,
+        /// List of compilers to use (e.g., ["rust", "c", "cxx"])
+        /// If not specified, a default will be used
+        pub compilers: Option<Vec<String>>,
+        /// Additional files to copy into the prefix after `cargo install`
+        /// finished (e.g. shell completions or man pages).
+        #[serde(default)]
+        pub install_extra: Vec<InstallExtra>,
+    }
+}
+
+/// A rule that copies files produced by the build into the installation
+/// prefix.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
 #[serde(rename_all = "kebab-case", deny_unknown_fields)]
-pub struct RustBackendConfig {
-    /// Extra args to pass for cargo
-    #[serde(default)]
-    pub extra_args: Vec<String>,
-    /// Environment Variables
-    #[serde(default)]
-    pub env: IndexMap<String, String>,
-    /// Deprecated. Setting this has no effect; debug data is always written to
-    /// the `debug` subdirectory of the work directory.
-    #[serde(alias = "debug_dir")]
-    pub debug_dir: Option<PathBuf>,
-    /// Extra input globs to include in addition to the default ones
-    #[serde(default)]
-    pub extra_input_globs: Vec<String>,
-    /// Ignore the cargo manifest and depend only on the project model.
-    #[serde(default)]
-    pub ignore_cargo_manifest: Option<bool>,
-    /// List of compilers to use (e.g., ["rust", "c", "cxx"])
-    /// If not specified, a default will be used
-    pub compilers: Option<Vec<String>>,
+pub struct InstallExtra {
+    /// The files to copy. Relative paths are resolved against the working
+    /// directory of the build, which also contains cargo's `target`
+    /// directory. May contain glob patterns.
+    pub from: String,
+    /// The destination directory, relative to the installation prefix.
+    pub to: String,
 }
 
 impl RustBackendConfig {
@@ -45,12 +64,18 @@ impl BackendConfig for RustBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn common_options(&self) -> Option<&CommonOptions> {
+        Some(&self.common)
+    }
+
     /// Merge this configuration with a target-specific configuration.
     /// Target-specific values override base values using the following rules:
     /// - extra_args: Platform-specific completely replaces base
     /// - env: Platform env vars override base, others merge
     /// - debug_dir: Not allowed to have target specific value
     /// - extra_input_globs: Platform-specific completely replaces base
+    /// - install_extra: Platform-specific completely replaces base
+    /// - common options: see [`CommonOptions::merge`]
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
@@ -80,14 +105,20 @@ impl BackendConfig for RustBackendConfig {
                 .compilers
                 .clone()
                 .or_else(|| self.compilers.clone()),
+            install_extra: if target_config.install_extra.is_empty() {
+                self.install_extra.clone()
+            } else {
+                target_config.install_extra.clone()
+            },
+            common: self.common.merge(&target_config.common),
         })
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::RustBackendConfig;
-    use pixi_build_backend::generated_recipe::BackendConfig;
+    use super::{InstallExtra, RustBackendConfig};
+    use pixi_build_backend::{common::CommonOptions, generated_recipe::BackendConfig};
     use serde_json::json;
     use std::path::PathBuf;
 
@@ -114,6 +145,43 @@ mod tests {
         assert_eq!(config.debug_dir, Some(PathBuf::from("/path/to/debug")));
     }
 
+    #[test]
+    fn test_install_extra_deserialize() {
+        let json_data = json!({
+            "install-extra": [
+                { "from": "target/completions/*", "to": "share/bash-completion/completions" }
+            ]
+        });
+        let config = serde_json::from_value::<RustBackendConfig>(json_data).unwrap();
+        assert_eq!(
+            config.install_extra,
+            vec![InstallExtra {
+                from: "target/completions/*".to_string(),
+                to: "share/bash-completion/completions".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn test_common_options_deserialize() {
+        let json_data = json!({
+            "extra-args": ["--locked"],
+            "recipe-overlay": "recipe.patch.yaml",
+            "files": ["bin/*"]
+        });
+        let config = serde_json::from_value::<RustBackendConfig>(json_data).unwrap();
+        assert_eq!(config.extra_args, vec!["--locked".to_string()]);
+        assert_eq!(
+            config.common.recipe_overlay,
+            Some(PathBuf::from("recipe.patch.yaml"))
+        );
+        assert_eq!(config.common.files, vec!["bin/*".to_string()]);
+
+        // Unknown fields are still rejected next to the common options
+        let json_data = json!({ "files": ["bin/*"], "unknown-option": true });
+        assert!(serde_json::from_value::<RustBackendConfig>(json_data).is_err());
+    }
+
     #[test]
     fn test_merge_with_target_config() {
         let mut base_env = indexmap::IndexMap::new();
@@ -127,6 +195,15 @@ mod tests {
             extra_input_globs: vec!["*.base".to_string()],
             ignore_cargo_manifest: None,
             compilers: Some(vec!["rust".to_string()]),
+            install_extra: vec![InstallExtra {
+                from: "target/man/*".to_string(),
+                to: "share/man/man1".to_string(),
+            }],
+            common: CommonOptions {
+                recipe_overlay: Some(PathBuf::from("recipe.patch.yaml")),
+                files: vec!["bin/*".to_string()],
+                ..Default::default()
+            },
         };
 
         let mut target_env = indexmap::IndexMap::new();
@@ -140,6 +217,14 @@ mod tests {
             extra_input_globs: vec!["*.target".to_string()],
             ignore_cargo_manifest: Some(true),
             compilers: Some(vec!["c".to_string(), "rust".to_string()]),
+            install_extra: vec![InstallExtra {
+                from: "target/completions/*".to_string(),
+                to: "share/bash-completion/completions".to_string(),
+            }],
+            common: CommonOptions {
+                files: vec!["lib/*".to_string()],
+                ..Default::default()
+            },
         };
 
         let merged = base_config
@@ -171,6 +256,21 @@ mod tests {
             merged.compilers,
             Some(vec!["c".to_string(), "rust".to_string()])
         );
+
+        // install_extra should be completely overridden
+        assert_eq!(
+            merged.install_extra,
+            vec![InstallExtra {
+                from: "target/completions/*".to_string(),
+                to: "share/bash-completion/completions".to_string(),
+            }]
+        );
+
+        // common options are merged by CommonOptions::merge
+        assert_eq!(
+            merged.common,
+            base_config.common.merge(&target_config.common)
+        );
     }
 
     #[test]
@@ -185,6 +285,8 @@ mod tests {
             extra_input_globs: vec!["*.base".to_string()],
             ignore_cargo_manifest: None,
             compilers: Some(vec!["rust".to_string()]),
+            install_extra: vec![],
+            common: CommonOptions::default(),
         };
 
         let empty_target_config = RustBackendConfig::default();
@@ -8,17 +8,16 @@ use metadata::CargoMetadataProvider;
 use miette::IntoDiagnostic;
 use pixi_build_backend::variants::NormalizedKey;
 use pixi_build_backend::{
-    cache::{sccache_envs, sccache_tools},
+    cache::{CompilerCache, add_compiler_cache_to_requirements, detect_compiler_cache},
     compilers::add_compilers_and_stdlib_to_requirements,
-    generated_recipe::{GenerateRecipe, GeneratedRecipe, PythonParams},
+    generated_recipe::{ChainedMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams},
+    git_metadata::GitVersionMetadataProvider,
+    input_globs::input_globs_respecting_ignores,
     intermediate_backend::IntermediateBackendInstantiator,
 };
 use pixi_build_types::ProjectModelV1;
 use rattler_conda_types::{ChannelUrl, Platform};
-use recipe_stage0::{
-    matchspec::PackageDependency,
-    recipe::{ConditionalRequirements, Item, Script},
-};
+use recipe_stage0::recipe::{ConditionalRequirements, PackageContents, Script, Test};
 use std::collections::HashSet;
 use std::{
     collections::{BTreeSet, HashMap},
@@ -49,9 +48,16 @@ impl GenerateRecipe for RustGenerator {
             config.ignore_cargo_manifest.is_some_and(|ignore| ignore),
         );
 
+        // Fall back to a version derived from the git tags of the source
+        // directory if neither the project model nor Cargo.toml define one.
+        let mut git_metadata = GitVersionMetadataProvider::new(&manifest_root);
+
         // Create the recipe
-        let mut generated_recipe =
-            GeneratedRecipe::from_model(model.clone(), &mut cargo_metadata).into_diagnostic()?;
+        let mut generated_recipe = GeneratedRecipe::from_model(
+            model.clone(),
+            &mut ChainedMetadataProvider::new(&mut cargo_metadata, &mut git_metadata),
+        )
+        .into_diagnostic()?;
 
         // we need to add compilers
         let requirements = &mut generated_recipe.recipe.requirements;
@@ -82,57 +88,23 @@ impl GenerateRecipe for RustGenerator {
 
         let has_openssl = resolved_requirements.contains(&"openssl".parse().into_diagnostic()?);
 
-        let mut has_sccache = false;
-
         let config_env = config.env.clone();
 
         let system_env_vars = std::env::vars().collect::<HashMap<String, String>>();
 
-        let all_env_vars = config_env
-            .clone()
-            .into_iter()
-            .chain(system_env_vars.clone())
-            .collect();
+        // Verify if user has set any sccache environment variables. Only sccache
+        // can wrap `rustc`, so other compiler caches are not considered.
+        let compiler_cache =
+            detect_compiler_cache(&[CompilerCache::Sccache], &config_env, &system_env_vars);
 
         let mut sccache_secrets = Vec::default();
-
-        // Verify if user has set any sccache environment variables
-        if sccache_envs(&all_env_vars).is_some() {
-            // check if we set some sccache in system env vars
-            if let Some(system_sccache_keys) = sccache_envs(&system_env_vars) {
-                // If sccache_envs are used in the system environment variables,
-                // we need to set them as secrets
-                let system_sccache_keys = system_env_vars
-                    .keys()
-                    // we set only those keys that are present in the system environment variables
-                    // and not in the config env
-                    .filter(|key| {
-                        system_sccache_keys.contains(&key.as_str())
-                            && !config_env.contains_key(*key)
-                    })
-                    .cloned()
-                    .collect();
-
-                sccache_secrets = system_sccache_keys;
-            };
-
-            let sccache_dep: Vec<Item<PackageDependency>> = sccache_tools()
-                .iter()
-                .map(|tool| tool.parse().into_diagnostic())
-                .collect::<miette::Result<Vec<_>>>()?;
-
+        if let Some(compiler_cache) = &compiler_cache {
             // Add sccache tools to the build requirements
             // only if they are not already present
-            let existing_reqs: Vec<_> = requirements.build.clone().into_iter().collect();
-
-            requirements.build.extend(
-                sccache_dep
-                    .into_iter()
-                    .filter(|dep| !existing_reqs.contains(dep)),
-            );
-
-            has_sccache = true;
+            add_compiler_cache_to_requirements(compiler_cache.cache, &mut requirements.build)?;
+            sccache_secrets = compiler_cache.secrets.clone();
         }
+        let has_sccache = compiler_cache.is_some();
 
         let build_script = BuildScriptContext {
             source_dir: manifest_root.display().to_string(),
@@ -140,8 +112,10 @@ impl GenerateRecipe for RustGenerator {
             has_openssl,
             has_sccache,
             is_bash: !Platform::current().is_windows(),
+            install_extra: config.install_extra.clone(),
         }
-        .render();
+        .render()
+        .into_diagnostic()?;
 
         generated_recipe.recipe.build.script = Script {
             content: build_script,
@@ -149,33 +123,79 @@ impl GenerateRecipe for RustGenerator {
             secrets: sccache_secrets,
         };
 
+        // Make sure that all binaries of the package are installed
+        let binaries = cargo_metadata
+            .binaries(&config.extra_args)
+            .into_diagnostic()?;
+        if !binaries.is_empty() {
+            let exe_suffix = if host_platform.is_windows() {
+                ".exe"
+            } else {
+                ""
+            };
+            generated_recipe.recipe.tests.push(Test {
+                package_contents: Some(PackageContents {
+                    files: Some(
+                        binaries
+                            .iter()
+                            .map(|name| format!("bin/{name}{exe_suffix}").parse())
+                            .collect::<Result<_, _>>()
+                            .into_diagnostic()?,
+                    ),
+                    ..PackageContents::default()
+                }),
+                ..Test::default()
+            });
+        }
+
         // Add the input globs from the Cargo metadata provider
         generated_recipe
             .metadata_input_globs
             .extend(cargo_metadata.input_globs());
+        // Cargo discovers the binaries and examples of the package from the
+        // source layout
+        if !config.ignore_cargo_manifest.is_some_and(|ignore| ignore) {
+            generated_recipe.metadata_input_globs.extend(
+                [
+                    "src/main.rs",
+                    "src/bin/*.rs",
+                    "src/bin/*/main.rs",
+                    "examples/*.rs",
+                    "examples/*/main.rs",
+                ]
+                .map(String::from),
+            );
+        }
+        generated_recipe
+            .metadata_input_globs
+            .extend(git_metadata.input_globs());
 
         Ok(generated_recipe)
     }
 
     /// Returns the build input globs used by the backend.
-    fn extract_input_globs_from_build(
+    fn extract_input_globs_from_source(
         &self,
         config: &Self::Config,
+        source_dir: impl AsRef<Path>,
         _workdir: impl AsRef<Path>,
         _editable: bool,
     ) -> miette::Result<BTreeSet<String>> {
-        Ok([
-            "**/*.rs",
-            // Cargo configuration files
-            "Cargo.toml",
-            "Cargo.lock",
-            // Build scripts
-            "build.rs",
-        ]
-        .iter()
-        .map(|s| s.to_string())
-        .chain(config.extra_input_globs.clone())
-        .collect())
+        Ok(input_globs_respecting_ignores(
+            source_dir.as_ref(),
+            [
+                "**/*.rs",
+                // Cargo configuration files
+                "Cargo.toml",
+                "Cargo.lock",
+                // Build scripts
+                "build.rs",
+            ]
+            .iter()
+            .map(|s| s.to_string()),
+            config.extra_input_globs.clone(),
+            &["target"],
+        ))
     }
 }
 
@@ -208,8 +228,9 @@ mod tests {
 
         let generator = RustGenerator::default();
 
+        let source_dir = tempfile::tempdir().unwrap();
         let result = generator
-            .extract_input_globs_from_build(&config, PathBuf::new(), false)
+            .extract_input_globs_from_source(&config, source_dir.path(), PathBuf::new(), false)
             .unwrap();
 
         // Verify that all extra globs are included in the result
@@ -225,6 +246,9 @@ mod tests {
         assert!(result.contains("Cargo.toml"));
         assert!(result.contains("Cargo.lock"));
         assert!(result.contains("build.rs"));
+
+        // The output directory of cargo is excluded
+        assert!(result.contains("!target/**"));
     }
 
     #[macro_export]
@@ -272,6 +296,34 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_metadata_input_globs_without_cargo_manifest() {
+        let project_model = project_fixture!({
+            "name": "foobar",
+            "version": "0.1.0",
+            "targets": {
+                "defaultTarget": {}
+            }
+        });
+
+        let manifest_root = tempfile::tempdir().unwrap();
+        let generated_recipe = RustGenerator::default()
+            .generate_recipe(
+                &project_model,
+                &RustBackendConfig::default_with_ignore_cargo_manifest(),
+                manifest_root.path().to_path_buf(),
+                Platform::Linux64,
+                None,
+                &HashSet::new(),
+                vec![],
+            )
+            .expect("Failed to generate recipe");
+
+        // Neither the Cargo.toml nor the sources are read to generate the
+        // recipe.
+        assert!(generated_recipe.metadata_input_globs.is_empty());
+    }
+
     #[test]
     fn test_rust_is_not_added_if_already_present() {
         let project_model = project_fixture!({
@@ -487,10 +539,25 @@ mod tests {
                 .to_string()
         );
 
+        // The binary of this crate is expected to be installed
+        let package_contents = generated_recipe.recipe.tests[0]
+            .package_contents
+            .as_ref()
+            .unwrap();
+        assert_eq!(
+            package_contents.files,
+            Some(vec![Item::Value(Value::Concrete(
+                "bin/pixi-build-rust".to_string()
+            ))])
+        );
+
         insta::assert_yaml_snapshot!(&generated_recipe.metadata_input_globs, @r###"
         - "../../Cargo.toml"
         - "../Cargo.toml"
         - Cargo.toml
+        - src/bin/*.rs
+        - src/bin/*/main.rs
+        - src/main.rs
         "###);
     }
 
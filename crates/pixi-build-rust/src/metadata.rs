@@ -1,4 +1,8 @@
-use std::{collections::BTreeSet, path::PathBuf, str::FromStr};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    path::PathBuf,
+    str::FromStr,
+};
 
 use cargo_toml::{
     AbstractFilesystem, Error as CargoTomlError, Filesystem, Inheritable, Manifest, Package,
@@ -21,6 +25,112 @@ pub enum MetadataError {
     MissingInheritedValue(String),
 }
 
+/// The arguments of `cargo install` that decide which binaries are installed.
+#[derive(Debug, Default)]
+struct InstallSelection {
+    /// The binaries that are selected with `--bin`.
+    bins: BTreeSet<String>,
+    /// True if all binaries are selected with `--bins`.
+    all_bins: bool,
+    /// The examples that are selected with `--example`.
+    examples: BTreeSet<String>,
+    /// True if all examples are selected with `--examples`.
+    all_examples: bool,
+    /// The features that are enabled with `--features`.
+    features: BTreeSet<String>,
+    all_features: bool,
+    no_default_features: bool,
+}
+
+impl InstallSelection {
+    fn from_args(args: &[String]) -> Self {
+        let mut selection = Self::default();
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--all-features" => selection.all_features = true,
+                "--no-default-features" => selection.no_default_features = true,
+                "--features" | "-F" => {
+                    if let Some(features) = args.next() {
+                        selection.add_features(features);
+                    }
+                }
+                "--bin" => selection.bins.extend(args.next().cloned()),
+                "--bins" => selection.all_bins = true,
+                "--example" => selection.examples.extend(args.next().cloned()),
+                "--examples" => selection.all_examples = true,
+                arg => {
+                    if let Some(features) = arg
+                        .strip_prefix("--features=")
+                        .or_else(|| arg.strip_prefix("-F"))
+                    {
+                        selection.add_features(features);
+                    } else if let Some(bin) = arg.strip_prefix("--bin=") {
+                        selection.bins.insert(bin.to_string());
+                    } else if let Some(example) = arg.strip_prefix("--example=") {
+                        selection.examples.insert(example.to_string());
+                    }
+                }
+            }
+        }
+        selection
+    }
+
+    /// Returns true if no target is selected explicitly, in which case
+    /// `cargo install` installs all binaries and no examples.
+    fn selects_default_targets(&self) -> bool {
+        !self.all_bins && !self.all_examples && self.bins.is_empty() && self.examples.is_empty()
+    }
+
+    /// Returns true if the binary with the given name is installed.
+    fn installs_bin(&self, name: &str) -> bool {
+        self.selects_default_targets() || self.all_bins || self.bins.contains(name)
+    }
+
+    /// Returns true if the example with the given name is installed.
+    fn installs_example(&self, name: &str) -> bool {
+        self.all_examples || self.examples.contains(name)
+    }
+
+    /// Adds a list of features that is separated by commas or spaces.
+    fn add_features(&mut self, features: &str) {
+        self.features.extend(
+            features
+                .split([',', ' '])
+                .filter(|feature| !feature.is_empty())
+                .map(String::from),
+        );
+    }
+
+    /// Returns the features that are enabled, including the ones that other
+    /// enabled features enable, or `None` if all features are enabled.
+    fn enabled_features(
+        &self,
+        declared: &BTreeMap<String, Vec<String>>,
+    ) -> Option<BTreeSet<String>> {
+        if self.all_features {
+            return None;
+        }
+        let mut pending = self.features.iter().cloned().collect::<Vec<_>>();
+        if !self.no_default_features {
+            pending.push(String::from("default"));
+        }
+        let mut enabled = BTreeSet::new();
+        while let Some(feature) = pending.pop() {
+            if let Some(implied) = declared.get(&feature) {
+                pending.extend(
+                    implied
+                        .iter()
+                        .filter(|implied| !enabled.contains(*implied))
+                        .cloned(),
+                );
+            }
+            enabled.insert(feature);
+        }
+        Some(enabled)
+    }
+}
+
 /// An implementation of [`MetadataProvider`] that reads metadata from a
 /// Cargo.toml file and possibly an associated workspace manifest.
 pub struct CargoMetadataProvider {
@@ -80,6 +190,57 @@ impl CargoMetadataProvider {
         Ok(manifest.workspace.as_ref().and_then(|w| w.package.as_ref()))
     }
 
+    /// Returns the names of the binaries that `cargo install` installs,
+    /// including the ones that cargo discovers automatically from
+    /// `src/main.rs`, `src/bin` and `examples`.
+    ///
+    /// A binary with `required-features` is only installed if the features
+    /// are enabled, either by the default features or by the `--features`,
+    /// `--all-features` and `--no-default-features` flags in `extra_args`.
+    /// If `extra_args` select targets with `--bin`, `--bins`, `--example` or
+    /// `--examples`, only those are returned. Otherwise all binaries and no
+    /// examples are returned, like `cargo install` does.
+    ///
+    /// If `ignore_cargo_manifest` is true, no binaries are returned.
+    pub fn binaries(&self, extra_args: &[String]) -> Result<Vec<String>, MetadataError> {
+        if self.ignore_cargo_manifest {
+            return Ok(Vec::new());
+        }
+        let mut manifest = self.ensure_manifest()?.clone();
+        manifest
+            .complete_from_abstract_filesystem::<cargo_toml::Value, _>(
+                Filesystem::new(&self.manifest_root),
+                None,
+            )
+            .map_err(MetadataError::CargoTomlError)?;
+
+        let selection = InstallSelection::from_args(extra_args);
+        let enabled_features = selection.enabled_features(&manifest.features);
+        let bins = manifest.bin.into_iter().filter(|bin| {
+            bin.name
+                .as_deref()
+                .is_some_and(|name| selection.installs_bin(name))
+        });
+        let examples = manifest.example.into_iter().filter(|example| {
+            example
+                .name
+                .as_deref()
+                .is_some_and(|name| selection.installs_example(name))
+        });
+        Ok(bins
+            .chain(examples)
+            .filter(|product| {
+                enabled_features.as_ref().is_none_or(|enabled| {
+                    product
+                        .required_features
+                        .iter()
+                        .all(|feature| enabled.contains(feature))
+                })
+            })
+            .filter_map(|product| product.name)
+            .collect())
+    }
+
     /// Returns the set of globs that match files that influence the metadata of
     /// this package.
     ///
@@ -822,6 +983,115 @@ description = "Test description"
         assert_eq!(provider.summary().unwrap(), None);
     }
 
+    #[test]
+    fn test_binaries() {
+        let cargo_toml_content = r#"
+[package]
+name = "test-package"
+version = "1.0.0"
+edition = "2021"
+
+[[bin]]
+name = "explicit"
+path = "tools/explicit.rs"
+"#;
+
+        let temp_dir = create_temp_cargo_project(cargo_toml_content);
+        fs::create_dir_all(temp_dir.path().join("src/bin")).unwrap();
+        fs::write(temp_dir.path().join("src/main.rs"), "fn main() {}").unwrap();
+        fs::write(temp_dir.path().join("src/bin/helper.rs"), "fn main() {}").unwrap();
+
+        let provider = create_metadata_provider(temp_dir.path());
+        let mut binaries = provider.binaries(&[]).unwrap();
+        binaries.sort();
+        assert_eq!(binaries, vec!["explicit", "helper", "test-package"]);
+
+        // Only the selected binaries are installed
+        let args = ["--bin".to_string(), "helper".to_string()];
+        assert_eq!(provider.binaries(&args).unwrap(), vec!["helper"]);
+
+        // Examples are only installed if they are selected, and then the
+        // binaries are only installed if they are selected as well
+        fs::create_dir_all(temp_dir.path().join("examples")).unwrap();
+        fs::write(temp_dir.path().join("examples/demo.rs"), "fn main() {}").unwrap();
+        let provider = create_metadata_provider(temp_dir.path());
+        assert!(
+            !provider
+                .binaries(&[])
+                .unwrap()
+                .contains(&"demo".to_string())
+        );
+        let args = ["--examples".to_string()];
+        assert_eq!(provider.binaries(&args).unwrap(), vec!["demo"]);
+        let args = ["--bins".to_string(), "--example=demo".to_string()];
+        let mut binaries = provider.binaries(&args).unwrap();
+        binaries.sort();
+        assert_eq!(binaries, vec!["demo", "explicit", "helper", "test-package"]);
+
+        // Nothing is discovered when the manifest is ignored
+        let provider = CargoMetadataProvider::new(temp_dir.path(), true);
+        assert!(provider.binaries(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_binaries_with_required_features() {
+        let cargo_toml_content = r#"
+[package]
+name = "test-package"
+version = "1.0.0"
+edition = "2021"
+
+[features]
+default = ["cli"]
+cli = []
+server = ["dep:tokio"]
+full = ["server"]
+
+[[bin]]
+name = "plain"
+path = "src/plain.rs"
+
+[[bin]]
+name = "cli"
+path = "src/cli.rs"
+required-features = ["cli"]
+
+[[bin]]
+name = "server"
+path = "src/server.rs"
+required-features = ["server"]
+"#;
+
+        let temp_dir = create_temp_cargo_project(cargo_toml_content);
+        let provider = create_metadata_provider(temp_dir.path());
+        let binaries = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+            let mut binaries = provider.binaries(&args).unwrap();
+            binaries.sort();
+            binaries
+        };
+
+        // Only the binaries whose features are enabled by default
+        assert_eq!(binaries(&[]), vec!["cli", "plain"]);
+        assert_eq!(binaries(&["--no-default-features"]), vec!["plain"]);
+
+        // Features that are enabled in the extra arguments, also through
+        // other features
+        assert_eq!(
+            binaries(&["--features", "full"]),
+            vec!["cli", "plain", "server"]
+        );
+        assert_eq!(
+            binaries(&["--no-default-features", "--features=server"]),
+            vec!["plain", "server"]
+        );
+        assert_eq!(binaries(&["-Fserver"]), vec!["cli", "plain", "server"]);
+        assert_eq!(
+            binaries(&["--no-default-features", "--all-features"]),
+            vec!["cli", "plain", "server"]
+        );
+    }
+
     #[test]
     fn test_invalid_version_format() {
         let cargo_toml_content = r#"
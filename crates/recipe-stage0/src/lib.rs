@@ -2,4 +2,6 @@ pub mod recipe;
 
 pub mod marked_yaml;
 pub mod matchspec;
+pub mod overlay;
+pub mod parser;
 pub mod requirements;
@@ -5,8 +5,10 @@ use marked_yaml::{Node as MarkedNode, Span};
 pub type MappingHash = LinkedHashMap<MarkedScalarNode, MarkedNode>;
 
 use crate::recipe::{
-    About, Build, Conditional, ConditionalList, ConditionalRequirements, Extra, IntermediateRecipe,
-    Item, ListOrItem, Package, PackageContents, Source, Test, Value,
+    About, Build, Cache, Conditional, ConditionalList, ConditionalRequirements, DynamicLinking,
+    Extra, ForceFileType, IgnoreRunExports, IntermediateRecipe, Item, ListOrItem, Output, Package,
+    PackageContents, PrefixDetection, PythonTest, RunExports, Script, Source, Test, TestFiles,
+    TestRequirements, Value,
 };
 
 // Trait for converting to marked YAML nodes
@@ -140,6 +142,154 @@ impl ToMarkedYaml for Build {
             );
         }
 
+        if let Some(ref string) = self.string {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "string"),
+                string.to_marked_yaml(),
+            );
+        }
+
+        if !self.files.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "files"),
+                self.files.to_marked_yaml(),
+            );
+        }
+
+        if !self.always_include_files.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "always_include_files"),
+                self.always_include_files.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref dynamic_linking) = self.dynamic_linking {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "dynamic_linking"),
+                dynamic_linking.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref prefix_detection) = self.prefix_detection {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "prefix_detection"),
+                prefix_detection.to_marked_yaml(),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for DynamicLinking {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.rpaths.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "rpaths"),
+                strings_to_marked_yaml(&self.rpaths),
+            );
+        }
+
+        if let Some(binary_relocation) = self.binary_relocation {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "binary_relocation"),
+                MarkedNode::Scalar(MarkedScalarNode::new(
+                    Span::new_blank(),
+                    binary_relocation.to_string(),
+                )),
+            );
+        }
+
+        if !self.missing_dso_allowlist.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "missing_dso_allowlist"),
+                strings_to_marked_yaml(&self.missing_dso_allowlist),
+            );
+        }
+
+        if !self.rpath_allowlist.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "rpath_allowlist"),
+                strings_to_marked_yaml(&self.rpath_allowlist),
+            );
+        }
+
+        if let Some(behavior) = self.overdepending_behavior {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "overdepending_behavior"),
+                MarkedNode::Scalar(MarkedScalarNode::new(
+                    Span::new_blank(),
+                    behavior.to_string(),
+                )),
+            );
+        }
+
+        if let Some(behavior) = self.overlinking_behavior {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "overlinking_behavior"),
+                MarkedNode::Scalar(MarkedScalarNode::new(
+                    Span::new_blank(),
+                    behavior.to_string(),
+                )),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for PrefixDetection {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if let Some(ref force_file_type) = self.force_file_type {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "force_file_type"),
+                force_file_type.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ignore) = self.ignore {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "ignore"),
+                MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), ignore.to_string())),
+            );
+        }
+
+        if let Some(ignore_binary_files) = self.ignore_binary_files {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "ignore_binary_files"),
+                MarkedNode::Scalar(MarkedScalarNode::new(
+                    Span::new_blank(),
+                    ignore_binary_files.to_string(),
+                )),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for ForceFileType {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.text.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "text"),
+                strings_to_marked_yaml(&self.text),
+            );
+        }
+
+        if !self.binary.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "binary"),
+                strings_to_marked_yaml(&self.binary),
+            );
+        }
+
         MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
     }
 }
@@ -176,6 +326,65 @@ impl ToMarkedYaml for ConditionalRequirements {
             );
         }
 
+        if let Some(ref run_exports) = self.run_exports {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "run_exports"),
+                run_exports.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref ignore_run_exports) = self.ignore_run_exports {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "ignore_run_exports"),
+                ignore_run_exports.to_marked_yaml(),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for RunExports {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        for (key, list) in [
+            ("weak", &self.weak),
+            ("strong", &self.strong),
+            ("noarch", &self.noarch),
+            ("weak_constraints", &self.weak_constraints),
+            ("strong_constraints", &self.strong_constraints),
+        ] {
+            if !list.is_empty() {
+                mapping.insert(
+                    MarkedScalarNode::new(Span::new_blank(), key),
+                    list.to_marked_yaml(),
+                );
+            }
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for IgnoreRunExports {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.by_name.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "by_name"),
+                self.by_name.to_marked_yaml(),
+            );
+        }
+
+        if !self.from_package.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "from_package"),
+                self.from_package.to_marked_yaml(),
+            );
+        }
+
         MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
     }
 }
@@ -198,6 +407,13 @@ impl ToMarkedYaml for PackageContents {
             );
         }
 
+        if let Some(ref site_packages) = self.site_packages {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "site_packages"),
+                site_packages.to_marked_yaml(),
+            );
+        }
+
         MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
     }
 }
@@ -213,6 +429,147 @@ impl ToMarkedYaml for Test {
             );
         }
 
+        if let Some(ref script) = self.script {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "script"),
+                script.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref requirements) = self.requirements {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "requirements"),
+                requirements.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref files) = self.files {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "files"),
+                files.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref python) = self.python {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "python"),
+                python.to_marked_yaml(),
+            );
+        }
+
+        if let Some(ref downstream) = self.downstream {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "downstream"),
+                MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), downstream)),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for PythonTest {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "imports"),
+            self.imports.to_marked_yaml(),
+        );
+
+        if let Some(pip_check) = self.pip_check {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "pip_check"),
+                MarkedNode::Scalar(MarkedScalarNode::new(
+                    Span::new_blank(),
+                    pip_check.to_string(),
+                )),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for Script {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "content"),
+            MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), &self.content)),
+        );
+
+        if !self.env.is_empty() {
+            let mut env_mapping = MappingHash::new();
+            for (key, value) in self.env.iter() {
+                env_mapping.insert(
+                    MarkedScalarNode::new(Span::new_blank(), key),
+                    MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), value)),
+                );
+            }
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "env"),
+                MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), env_mapping)),
+            );
+        }
+
+        if !self.secrets.is_empty() {
+            let nodes: Vec<MarkedNode> = self
+                .secrets
+                .iter()
+                .map(|secret| MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), secret)))
+                .collect();
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "secrets"),
+                MarkedNode::Sequence(MarkedSequenceNode::new(Span::new_blank(), nodes)),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for TestRequirements {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.build.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "build"),
+                self.build.to_marked_yaml(),
+            );
+        }
+
+        if !self.run.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "run"),
+                self.run.to_marked_yaml(),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for TestFiles {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.source.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "source"),
+                self.source.to_marked_yaml(),
+            );
+        }
+
+        if !self.recipe.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "recipe"),
+                self.recipe.to_marked_yaml(),
+            );
+        }
+
         MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
     }
 }
@@ -308,9 +665,14 @@ impl ToMarkedYaml for IntermediateRecipe {
             );
         }
 
-        // Add package
+        // Add package, a multi-output recipe only names the recipe
+        let package_key = if self.is_multi_output() {
+            "recipe"
+        } else {
+            "package"
+        };
         mapping.insert(
-            MarkedScalarNode::new(Span::new_blank(), "package"),
+            MarkedScalarNode::new(Span::new_blank(), package_key),
             self.package.to_marked_yaml(),
         );
 
@@ -326,21 +688,20 @@ impl ToMarkedYaml for IntermediateRecipe {
             self.build.to_marked_yaml(),
         );
 
-        mapping.insert(
-            MarkedScalarNode::new(Span::new_blank(), "requirements"),
-            self.requirements.to_marked_yaml(),
-        );
-
-        if !self.tests.is_empty() {
-            let test_nodes: Vec<MarkedNode> = self
-                .tests
-                .iter()
-                .map(|test| test.to_marked_yaml())
-                .collect();
+        // The requirements and tests of a multi-output recipe are written to
+        // every output instead
+        if !self.is_multi_output() {
             mapping.insert(
-                MarkedScalarNode::new(Span::new_blank(), "tests"),
-                MarkedNode::Sequence(MarkedSequenceNode::new(Span::new_blank(), test_nodes)),
+                MarkedScalarNode::new(Span::new_blank(), "requirements"),
+                self.requirements.to_marked_yaml(),
             );
+
+            if !self.tests.is_empty() {
+                mapping.insert(
+                    MarkedScalarNode::new(Span::new_blank(), "tests"),
+                    tests_to_marked_yaml(&self.tests),
+                );
+            }
         }
 
         if let Some(ref about) = self.about {
@@ -357,10 +718,106 @@ impl ToMarkedYaml for IntermediateRecipe {
             );
         }
 
+        if let Some(ref cache) = self.cache {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "cache"),
+                cache.to_marked_yaml(),
+            );
+        }
+
+        if self.is_multi_output() {
+            let output_nodes: Vec<MarkedNode> = self
+                .outputs_with_shared_sections()
+                .iter()
+                .map(|output| output.to_marked_yaml())
+                .collect();
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "outputs"),
+                MarkedNode::Sequence(MarkedSequenceNode::new(Span::new_blank(), output_nodes)),
+            );
+        }
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for Cache {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        if !self.source.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "source"),
+                self.source.to_marked_yaml(),
+            );
+        }
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "build"),
+            self.build.to_marked_yaml(),
+        );
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "requirements"),
+            self.requirements.to_marked_yaml(),
+        );
+
+        MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
+    }
+}
+
+impl ToMarkedYaml for Output {
+    fn to_marked_yaml(&self) -> MarkedNode {
+        let mut mapping = MappingHash::new();
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "package"),
+            self.package.to_marked_yaml(),
+        );
+
+        if let Some(ref build) = self.build {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "build"),
+                build.to_marked_yaml(),
+            );
+        }
+
+        mapping.insert(
+            MarkedScalarNode::new(Span::new_blank(), "requirements"),
+            self.requirements.to_marked_yaml(),
+        );
+
+        if !self.tests.is_empty() {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "tests"),
+                tests_to_marked_yaml(&self.tests),
+            );
+        }
+
+        if let Some(ref about) = self.about {
+            mapping.insert(
+                MarkedScalarNode::new(Span::new_blank(), "about"),
+                about.to_marked_yaml(),
+            );
+        }
+
         MarkedNode::Mapping(MarkedMappingNode::new(Span::new_blank(), mapping))
     }
 }
 
+fn strings_to_marked_yaml(values: &[String]) -> MarkedNode {
+    let nodes: Vec<MarkedNode> = values
+        .iter()
+        .map(|value| MarkedNode::Scalar(MarkedScalarNode::new(Span::new_blank(), value)))
+        .collect();
+    MarkedNode::Sequence(MarkedSequenceNode::new(Span::new_blank(), nodes))
+}
+
+fn tests_to_marked_yaml(tests: &[Test]) -> MarkedNode {
+    let test_nodes: Vec<MarkedNode> = tests.iter().map(|test| test.to_marked_yaml()).collect();
+    MarkedNode::Sequence(MarkedSequenceNode::new(Span::new_blank(), test_nodes))
+}
+
 impl<T> ToMarkedYaml for ListOrItem<T>
 where
     T: ToString,
@@ -383,3 +840,103 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts a marked YAML node into a plain YAML value that can be
+    /// deserialized. All scalars are read back as strings.
+    fn to_yaml_value(node: &MarkedNode) -> serde_yaml::Value {
+        match node {
+            MarkedNode::Scalar(scalar) => serde_yaml::Value::String(scalar.as_str().to_string()),
+            MarkedNode::Sequence(sequence) => {
+                serde_yaml::Value::Sequence(sequence.iter().map(to_yaml_value).collect())
+            }
+            MarkedNode::Mapping(mapping) => serde_yaml::Value::Mapping(
+                mapping
+                    .iter()
+                    .map(|(key, value)| {
+                        (
+                            serde_yaml::Value::String(key.as_str().to_string()),
+                            to_yaml_value(value),
+                        )
+                    })
+                    .collect(),
+            ),
+        }
+    }
+
+    #[test]
+    fn test_multi_output_roundtrip() {
+        let package = |name: &str| Package {
+            name: Value::Concrete(name.to_string()),
+            version: Value::Concrete("1.0.0".to_string()),
+        };
+
+        let recipe = IntermediateRecipe {
+            package: package("foo"),
+            requirements: ConditionalRequirements {
+                run_constraints: vec!["numpy >=2".parse().unwrap()],
+                ..Default::default()
+            },
+            tests: vec![Test {
+                python: Some(PythonTest {
+                    imports: vec!["foo".parse().unwrap()],
+                    pip_check: None,
+                }),
+                ..Default::default()
+            }],
+            cache: Some(Cache {
+                build: Build::new("cmake --install build".to_string()),
+                ..Default::default()
+            }),
+            outputs: vec![
+                Output {
+                    package: package("libfoo"),
+                    ..Default::default()
+                },
+                Output {
+                    package: package("foo-dev"),
+                    requirements: ConditionalRequirements {
+                        run: vec!["libfoo".parse().unwrap()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let value = to_yaml_value(&recipe.to_marked_yaml());
+        assert!(value.get("package").is_none());
+        assert!(value.get("requirements").is_none());
+        assert!(value.get("tests").is_none());
+
+        let roundtrip: IntermediateRecipe = serde_yaml::from_value(value).unwrap();
+        assert_eq!(roundtrip.package.name, Value::Concrete("foo".to_string()));
+        assert_eq!(
+            roundtrip.cache.unwrap().build.script.content,
+            "cmake --install build"
+        );
+        assert_eq!(roundtrip.outputs.len(), 2);
+
+        // The top-level requirements and tests are added to every output
+        for output in &roundtrip.outputs {
+            assert_eq!(
+                output.requirements.run_constraints[0].to_string(),
+                "numpy >=2"
+            );
+            let python = output.tests[0].python.as_ref().unwrap();
+            assert_eq!(python.imports[0].to_string(), "foo");
+        }
+        assert_eq!(
+            roundtrip.outputs[1].package.name,
+            Value::Concrete("foo-dev".to_string())
+        );
+        assert_eq!(
+            roundtrip.outputs[1].requirements.run[0].to_string(),
+            "libfoo"
+        );
+    }
+}
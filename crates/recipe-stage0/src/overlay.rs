@@ -0,0 +1,175 @@
+//! Applies a user provided, partial `recipe.yaml` on top of a generated
+//! [`IntermediateRecipe`].
+//!
+//! The overlay is deep-merged into the recipe using the following rules:
+//! - Mappings are merged key by key, keys that only exist in the overlay are
+//!   added.
+//! - Lists are appended to the list of the generated recipe.
+//! - A value tagged with `!replace` replaces the value of the generated recipe
+//!   instead of being merged, e.g. `run: !replace [python]`.
+//! - Any other value of the overlay replaces the value of the generated
+//!   recipe.
+
+use miette::Diagnostic;
+use serde_yaml::{Mapping, Value as YamlValue, value::TaggedValue};
+use thiserror::Error;
+
+use crate::recipe::IntermediateRecipe;
+
+/// The tag that marks a value of the overlay that replaces the value of the
+/// generated recipe.
+const REPLACE_TAG: &str = "replace";
+
+#[derive(Debug, Error, Diagnostic)]
+pub enum RecipeOverlayError {
+    #[error("the recipe overlay is not valid YAML")]
+    InvalidYaml(#[source] serde_yaml::Error),
+
+    #[error("the recipe overlay must be a mapping")]
+    NotAMapping,
+
+    #[error("the recipe overlay does not result in a valid recipe")]
+    InvalidRecipe(#[source] serde_yaml::Error),
+}
+
+/// Deep-merges the `overlay` YAML into `recipe` and returns the result.
+pub fn apply_overlay(
+    recipe: &IntermediateRecipe,
+    overlay: &str,
+) -> Result<IntermediateRecipe, RecipeOverlayError> {
+    let overlay: YamlValue =
+        serde_yaml::from_str(overlay).map_err(RecipeOverlayError::InvalidYaml)?;
+    let overlay = match overlay {
+        YamlValue::Mapping(overlay) => overlay,
+        // An empty overlay does not change anything
+        YamlValue::Null => Mapping::new(),
+        _ => return Err(RecipeOverlayError::NotAMapping),
+    };
+
+    let mut merged = serde_yaml::to_value(recipe).map_err(RecipeOverlayError::InvalidRecipe)?;
+    merge(&mut merged, YamlValue::Mapping(overlay));
+    serde_yaml::from_value(merged).map_err(RecipeOverlayError::InvalidRecipe)
+}
+
+/// Merges `overlay` into `base` following the rules described in the module
+/// documentation.
+fn merge(base: &mut YamlValue, overlay: YamlValue) {
+    match (base, overlay) {
+        (base, YamlValue::Tagged(tagged)) if tagged.tag == REPLACE_TAG => {
+            let TaggedValue { value, .. } = *tagged;
+            *base = strip_replace_tags(value);
+        }
+        (YamlValue::Mapping(base), YamlValue::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge(base_value, value),
+                    None => {
+                        base.insert(key, strip_replace_tags(value));
+                    }
+                }
+            }
+        }
+        (YamlValue::Sequence(base), YamlValue::Sequence(overlay)) => {
+            base.extend(overlay.into_iter().map(strip_replace_tags));
+        }
+        (base, overlay) => *base = strip_replace_tags(overlay),
+    }
+}
+
+/// Removes the `!replace` tags from a value that is inserted as a whole.
+fn strip_replace_tags(value: YamlValue) -> YamlValue {
+    match value {
+        YamlValue::Tagged(tagged) if tagged.tag == REPLACE_TAG => strip_replace_tags(tagged.value),
+        YamlValue::Mapping(mapping) => YamlValue::Mapping(
+            mapping
+                .into_iter()
+                .map(|(key, value)| (key, strip_replace_tags(value)))
+                .collect(),
+        ),
+        YamlValue::Sequence(sequence) => {
+            YamlValue::Sequence(sequence.into_iter().map(strip_replace_tags).collect())
+        }
+        value => value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        matchspec::PackageDependency,
+        recipe::{About, ConditionalList, ConditionalRequirements, Package, Value},
+    };
+
+    fn dependencies(specs: &[&str]) -> ConditionalList<PackageDependency> {
+        specs.iter().map(|spec| spec.parse().unwrap()).collect()
+    }
+
+    fn recipe() -> IntermediateRecipe {
+        IntermediateRecipe {
+            package: Package {
+                name: Value::Concrete("foo".to_string()),
+                version: Value::Concrete("1.0.0".to_string()),
+            },
+            requirements: ConditionalRequirements {
+                host: dependencies(&["python"]),
+                run: dependencies(&["python"]),
+                ..Default::default()
+            },
+            about: Some(About {
+                license: Some(Value::Concrete("MIT".to_string())),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_overlay_appends_lists_and_merges_mappings() {
+        let overlay = r#"
+requirements:
+  run:
+    - numpy
+about:
+  license_file: LICENSE.txt
+"#;
+        let merged = apply_overlay(&recipe(), overlay).unwrap();
+
+        assert_eq!(merged.requirements.run, dependencies(&["python", "numpy"]));
+        assert_eq!(merged.requirements.host, dependencies(&["python"]));
+        let about = merged.about.unwrap();
+        assert_eq!(about.license, Some(Value::Concrete("MIT".to_string())));
+        assert_eq!(
+            about.license_file,
+            Some(Value::Concrete("LICENSE.txt".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_overlay_replace_tag() {
+        let overlay = r#"
+requirements:
+  run: !replace
+    - python >=3.12
+"#;
+        let merged = apply_overlay(&recipe(), overlay).unwrap();
+
+        assert_eq!(merged.requirements.run, dependencies(&["python >=3.12"]));
+    }
+
+    #[test]
+    fn test_overlay_replaces_scalars() {
+        let merged = apply_overlay(&recipe(), "package:\n  version: 2.0.0\n").unwrap();
+        assert_eq!(merged.package.version, Value::Concrete("2.0.0".to_string()));
+        assert_eq!(merged.package.name, Value::Concrete("foo".to_string()));
+    }
+
+    #[test]
+    fn test_overlay_must_be_a_mapping() {
+        assert!(matches!(
+            apply_overlay(&recipe(), "- foo"),
+            Err(RecipeOverlayError::NotAMapping)
+        ));
+        assert!(apply_overlay(&recipe(), "").is_ok());
+    }
+}
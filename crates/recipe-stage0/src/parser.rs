@@ -0,0 +1,174 @@
+//! Parses a rattler-build `recipe.yaml` back into an [`IntermediateRecipe`].
+//!
+//! Jinja templates (`${{ ... }}`) are kept as [`Value::Template`] and
+//! `if/then/else` items as [`Item::Conditional`], so that a recipe can be
+//! patched and written out again without evaluating it. The location of every
+//! node in the source is recorded, which allows pointing at the line that a
+//! value originates from.
+//!
+//! [`Value::Template`]: crate::recipe::Value::Template
+//! [`Item::Conditional`]: crate::recipe::Item::Conditional
+
+use indexmap::IndexMap;
+use marked_yaml::{Node as MarkedNode, Span};
+use miette::{Diagnostic, NamedSource, SourceSpan};
+use thiserror::Error;
+
+use crate::recipe::IntermediateRecipe;
+
+/// A recipe together with the location of each of its nodes in the source.
+#[derive(Debug, Clone)]
+pub struct ParsedRecipe {
+    pub recipe: IntermediateRecipe,
+    /// The span of every node in the source, keyed by its path in the recipe,
+    /// e.g. `package.name` or `requirements.host[1]`.
+    pub spans: IndexMap<String, Span>,
+}
+
+impl ParsedRecipe {
+    /// Returns the 1-based line and column at which the node at `path`
+    /// starts.
+    pub fn location(&self, path: &str) -> Option<(usize, usize)> {
+        let start = self.spans.get(path)?.start()?;
+        Some((start.line(), start.column()))
+    }
+}
+
+#[derive(Debug, Error, Diagnostic)]
+#[error("failed to parse the recipe: {message}")]
+pub struct ParseRecipeError {
+    message: String,
+    #[source_code]
+    source_code: NamedSource<String>,
+    #[label("here")]
+    span: Option<SourceSpan>,
+}
+
+/// Parses the `recipe.yaml` contents in `source` into an
+/// [`IntermediateRecipe`]. `name` is used to refer to the recipe in errors.
+pub fn parse_recipe(name: &str, source: &str) -> Result<ParsedRecipe, ParseRecipeError> {
+    let error = |message: String, span: Option<SourceSpan>| ParseRecipeError {
+        message,
+        source_code: NamedSource::new(name, source.to_string()),
+        span,
+    };
+
+    let recipe: IntermediateRecipe = serde_yaml::from_str(source).map_err(|err| {
+        let span = err
+            .location()
+            .map(|location| SourceSpan::from((location.index(), 0)));
+        error(err.to_string(), span)
+    })?;
+
+    let root = marked_yaml::parse_yaml(0, source).map_err(|err| error(err.to_string(), None))?;
+    let mut spans = IndexMap::new();
+    collect_spans(&root, String::new(), &mut spans);
+
+    Ok(ParsedRecipe { recipe, spans })
+}
+
+/// Records the span of `node` and all of its children under their path.
+fn collect_spans(node: &MarkedNode, path: String, spans: &mut IndexMap<String, Span>) {
+    match node {
+        MarkedNode::Mapping(mapping) => {
+            for (key, value) in mapping.iter() {
+                let child_path = if path.is_empty() {
+                    key.as_str().to_string()
+                } else {
+                    format!("{path}.{}", key.as_str())
+                };
+                collect_spans(value, child_path, spans);
+            }
+        }
+        MarkedNode::Sequence(sequence) => {
+            for (index, value) in sequence.iter().enumerate() {
+                collect_spans(value, format!("{path}[{index}]"), spans);
+            }
+        }
+        MarkedNode::Scalar(_) => {}
+    }
+
+    if !path.is_empty() {
+        spans.insert(path, *node.span());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::recipe::{Item, Source, Value};
+
+    const RECIPE: &str = r#"context:
+  name: xtensor
+  version: 0.24.6
+package:
+  name: ${{ name|lower }}
+  version: ${{ version }}
+source:
+  - path: .
+build:
+  number: 1
+  script:
+    content: cmake --build build
+requirements:
+  build:
+    - ${{ compiler('cxx') }}
+    - cmake
+    - if: unix
+      then: make
+  host:
+    - xtl >=0.7,<0.8
+about:
+  license: BSD-3-Clause
+"#;
+
+    #[test]
+    fn test_parse_recipe() {
+        let parsed = parse_recipe("recipe.yaml", RECIPE).unwrap();
+        let recipe = &parsed.recipe;
+
+        assert_eq!(
+            recipe.package.name,
+            Value::Template("${{ name|lower }}".to_string())
+        );
+        assert_eq!(
+            recipe.context["version"],
+            Value::Concrete("0.24.6".to_string())
+        );
+        assert_eq!(recipe.build.number, Some(Value::Concrete(1)));
+        assert_eq!(recipe.build.script.content, "cmake --build build");
+        assert!(matches!(
+            &recipe.source[0],
+            Item::Value(Value::Concrete(Source::Path(_)))
+        ));
+
+        let build = &recipe.requirements.build;
+        assert!(matches!(&build[0], Item::Value(Value::Template(_))));
+        assert_eq!(build[1], "cmake".parse().unwrap());
+        let Item::Conditional(conditional) = &build[2] else {
+            panic!("expected a conditional, got {:?}", build[2]);
+        };
+        assert_eq!(conditional.condition, "unix");
+        assert_eq!(conditional.then.len(), 1);
+        assert!(conditional.else_value.is_empty());
+
+        assert_eq!(parsed.location("package.name"), Some((5, 9)));
+        assert_eq!(parsed.location("requirements.build[2]"), Some((17, 7)));
+        assert_eq!(parsed.location("about.license"), Some((22, 12)));
+    }
+
+    #[test]
+    fn test_parse_recipe_roundtrip() {
+        let parsed = parse_recipe("recipe.yaml", RECIPE).unwrap();
+        let yaml = parsed.recipe.to_yaml_pretty().unwrap();
+        let reparsed = parse_recipe("recipe.yaml", &yaml).unwrap();
+
+        assert_eq!(reparsed.recipe.to_yaml_pretty().unwrap(), yaml);
+    }
+
+    #[test]
+    fn test_parse_recipe_error_points_at_location() {
+        let err = parse_recipe("recipe.yaml", "package:\n  name: foo\nbuild: [\n").unwrap_err();
+        assert!(err.span.is_some());
+    }
+}
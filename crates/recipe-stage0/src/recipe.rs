@@ -6,7 +6,7 @@ use std::{
 use indexmap::IndexMap;
 use itertools::Itertools;
 use rattler_conda_types::{PackageName, Platform, package::EntryPoint};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
 
 use crate::{
     matchspec::{PackageDependency, SerializableMatchSpec},
@@ -14,13 +14,34 @@ use crate::{
 };
 
 // Core enum for values that can be either concrete or templated
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 #[serde(untagged)]
 pub enum Value<T> {
     Concrete(T),
     Template(String), // Jinja template like "${{ name|lower }}"
 }
 
+impl<'de, T: DeserializeOwned> Deserialize<'de> for Value<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        use serde::de::Error;
+
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+
+        // Templates are kept verbatim, even if they would also be a valid
+        // concrete value, so that they are evaluated by rattler-build.
+        if let Some(template) = value.as_str().filter(|s| s.contains("${{")) {
+            return Ok(Value::Template(template.to_string()));
+        }
+
+        T::deserialize(value)
+            .map(Value::Concrete)
+            .map_err(D::Error::custom)
+    }
+}
+
 impl<T: Display> Display for Value<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
@@ -64,7 +85,7 @@ impl From<SerializableMatchSpec> for Value<SerializableMatchSpec> {
 
 // Any item in a list can be either a value or a conditional
 #[derive(Clone, Serialize, Deserialize)]
-#[serde(untagged)]
+#[serde(untagged, bound(deserialize = "T: DeserializeOwned"))]
 pub enum Item<T> {
     Value(Value<T>),
     Conditional(Conditional<T>),
@@ -304,7 +325,7 @@ pub struct Conditional<T> {
     #[serde(rename = "if")]
     pub condition: String,
     pub then: ListOrItem<T>,
-    #[serde(rename = "else")]
+    #[serde(rename = "else", default)]
     pub else_value: ListOrItem<T>,
 }
 
@@ -332,24 +353,123 @@ impl<T: Display> Display for Conditional<T> {
 pub type ConditionalList<T> = Vec<Item<T>>;
 
 // Main recipe structure
-#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[derive(Deserialize, Default, Clone, Debug)]
 pub struct IntermediateRecipe {
     #[serde(default)]
     pub context: IndexMap<String, Value<String>>,
-    #[serde(default)]
+    /// The package of a single-output recipe. For a multi-output recipe this
+    /// is written as the top-level `recipe` section instead.
+    #[serde(default, alias = "recipe")]
     pub package: Package,
     #[serde(default)]
     pub source: ConditionalList<Source>,
     #[serde(default)]
     pub build: Build,
+    /// The requirements of the package. A multi-output recipe can't have
+    /// top-level requirements, so these are added to those of every output.
     #[serde(default)]
     pub requirements: ConditionalRequirements,
+    /// The tests of the package. A multi-output recipe can't have top-level
+    /// tests, so these are added to those of every output.
     #[serde(default)]
     pub tests: Vec<Test>,
     #[serde(default)]
     pub about: Option<About>,
     #[serde(default)]
     pub extra: Option<Extra>,
+    /// A build that is shared by all the outputs of the recipe.
+    #[serde(default)]
+    pub cache: Option<Cache>,
+    /// The packages produced by a multi-output recipe.
+    #[serde(default)]
+    pub outputs: Vec<Output>,
+}
+
+impl Serialize for IntermediateRecipe {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeMap;
+
+        // rattler-build rejects a top-level `package` or `requirements` section
+        // in a recipe that has outputs.
+        let is_multi_output = self.is_multi_output();
+
+        let mut map = serializer.serialize_map(None)?;
+        map.serialize_entry("context", &self.context)?;
+        if is_multi_output {
+            map.serialize_entry("recipe", &self.package)?;
+        } else {
+            map.serialize_entry("package", &self.package)?;
+        }
+        map.serialize_entry("source", &self.source)?;
+        map.serialize_entry("build", &self.build)?;
+        if !is_multi_output {
+            map.serialize_entry("requirements", &self.requirements)?;
+            map.serialize_entry("tests", &self.tests)?;
+        }
+        map.serialize_entry("about", &self.about)?;
+        map.serialize_entry("extra", &self.extra)?;
+        if let Some(cache) = &self.cache {
+            map.serialize_entry("cache", cache)?;
+        }
+        if is_multi_output {
+            map.serialize_entry("outputs", &self.outputs_with_shared_sections())?;
+        }
+        map.end()
+    }
+}
+
+/// A build that runs once before the outputs of a multi-output recipe are
+/// built. The outputs start from the files installed by this build and select
+/// the ones they package, which allows splitting e.g. `-dev`, `-libs` and
+/// `-doc` packages from a single source build.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Cache {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source: ConditionalList<Source>,
+    #[serde(default)]
+    pub build: Build,
+    #[serde(default)]
+    pub requirements: ConditionalRequirements,
+}
+
+impl Display for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ source: [{}], build: {}, requirements: {} }}",
+            self.source.iter().format(", "),
+            self.build.script.content,
+            self.requirements
+        )
+    }
+}
+
+/// A single package of a multi-output recipe.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct Output {
+    pub package: Package,
+    /// Overrides the top-level build section for this output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub build: Option<Build>,
+    #[serde(default)]
+    pub requirements: ConditionalRequirements,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tests: Vec<Test>,
+    /// Overrides the top-level about section for this output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub about: Option<About>,
+}
+
+impl Display for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ package: {}, requirements: {}, tests: [{}] }}",
+            self.package,
+            self.requirements,
+            self.tests.iter().format(", ")
+        )
+    }
 }
 
 pub struct EvaluatedDependencies {
@@ -534,11 +654,27 @@ impl Display for Python {
 #[derive(Debug, Serialize, Deserialize, Default, Clone)]
 pub struct Build {
     pub number: Option<Value<u64>>,
+    /// The build string of the package, rattler-build derives it from the
+    /// hash of the variant if not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub string: Option<Value<String>>,
     pub script: Script,
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub noarch: Option<NoArchKind>,
     #[serde(default, skip_serializing_if = "Python::is_default")]
     pub python: Python,
+    /// Glob patterns of the files in the prefix that are included in the
+    /// package. If empty, all new files are included.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: ConditionalList<String>,
+    /// Glob patterns of files that are always included in the package, even
+    /// if they already existed in the prefix before the build.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub always_include_files: ConditionalList<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dynamic_linking: Option<DynamicLinking>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prefix_detection: Option<PrefixDetection>,
 }
 
 impl Build {
@@ -554,6 +690,71 @@ impl Build {
     }
 }
 
+/// Settings for the relocation and linking checks of shared libraries and
+/// executables.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct DynamicLinking {
+    /// The rpaths that are set on shared libraries, relative to the prefix.
+    /// rattler-build defaults to `lib/` on unix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpaths: Vec<String>,
+    /// Whether binaries are relocated, rattler-build defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub binary_relocation: Option<bool>,
+    /// Glob patterns of shared libraries that may be linked without being
+    /// provided by a dependency.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub missing_dso_allowlist: Vec<String>,
+    /// Glob patterns of rpaths that may point outside of the prefix.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rpath_allowlist: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overdepending_behavior: Option<LinkingCheckBehavior>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub overlinking_behavior: Option<LinkingCheckBehavior>,
+}
+
+/// What rattler-build does when a linking check fails.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkingCheckBehavior {
+    Ignore,
+    Error,
+}
+
+impl Display for LinkingCheckBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LinkingCheckBehavior::Ignore => write!(f, "ignore"),
+            LinkingCheckBehavior::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// Settings for the detection and replacement of the build prefix in the
+/// files of the package.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct PrefixDetection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub force_file_type: Option<ForceFileType>,
+    /// Disables prefix detection for all files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<bool>,
+    /// Disables prefix detection for binary files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_binary_files: Option<bool>,
+}
+
+/// Glob patterns of files whose type is not detected but forced during prefix
+/// replacement.
+#[derive(Debug, Serialize, Deserialize, Default, Clone, PartialEq)]
+pub struct ForceFileType {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub text: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub binary: Vec<String>,
+}
+
 /// A struct to hold the fully resolved, non-conditional requirements.
 #[derive(Default)]
 pub struct ResolvedRequirements {
@@ -580,6 +781,75 @@ pub struct ConditionalRequirements {
     pub run: ConditionalList<PackageDependency>,
     #[serde(default)]
     pub run_constraints: ConditionalList<PackageDependency>,
+    /// The requirements that are added to packages that depend on this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_exports: Option<RunExports>,
+    /// The run exports of dependencies that are ignored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore_run_exports: Option<IgnoreRunExports>,
+}
+
+/// The run exports of a package, e.g.
+/// `${{ pin_subpackage(name, upper_bound='x.x') }}`.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct RunExports {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weak: ConditionalList<PackageDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strong: ConditionalList<PackageDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub noarch: ConditionalList<PackageDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub weak_constraints: ConditionalList<PackageDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub strong_constraints: ConditionalList<PackageDependency>,
+}
+
+impl RunExports {
+    /// Returns true if no run exports are declared.
+    pub fn is_empty(&self) -> bool {
+        self.weak.is_empty()
+            && self.strong.is_empty()
+            && self.noarch.is_empty()
+            && self.weak_constraints.is_empty()
+            && self.strong_constraints.is_empty()
+    }
+}
+
+impl Display for RunExports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RunExports {{ weak: {}, strong: {}, noarch: {}, weak_constraints: {}, strong_constraints: {} }}",
+            self.weak.iter().format(", "),
+            self.strong.iter().format(", "),
+            self.noarch.iter().format(", "),
+            self.weak_constraints.iter().format(", "),
+            self.strong_constraints.iter().format(", "),
+        )
+    }
+}
+
+/// Run exports of dependencies that are not applied to this package.
+#[derive(Serialize, Deserialize, Default, Debug, Clone)]
+pub struct IgnoreRunExports {
+    /// Names of run exported packages that are ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub by_name: ConditionalList<String>,
+    /// Names of dependencies whose run exports are all ignored.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub from_package: ConditionalList<String>,
+}
+
+impl Display for IgnoreRunExports {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "IgnoreRunExports {{ by_name: {}, from_package: {} }}",
+            self.by_name.iter().format(", "),
+            self.from_package.iter().format(", "),
+        )
+    }
 }
 
 impl ConditionalRequirements {
@@ -667,34 +937,134 @@ impl Display for ConditionalRequirements {
 //     pub run_constraints: Vec<SerializableMatchSpec>,
 // }
 
+/// A single test of a package. Every populated field corresponds to one of
+/// the test kinds of rattler-build, e.g. `package_contents` or a `script`
+/// test with its `requirements` and `files`.
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct Test {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub package_contents: Option<PackageContents>,
+    /// The script to execute in the test environment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub script: Option<Script>,
+    /// The requirements of the test environment of a script test.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requirements: Option<TestRequirements>,
+    /// The files that are copied into the test directory of a script test.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub files: Option<TestFiles>,
+    /// Imports the given Python modules and optionally runs `pip check`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub python: Option<PythonTest>,
+    /// The name of a downstream package whose tests are run against this
+    /// package.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub downstream: Option<String>,
 }
 
 impl Display for Test {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "Test {{ package_contents: {} }}",
-            self.package_contents.as_ref().into_iter().format("")
+            "Test {{ package_contents: {}, script: {}, requirements: {}, files: {}, python: {}, downstream: {} }}",
+            self.package_contents.as_ref().into_iter().format(""),
+            self.script
+                .as_ref()
+                .into_iter()
+                .map(|script| &script.content)
+                .format(""),
+            self.requirements.as_ref().into_iter().format(""),
+            self.files.as_ref().into_iter().format(""),
+            self.python.as_ref().into_iter().format(""),
+            self.downstream.as_ref().into_iter().format(""),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct PythonTest {
+    /// The modules that are imported.
+    #[serde(default)]
+    pub imports: ConditionalList<String>,
+    /// Whether `pip check` is run, rattler-build defaults to `true`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pip_check: Option<bool>,
+}
+
+impl Display for PythonTest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "PythonTest {{ imports: {}, pip_check: {} }}",
+            self.imports.iter().format(", "),
+            self.pip_check.as_ref().into_iter().format(""),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TestRequirements {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub build: ConditionalList<PackageDependency>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub run: ConditionalList<PackageDependency>,
+}
+
+impl Display for TestRequirements {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TestRequirements {{ build: {}, run: {} }}",
+            self.build.iter().format(", "),
+            self.run.iter().format(", "),
+        )
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TestFiles {
+    /// Files from the source directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub source: ConditionalList<String>,
+    /// Files from the recipe directory.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub recipe: ConditionalList<String>,
+}
+
+impl Display for TestFiles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "TestFiles {{ source: {}, recipe: {} }}",
+            self.source.iter().format(", "),
+            self.recipe.iter().format(", "),
         )
     }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct PackageContents {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub include: Option<ConditionalList<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub files: Option<ConditionalList<String>>,
+    /// Python modules that must exist in the `site-packages` directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub site_packages: Option<ConditionalList<String>>,
 }
 
 impl Display for PackageContents {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "PackageContents {{ include: {}, files: {} }}",
+            "PackageContents {{ include: {}, files: {}, site_packages: {} }}",
             self.include.as_ref().into_iter().flatten().format(", "),
             self.files.as_ref().into_iter().flatten().format(", "),
+            self.site_packages
+                .as_ref()
+                .into_iter()
+                .flatten()
+                .format(", "),
         )
     }
 }
@@ -760,6 +1130,60 @@ impl IntermediateRecipe {
     pub fn from_yaml(yaml: &str) -> Result<IntermediateRecipe, serde_yaml::Error> {
         serde_yaml::from_str(yaml)
     }
+
+    /// Returns true if the recipe produces its packages from `outputs` instead
+    /// of from the top-level package.
+    pub fn is_multi_output(&self) -> bool {
+        !self.outputs.is_empty()
+    }
+
+    /// Returns the outputs with the top-level requirements and tests added to
+    /// each of them. The settings of a backend and the tests it generates are
+    /// written to the top-level sections, which a multi-output recipe can't
+    /// have. An output keeps its own run exports if it declares any.
+    pub fn outputs_with_shared_sections(&self) -> Vec<Output> {
+        let shared = &self.requirements;
+        self.outputs
+            .iter()
+            .map(|output| {
+                let requirements = &output.requirements;
+                Output {
+                    requirements: ConditionalRequirements {
+                        build: [shared.build.as_slice(), &requirements.build].concat(),
+                        host: [shared.host.as_slice(), &requirements.host].concat(),
+                        run: [shared.run.as_slice(), &requirements.run].concat(),
+                        run_constraints: [
+                            shared.run_constraints.as_slice(),
+                            &requirements.run_constraints,
+                        ]
+                        .concat(),
+                        run_exports: requirements
+                            .run_exports
+                            .clone()
+                            .or_else(|| shared.run_exports.clone()),
+                        ignore_run_exports: requirements
+                            .ignore_run_exports
+                            .clone()
+                            .or_else(|| shared.ignore_run_exports.clone()),
+                    },
+                    tests: [self.tests.as_slice(), &output.tests].concat(),
+                    ..output.clone()
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the names of the packages produced by this recipe.
+    pub fn output_names(&self) -> Vec<&Value<String>> {
+        if self.is_multi_output() {
+            self.outputs
+                .iter()
+                .map(|output| &output.package.name)
+                .collect()
+        } else {
+            vec![&self.package.name]
+        }
+    }
 }
 
 impl<T: ToString + Default + Debug> Conditional<T> {
@@ -837,6 +1261,7 @@ mod tests {
                 ],
                 run: vec!["xtl >=0.7,<0.8".parse().unwrap()],
                 run_constraints: vec!["xsimd >=8.0.3,<10".parse().unwrap()],
+                ..Default::default()
             },
             about: Some(About {
                 homepage: Some(Value::Concrete(
@@ -861,4 +1286,210 @@ mod tests {
 
         insta::assert_yaml_snapshot!(recipe)
     }
+
+    #[test]
+    fn test_multi_output_recipe() {
+        let package = |name: &str| Package {
+            name: Value::Concrete(name.to_string()),
+            version: Value::Concrete("1.0.0".to_string()),
+        };
+
+        let recipe = IntermediateRecipe {
+            package: package("foo"),
+            // Applied to every output
+            requirements: ConditionalRequirements {
+                run_constraints: vec!["numpy >=2".parse().unwrap()],
+                ..Default::default()
+            },
+            tests: vec![Test {
+                python: Some(PythonTest {
+                    imports: vec!["foo".parse().unwrap()],
+                    pip_check: None,
+                }),
+                ..Default::default()
+            }],
+            cache: Some(Cache {
+                build: Build::new("cmake --install build".to_string()),
+                requirements: ConditionalRequirements {
+                    build: vec!["cmake".parse().unwrap()],
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+            outputs: vec![
+                Output {
+                    package: package("libfoo"),
+                    ..Default::default()
+                },
+                Output {
+                    package: package("foo-dev"),
+                    requirements: ConditionalRequirements {
+                        run: vec!["libfoo".parse().unwrap()],
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let yaml = recipe.to_yaml().unwrap();
+        let value: serde_yaml::Value = serde_yaml::from_str(&yaml).unwrap();
+        assert!(value.get("package").is_none());
+        assert!(value.get("requirements").is_none());
+        assert_eq!(value["recipe"]["name"].as_str(), Some("foo"));
+        assert_eq!(
+            value["cache"]["requirements"]["build"][0].as_str(),
+            Some("cmake")
+        );
+        assert_eq!(
+            value["outputs"][1]["package"]["name"].as_str(),
+            Some("foo-dev")
+        );
+        for output in value["outputs"].as_sequence().unwrap() {
+            assert_eq!(
+                output["requirements"]["run_constraints"][0].as_str(),
+                Some("numpy >=2")
+            );
+            assert_eq!(
+                output["tests"][0]["python"]["imports"][0].as_str(),
+                Some("foo")
+            );
+        }
+        assert_eq!(
+            value["outputs"][1]["requirements"]["run"][0].as_str(),
+            Some("libfoo")
+        );
+
+        let roundtrip = IntermediateRecipe::from_yaml(&yaml).unwrap();
+        assert_eq!(roundtrip.package.name, Value::Concrete("foo".to_string()));
+        assert!(roundtrip.cache.is_some());
+        assert_eq!(
+            roundtrip.output_names(),
+            vec![
+                &Value::Concrete("libfoo".to_string()),
+                &Value::Concrete("foo-dev".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_value_is_rejected() {
+        let requirements = "run:\n  - foo [version=1.0\n";
+        assert!(serde_yaml::from_str::<ConditionalRequirements>(requirements).is_err());
+        assert!(serde_yaml::from_str::<Value<u64>>("abc").is_err());
+
+        // Templates are kept without parsing them
+        let number = serde_yaml::from_str::<Value<u64>>("'${{ build_number }}'").unwrap();
+        assert!(number.is_template());
+    }
+
+    #[test]
+    fn test_tests_roundtrip() {
+        let yaml = r#"
+- script:
+    content: pytest tests
+  requirements:
+    run:
+    - pytest
+  files:
+    source:
+    - tests/
+- python:
+    imports:
+    - foo
+    - foo.bar
+    pip_check: false
+- package_contents:
+    include:
+    - foo.h
+- downstream: foo-plugin
+"#;
+        let tests: Vec<Test> = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(tests[0].script.as_ref().unwrap().content, "pytest tests");
+        assert_eq!(tests[0].requirements.as_ref().unwrap().run.len(), 1);
+        assert_eq!(tests[0].files.as_ref().unwrap().source.len(), 1);
+        assert!(tests[0].python.is_none());
+
+        let python = tests[1].python.as_ref().unwrap();
+        assert_eq!(python.imports.len(), 2);
+        assert_eq!(python.pip_check, Some(false));
+
+        assert!(tests[2].package_contents.is_some());
+        assert_eq!(tests[3].downstream.as_deref(), Some("foo-plugin"));
+
+        // Only the populated test kinds are serialized
+        let serialized = serde_yaml::to_string(&tests).unwrap();
+        assert!(!serialized.contains("null"));
+        let roundtrip: Vec<Test> = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(roundtrip[3].downstream.as_deref(), Some("foo-plugin"));
+    }
+
+    #[test]
+    fn test_library_build_settings_roundtrip() {
+        let yaml = r#"
+package:
+  name: libfoo
+  version: 1.0.0
+build:
+  number: 0
+  string: ${{ hash }}_nightly
+  script:
+    content: cmake --install build
+  files:
+  - lib/libfoo*
+  dynamic_linking:
+    missing_dso_allowlist:
+    - /usr/lib/libGL.so*
+    overlinking_behavior: error
+  prefix_detection:
+    force_file_type:
+      text:
+      - share/foo/*.pc
+    ignore_binary_files: true
+requirements:
+  host:
+  - zlib
+  run_exports:
+    weak:
+    - ${{ pin_subpackage('libfoo', upper_bound='x.x') }}
+  ignore_run_exports:
+    from_package:
+    - zlib
+"#;
+        let recipe: IntermediateRecipe = serde_yaml::from_str(yaml).unwrap();
+
+        assert_eq!(
+            recipe.build.string,
+            Some(Value::Template("${{ hash }}_nightly".to_string()))
+        );
+        assert_eq!(recipe.build.files.len(), 1);
+        let dynamic_linking = recipe.build.dynamic_linking.as_ref().unwrap();
+        assert_eq!(
+            dynamic_linking.overlinking_behavior,
+            Some(LinkingCheckBehavior::Error)
+        );
+        assert_eq!(dynamic_linking.binary_relocation, None);
+        let prefix_detection = recipe.build.prefix_detection.as_ref().unwrap();
+        assert_eq!(prefix_detection.ignore_binary_files, Some(true));
+
+        let run_exports = recipe.requirements.run_exports.as_ref().unwrap();
+        assert!(matches!(
+            &run_exports.weak[0],
+            Item::Value(Value::Template(_))
+        ));
+        let ignore_run_exports = recipe.requirements.ignore_run_exports.as_ref().unwrap();
+        assert_eq!(ignore_run_exports.from_package.len(), 1);
+
+        // Unset settings are not written to the recipe
+        let serialized = recipe.to_yaml().unwrap();
+        assert!(!serialized.contains("null"));
+        assert!(!serialized.contains("always_include_files"));
+        let roundtrip: IntermediateRecipe = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(
+            roundtrip.build.dynamic_linking,
+            recipe.build.dynamic_linking
+        );
+    }
 }
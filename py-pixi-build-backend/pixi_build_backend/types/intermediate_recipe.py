@@ -17,6 +17,12 @@ from pixi_build_backend.pixi_build_backend import (
     PyPathSource,
     PyPackageSpecDependencies,
     PyItemString,
+    PyTest,
+    PyVecTest,
+    PyDynamicLinking,
+    PyPrefixDetection,
+    PyRunExports,
+    PyIgnoreRunExports,
 )
 from pixi_build_backend.types.item import VecItemPackageDependency, ItemPackageDependency
 from pixi_build_backend.types.platform import Platform
@@ -64,6 +70,17 @@ class IntermediateRecipe:
         """Set the requirements configuration."""
         self._inner.requirements = value._inner
 
+    @property
+    def tests(self) -> List["Test"]:
+        """Get the tests of the package."""
+        tests = self._inner.tests
+        return [Test._from_inner(tests[i]) for i in range(len(tests))]
+
+    @tests.setter
+    def tests(self, value: List["Test"]) -> None:
+        """Set the tests of the package."""
+        self._inner.tests = PyVecTest([test._inner for test in value])
+
     @property
     def about(self) -> Optional["About"]:
         """Get the about information."""
@@ -202,6 +219,16 @@ class Build:
         """Set the build number."""
         self._inner.number = value._inner if value else None
 
+    @property
+    def string(self) -> Optional[str]:
+        """Get the build string."""
+        return self._inner.string
+
+    @string.setter
+    def string(self, value: Optional[str]) -> None:
+        """Set the build string, which may contain templates like `${{ hash }}`."""
+        self._inner.string = value
+
     @property
     def script(self) -> "Script":
         """Get the build script."""
@@ -233,6 +260,48 @@ class Build:
         """Set the Python configuration."""
         self._inner.python = value._inner
 
+    @property
+    def files(self) -> List[str]:
+        """Get the glob patterns of the files that are included in the package."""
+        return self._inner.files
+
+    @files.setter
+    def files(self, value: List[str]) -> None:
+        """Set the glob patterns of the files that are included in the package."""
+        self._inner.files = value
+
+    @property
+    def always_include_files(self) -> List[str]:
+        """Get the glob patterns of the files that are always included in the package."""
+        return self._inner.always_include_files
+
+    @always_include_files.setter
+    def always_include_files(self, value: List[str]) -> None:
+        """Set the glob patterns of the files that are always included in the package."""
+        self._inner.always_include_files = value
+
+    @property
+    def dynamic_linking(self) -> Optional["DynamicLinking"]:
+        """Get the dynamic linking settings."""
+        inner = self._inner.dynamic_linking
+        return DynamicLinking._from_inner(inner) if inner else None
+
+    @dynamic_linking.setter
+    def dynamic_linking(self, value: Optional["DynamicLinking"]) -> None:
+        """Set the dynamic linking settings."""
+        self._inner.dynamic_linking = value._inner if value else None
+
+    @property
+    def prefix_detection(self) -> Optional["PrefixDetection"]:
+        """Get the prefix detection settings."""
+        inner = self._inner.prefix_detection
+        return PrefixDetection._from_inner(inner) if inner else None
+
+    @prefix_detection.setter
+    def prefix_detection(self, value: Optional["PrefixDetection"]) -> None:
+        """Set the prefix detection settings."""
+        self._inner.prefix_detection = value._inner if value else None
+
     @classmethod
     def _from_inner(cls, inner: PyBuild) -> "Build":
         """Create a Build from a PyBuild."""
@@ -252,6 +321,126 @@ class Build:
         return str(self._inner)
 
 
+class DynamicLinking:
+    """
+    Settings for the relocation and linking checks of shared libraries.
+
+    Examples
+    --------
+    ```python
+    >>> linking = DynamicLinking(missing_dso_allowlist=["/usr/lib/libGL.so*"], overlinking_behavior="error")
+    >>> linking.overlinking_behavior
+    'error'
+    >>>
+    ```
+    """
+
+    _inner: PyDynamicLinking
+
+    def __init__(
+        self,
+        rpaths: Optional[List[str]] = None,
+        binary_relocation: Optional[bool] = None,
+        missing_dso_allowlist: Optional[List[str]] = None,
+        rpath_allowlist: Optional[List[str]] = None,
+        overdepending_behavior: Optional[str] = None,
+        overlinking_behavior: Optional[str] = None,
+    ) -> None:
+        self._inner = PyDynamicLinking(
+            rpaths,
+            binary_relocation,
+            missing_dso_allowlist,
+            rpath_allowlist,
+            overdepending_behavior,
+            overlinking_behavior,
+        )
+
+    @property
+    def rpaths(self) -> List[str]:
+        """Get the rpaths that are set on shared libraries."""
+        return self._inner.rpaths
+
+    @property
+    def binary_relocation(self) -> Optional[bool]:
+        """Get whether binaries are relocated."""
+        return self._inner.binary_relocation
+
+    @property
+    def missing_dso_allowlist(self) -> List[str]:
+        """Get the shared libraries that may be linked without being provided by a dependency."""
+        return self._inner.missing_dso_allowlist
+
+    @property
+    def rpath_allowlist(self) -> List[str]:
+        """Get the rpaths that may point outside of the prefix."""
+        return self._inner.rpath_allowlist
+
+    @property
+    def overdepending_behavior(self) -> Optional[str]:
+        """Get the behavior of the overdepending check, `ignore` or `error`."""
+        return self._inner.overdepending_behavior
+
+    @property
+    def overlinking_behavior(self) -> Optional[str]:
+        """Get the behavior of the overlinking check, `ignore` or `error`."""
+        return self._inner.overlinking_behavior
+
+    @classmethod
+    def _from_inner(cls, inner: PyDynamicLinking) -> "DynamicLinking":
+        """Create a DynamicLinking from a PyDynamicLinking."""
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
+    def __str__(self) -> str:
+        return str(self._inner)
+
+
+class PrefixDetection:
+    """Settings for the detection and replacement of the build prefix in the files of the package."""
+
+    _inner: PyPrefixDetection
+
+    def __init__(
+        self,
+        force_text: Optional[List[str]] = None,
+        force_binary: Optional[List[str]] = None,
+        ignore: Optional[bool] = None,
+        ignore_binary_files: Optional[bool] = None,
+    ) -> None:
+        self._inner = PyPrefixDetection(force_text, force_binary, ignore, ignore_binary_files)
+
+    @property
+    def force_text(self) -> List[str]:
+        """Get the files that are always treated as text files."""
+        return self._inner.force_text
+
+    @property
+    def force_binary(self) -> List[str]:
+        """Get the files that are always treated as binary files."""
+        return self._inner.force_binary
+
+    @property
+    def ignore(self) -> Optional[bool]:
+        """Get whether prefix detection is disabled."""
+        return self._inner.ignore
+
+    @property
+    def ignore_binary_files(self) -> Optional[bool]:
+        """Get whether prefix detection is disabled for binary files."""
+        return self._inner.ignore_binary_files
+
+    @classmethod
+    def _from_inner(cls, inner: PyPrefixDetection) -> "PrefixDetection":
+        """Create a PrefixDetection from a PyPrefixDetection."""
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
+    def __str__(self) -> str:
+        return str(self._inner)
+
+
 class Script:
     """A script wrapper."""
 
@@ -712,6 +901,28 @@ class ConditionalRequirements:
             vec.extend(value)
             self._inner.run_constraints = vec._inner
 
+    @property
+    def run_exports(self) -> Optional["RunExports"]:
+        """Get the run exports of the package."""
+        inner = self._inner.run_exports
+        return RunExports._from_inner(inner) if inner else None
+
+    @run_exports.setter
+    def run_exports(self, value: Optional["RunExports"]) -> None:
+        """Set the run exports of the package."""
+        self._inner.run_exports = value._inner if value else None
+
+    @property
+    def ignore_run_exports(self) -> Optional["IgnoreRunExports"]:
+        """Get the run exports of dependencies that are ignored."""
+        inner = self._inner.ignore_run_exports
+        return IgnoreRunExports._from_inner(inner) if inner else None
+
+    @ignore_run_exports.setter
+    def ignore_run_exports(self, value: Optional["IgnoreRunExports"]) -> None:
+        """Set the run exports of dependencies that are ignored."""
+        self._inner.ignore_run_exports = value._inner if value else None
+
     def resolve(self, host_platform: Optional[Platform] = None) -> "PackageSpecDependencies":
         """Resolve the requirements."""
         py_platform = host_platform._inner if host_platform else None
@@ -736,6 +947,192 @@ class ConditionalRequirements:
         return str(self._inner)
 
 
+class RunExports:
+    """
+    The requirements that are added to packages that depend on this package.
+
+    Examples
+    --------
+    ```python
+    >>> run_exports = RunExports(weak=["${{ pin_subpackage('libfoo', upper_bound='x.x') }}"])
+    >>> run_exports.weak
+    ["${{ pin_subpackage('libfoo', upper_bound='x.x') }}"]
+    >>>
+    ```
+    """
+
+    _inner: PyRunExports
+
+    def __init__(
+        self,
+        weak: Optional[List[str]] = None,
+        strong: Optional[List[str]] = None,
+        noarch: Optional[List[str]] = None,
+        weak_constraints: Optional[List[str]] = None,
+        strong_constraints: Optional[List[str]] = None,
+    ) -> None:
+        self._inner = PyRunExports(weak, strong, noarch, weak_constraints, strong_constraints)
+
+    @property
+    def weak(self) -> List[str]:
+        """Get the weak run exports."""
+        return self._inner.weak
+
+    @property
+    def strong(self) -> List[str]:
+        """Get the strong run exports."""
+        return self._inner.strong
+
+    @property
+    def noarch(self) -> List[str]:
+        """Get the noarch run exports."""
+        return self._inner.noarch
+
+    @property
+    def weak_constraints(self) -> List[str]:
+        """Get the weak run constraints."""
+        return self._inner.weak_constraints
+
+    @property
+    def strong_constraints(self) -> List[str]:
+        """Get the strong run constraints."""
+        return self._inner.strong_constraints
+
+    @classmethod
+    def _from_inner(cls, inner: PyRunExports) -> "RunExports":
+        """Create a RunExports from a PyRunExports."""
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
+    def __str__(self) -> str:
+        return str(self._inner)
+
+
+class IgnoreRunExports:
+    """The run exports of dependencies that are not applied to the package."""
+
+    _inner: PyIgnoreRunExports
+
+    def __init__(self, by_name: Optional[List[str]] = None, from_package: Optional[List[str]] = None) -> None:
+        self._inner = PyIgnoreRunExports(by_name, from_package)
+
+    @property
+    def by_name(self) -> List[str]:
+        """Get the names of the run exported packages that are ignored."""
+        return self._inner.by_name
+
+    @property
+    def from_package(self) -> List[str]:
+        """Get the names of the dependencies whose run exports are ignored."""
+        return self._inner.from_package
+
+    @classmethod
+    def _from_inner(cls, inner: PyIgnoreRunExports) -> "IgnoreRunExports":
+        """Create an IgnoreRunExports from a PyIgnoreRunExports."""
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
+    def __str__(self) -> str:
+        return str(self._inner)
+
+
+class Test:
+    """
+    A test of the package.
+
+    Use one of the static methods to create a test of a specific kind.
+    """
+
+    _inner: PyTest
+
+    @staticmethod
+    def script(
+        script: "Script",
+        run_requirements: Optional[List[str]] = None,
+        build_requirements: Optional[List[str]] = None,
+        source_files: Optional[List[str]] = None,
+        recipe_files: Optional[List[str]] = None,
+    ) -> "Test":
+        """
+        Create a test that runs a script in an environment that contains the package.
+
+        Examples
+        --------
+        ```python
+        >>> test = Test.script(Script("pytest tests"), run_requirements=["pytest"], source_files=["tests/"])
+        >>> test.script_content
+        ['pytest tests']
+        >>>
+        ```
+        """
+        return Test._from_inner(
+            PyTest.script_test(script._inner, run_requirements, build_requirements, source_files, recipe_files)
+        )
+
+    @staticmethod
+    def python(imports: List[str], pip_check: Optional[bool] = None) -> "Test":
+        """
+        Create a test that imports Python modules and optionally runs `pip check`.
+
+        Examples
+        --------
+        ```python
+        >>> test = Test.python(["boltons"])
+        >>> test.python_imports
+        ['boltons']
+        >>>
+        ```
+        """
+        return Test._from_inner(PyTest.python_test(imports, pip_check))
+
+    @staticmethod
+    def package_contents(
+        include: Optional[List[str]] = None,
+        files: Optional[List[str]] = None,
+        site_packages: Optional[List[str]] = None,
+    ) -> "Test":
+        """Create a test that checks the contents of the package."""
+        return Test._from_inner(PyTest.package_contents_test(include, files, site_packages))
+
+    @staticmethod
+    def downstream(package: str) -> "Test":
+        """Create a test that runs the tests of a downstream package."""
+        return Test._from_inner(PyTest.downstream_test(package))
+
+    @property
+    def script_content(self) -> Optional[List[str]]:
+        """Get the content of the script of a script test."""
+        script = self._inner.script
+        return Script._from_inner(script).content if script else None
+
+    @property
+    def python_imports(self) -> Optional[List[str]]:
+        """Get the imported modules of a Python test."""
+        return self._inner.python_imports
+
+    @property
+    def pip_check(self) -> Optional[bool]:
+        """Get whether a Python test runs `pip check`."""
+        return self._inner.pip_check
+
+    @property
+    def downstream_package(self) -> Optional[str]:
+        """Get the package of a downstream test."""
+        return self._inner.downstream
+
+    @classmethod
+    def _from_inner(cls, inner: PyTest) -> "Test":
+        """Create a Test from a PyTest."""
+        instance = cls.__new__(cls)
+        instance._inner = inner
+        return instance
+
+    def __str__(self) -> str:
+        return str(self._inner)
+
+
 class About:
     """An about information wrapper."""
 
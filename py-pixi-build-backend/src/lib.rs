@@ -88,14 +88,20 @@ fn pixi_build_backend(py: Python<'_>, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<recipe_stage0::recipe::PyUrlSource>()?;
     m.add_class::<recipe_stage0::recipe::PyPathSource>()?;
     m.add_class::<recipe_stage0::recipe::PyBuild>()?;
+    m.add_class::<recipe_stage0::recipe::PyDynamicLinking>()?;
+    m.add_class::<recipe_stage0::recipe::PyPrefixDetection>()?;
     m.add_class::<recipe_stage0::recipe::PyScript>()?;
     m.add_class::<recipe_stage0::recipe::PyPython>()?;
     m.add_class::<recipe_stage0::recipe::PyNoArchKind>()?;
     m.add_class::<recipe_stage0::recipe::PyValueString>()?;
     m.add_class::<recipe_stage0::recipe::PyValueU64>()?;
     m.add_class::<recipe_stage0::recipe::PyConditionalRequirements>()?;
+    m.add_class::<recipe_stage0::recipe::PyRunExports>()?;
+    m.add_class::<recipe_stage0::recipe::PyIgnoreRunExports>()?;
     m.add_class::<recipe_stage0::recipe::PyAbout>()?;
     m.add_class::<recipe_stage0::recipe::PyExtra>()?;
+    m.add_class::<recipe_stage0::recipe::PyTest>()?;
+    m.add_class::<recipe_stage0::recipe::PyVecTest>()?;
 
     // Add requirements types
     m.add_class::<recipe_stage0::requirements::PyPackageSpecDependencies>()?;
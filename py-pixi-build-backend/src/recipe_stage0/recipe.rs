@@ -17,9 +17,14 @@ use pyo3::{
     types::{PyList, PyListMethods},
 };
 use rattler_conda_types::package::EntryPoint;
-use recipe_stage0::recipe::{
-    About, Build, ConditionalRequirements, Extra, IntermediateRecipe, Item, NoArchKind, Package,
-    PathSource, Python as RecipePython, Script, Source, Test, UrlSource, Value,
+use recipe_stage0::{
+    matchspec::PackageDependency,
+    recipe::{
+        About, Build, ConditionalRequirements, DynamicLinking, Extra, ForceFileType,
+        IgnoreRunExports, IntermediateRecipe, Item, LinkingCheckBehavior, NoArchKind, Package,
+        PackageContents, PathSource, PrefixDetection, Python as RecipePython, PythonTest,
+        RunExports, Script, Source, Test, TestFiles, TestRequirements, UrlSource, Value,
+    },
 };
 
 use std::fmt::{Display, Formatter};
@@ -58,6 +63,32 @@ create_py_wrap!(
     }
 );
 
+#[pymethods]
+impl PyVecTest {
+    #[new]
+    #[pyo3(signature = (tests=None))]
+    pub fn new(tests: Option<Vec<PyTest>>) -> Self {
+        PyVecTest {
+            inner: tests.unwrap_or_default(),
+        }
+    }
+
+    pub fn __getitem__(&self, index: usize) -> PyResult<PyTest> {
+        self.inner
+            .get(index)
+            .cloned()
+            .ok_or_else(|| pyo3::exceptions::PyIndexError::new_err("index out of range"))
+    }
+
+    pub fn __len__(&self) -> usize {
+        self.inner.len()
+    }
+
+    pub fn append(&mut self, test: PyTest) {
+        self.inner.push(test);
+    }
+}
+
 create_py_wrap!(
     PyOptionAbout,
     Option<PyAbout>,
@@ -277,6 +308,7 @@ impl PyIntermediateRecipe {
             tests,
             about,
             extra,
+            ..Default::default()
         }
     }
 }
@@ -478,9 +510,14 @@ create_py_wrap!(
 #[derive(Clone, Serialize, Deserialize)]
 pub struct PyBuild {
     pub number: Py<PyOptionValueU64>,
+    pub string: Option<String>,
     pub script: Py<PyScript>,
     pub noarch: Py<PyOptionPyNoArchKind>,
     pub python: Py<PyPython>,
+    pub files: Vec<String>,
+    pub always_include_files: Vec<String>,
+    pub dynamic_linking: Option<PyDynamicLinking>,
+    pub prefix_detection: Option<PyPrefixDetection>,
 }
 
 impl PyBuild {
@@ -499,9 +536,16 @@ impl PyBuild {
                 .clone()
                 .as_ref()
                 .map(|n| n.deref().clone()),
+            string: self
+                .string
+                .map(|string| string.parse().expect("Failed to parse build string")),
             script: self.script.borrow(py).clone().into_script(py),
             noarch,
             python: self.python.borrow(py).inner.clone(),
+            files: to_conditional_list(self.files),
+            always_include_files: to_conditional_list(self.always_include_files),
+            dynamic_linking: self.dynamic_linking.map(|linking| linking.inner),
+            prefix_detection: self.prefix_detection.map(|detection| detection.inner),
         }
     }
 
@@ -515,9 +559,18 @@ impl PyBuild {
 
         PyBuild {
             number: Py::new(py, py_value).unwrap(),
+            string: build.string.map(|string| string.to_string()),
             script: Py::new(py, PyScript::from_script(py, build.script)).unwrap(),
             noarch: Py::new(py, py_noarch_value).unwrap(),
             python: Py::new(py, Into::<PyPython>::into(build.python)).unwrap(),
+            files: from_conditional_list(&build.files),
+            always_include_files: from_conditional_list(&build.always_include_files),
+            dynamic_linking: build
+                .dynamic_linking
+                .map(|inner| PyDynamicLinking { inner }),
+            prefix_detection: build
+                .prefix_detection
+                .map(|inner| PyPrefixDetection { inner }),
         }
     }
 }
@@ -528,9 +581,14 @@ impl PyBuild {
     pub fn new(py: Python) -> Self {
         PyBuild {
             number: Py::new(py, PyOptionValueU64::default()).unwrap(),
+            string: None,
             script: Py::new(py, PyScript::new(py, None, None, None)).unwrap(),
             noarch: Py::new(py, PyOptionPyNoArchKind::default()).unwrap(),
             python: Py::new(py, PyPython::new(None).unwrap()).unwrap(),
+            files: Vec::new(),
+            always_include_files: Vec::new(),
+            dynamic_linking: None,
+            prefix_detection: None,
         }
     }
 }
@@ -539,8 +597,189 @@ impl Display for PyBuild {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{{ number: {}, script: {}, noarch: {}, python: {} }}",
+            "{{ number: {}, script: {}, noarch: {}, python: {}",
             self.number, self.script, self.noarch, self.python
+        )?;
+        if !self.files.is_empty() {
+            write!(f, ", files: [{}]", self.files.join(", "))?;
+        }
+        if !self.always_include_files.is_empty() {
+            write!(
+                f,
+                ", always_include_files: [{}]",
+                self.always_include_files.join(", ")
+            )?;
+        }
+        if let Some(dynamic_linking) = &self.dynamic_linking {
+            write!(f, ", dynamic_linking: {dynamic_linking}")?;
+        }
+        if let Some(prefix_detection) = &self.prefix_detection {
+            write!(f, ", prefix_detection: {prefix_detection}")?;
+        }
+        write!(f, " }}")
+    }
+}
+
+/// Parses the name of a linking check behavior.
+fn to_linking_check_behavior(value: Option<String>) -> PyResult<Option<LinkingCheckBehavior>> {
+    value
+        .map(|value| match value.as_str() {
+            "ignore" => Ok(LinkingCheckBehavior::Ignore),
+            "error" => Ok(LinkingCheckBehavior::Error),
+            _ => Err(PyValueError::new_err(format!(
+                "Invalid linking check behavior '{value}', expected 'ignore' or 'error'"
+            ))),
+        })
+        .transpose()
+}
+
+#[pyclass(str)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PyDynamicLinking {
+    pub(crate) inner: DynamicLinking,
+}
+
+#[pymethods]
+impl PyDynamicLinking {
+    #[new]
+    #[pyo3(signature = (rpaths=None, binary_relocation=None, missing_dso_allowlist=None, rpath_allowlist=None, overdepending_behavior=None, overlinking_behavior=None))]
+    pub fn new(
+        rpaths: Option<Vec<String>>,
+        binary_relocation: Option<bool>,
+        missing_dso_allowlist: Option<Vec<String>>,
+        rpath_allowlist: Option<Vec<String>>,
+        overdepending_behavior: Option<String>,
+        overlinking_behavior: Option<String>,
+    ) -> PyResult<Self> {
+        Ok(PyDynamicLinking {
+            inner: DynamicLinking {
+                rpaths: rpaths.unwrap_or_default(),
+                binary_relocation,
+                missing_dso_allowlist: missing_dso_allowlist.unwrap_or_default(),
+                rpath_allowlist: rpath_allowlist.unwrap_or_default(),
+                overdepending_behavior: to_linking_check_behavior(overdepending_behavior)?,
+                overlinking_behavior: to_linking_check_behavior(overlinking_behavior)?,
+            },
+        })
+    }
+
+    #[getter]
+    pub fn rpaths(&self) -> Vec<String> {
+        self.inner.rpaths.clone()
+    }
+
+    #[getter]
+    pub fn binary_relocation(&self) -> Option<bool> {
+        self.inner.binary_relocation
+    }
+
+    #[getter]
+    pub fn missing_dso_allowlist(&self) -> Vec<String> {
+        self.inner.missing_dso_allowlist.clone()
+    }
+
+    #[getter]
+    pub fn rpath_allowlist(&self) -> Vec<String> {
+        self.inner.rpath_allowlist.clone()
+    }
+
+    #[getter]
+    pub fn overdepending_behavior(&self) -> Option<String> {
+        self.inner
+            .overdepending_behavior
+            .map(|behavior| behavior.to_string())
+    }
+
+    #[getter]
+    pub fn overlinking_behavior(&self) -> Option<String> {
+        self.inner
+            .overlinking_behavior
+            .map(|behavior| behavior.to_string())
+    }
+}
+
+impl Display for PyDynamicLinking {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ rpaths: [{}], binary_relocation: {:?}, missing_dso_allowlist: [{}], rpath_allowlist: [{}] }}",
+            self.inner.rpaths.join(", "),
+            self.inner.binary_relocation,
+            self.inner.missing_dso_allowlist.join(", "),
+            self.inner.rpath_allowlist.join(", "),
+        )
+    }
+}
+
+#[pyclass(str)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PyPrefixDetection {
+    pub(crate) inner: PrefixDetection,
+}
+
+#[pymethods]
+impl PyPrefixDetection {
+    #[new]
+    #[pyo3(signature = (force_text=None, force_binary=None, ignore=None, ignore_binary_files=None))]
+    pub fn new(
+        force_text: Option<Vec<String>>,
+        force_binary: Option<Vec<String>>,
+        ignore: Option<bool>,
+        ignore_binary_files: Option<bool>,
+    ) -> Self {
+        let force_file_type =
+            (force_text.is_some() || force_binary.is_some()).then(|| ForceFileType {
+                text: force_text.unwrap_or_default(),
+                binary: force_binary.unwrap_or_default(),
+            });
+
+        PyPrefixDetection {
+            inner: PrefixDetection {
+                force_file_type,
+                ignore,
+                ignore_binary_files,
+            },
+        }
+    }
+
+    #[getter]
+    pub fn force_text(&self) -> Vec<String> {
+        self.inner
+            .force_file_type
+            .as_ref()
+            .map(|force| force.text.clone())
+            .unwrap_or_default()
+    }
+
+    #[getter]
+    pub fn force_binary(&self) -> Vec<String> {
+        self.inner
+            .force_file_type
+            .as_ref()
+            .map(|force| force.binary.clone())
+            .unwrap_or_default()
+    }
+
+    #[getter]
+    pub fn ignore(&self) -> Option<bool> {
+        self.inner.ignore
+    }
+
+    #[getter]
+    pub fn ignore_binary_files(&self) -> Option<bool> {
+        self.inner.ignore_binary_files
+    }
+}
+
+impl Display for PyPrefixDetection {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{{ force_text: [{}], force_binary: [{}], ignore: {:?}, ignore_binary_files: {:?} }}",
+            self.force_text().join(", "),
+            self.force_binary().join(", "),
+            self.inner.ignore,
+            self.inner.ignore_binary_files,
         )
     }
 }
@@ -815,6 +1054,8 @@ pub struct PyConditionalRequirements {
     pub(crate) run: Py<PyVecItemPackageDependency>,
     // #[serde(default)]
     pub(crate) run_constraints: Py<PyVecItemPackageDependency>,
+    pub(crate) run_exports: Option<PyRunExports>,
+    pub(crate) ignore_run_exports: Option<PyIgnoreRunExports>,
 }
 
 #[pymethods]
@@ -831,6 +1072,8 @@ impl PyConditionalRequirements {
             host: Py::new(py, host).unwrap(),
             run: Py::new(py, run).unwrap(),
             run_constraints: Py::new(py, run_constraints).unwrap(),
+            run_exports: None,
+            ignore_run_exports: None,
         }
     }
 
@@ -884,6 +1127,10 @@ impl PyConditionalRequirements {
                 .clone()
                 .into_iter()
                 .collect(),
+            run_exports: self.run_exports.map(|run_exports| run_exports.inner),
+            ignore_run_exports: self
+                .ignore_run_exports
+                .map(|ignore_run_exports| ignore_run_exports.inner),
         }
     }
 
@@ -901,6 +1148,10 @@ impl PyConditionalRequirements {
             host: Py::new(py, host).unwrap(),
             run: Py::new(py, run).unwrap(),
             run_constraints: Py::new(py, run_constraints).unwrap(),
+            run_exports: requirements.run_exports.map(|inner| PyRunExports { inner }),
+            ignore_run_exports: requirements
+                .ignore_run_exports
+                .map(|inner| PyIgnoreRunExports { inner }),
         }
     }
 }
@@ -911,11 +1162,274 @@ impl Display for PyConditionalRequirements {
         write!(f, "{{ host: {} }}", self.host)?;
         write!(f, "{{ run: {} }}", self.run)?;
         write!(f, "{{ run_constraints: {} }}", self.run_constraints)?;
+        if let Some(run_exports) = &self.run_exports {
+            write!(f, "{{ run_exports: {run_exports} }}")?;
+        }
+        if let Some(ignore_run_exports) = &self.ignore_run_exports {
+            write!(f, "{{ ignore_run_exports: {ignore_run_exports} }}")?;
+        }
         Ok(())
     }
 }
 
-create_py_wrap!(PyTest, Test);
+#[pyclass(str)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PyRunExports {
+    pub(crate) inner: RunExports,
+}
+
+#[pymethods]
+impl PyRunExports {
+    #[new]
+    #[pyo3(signature = (weak=None, strong=None, noarch=None, weak_constraints=None, strong_constraints=None))]
+    pub fn new(
+        weak: Option<Vec<String>>,
+        strong: Option<Vec<String>>,
+        noarch: Option<Vec<String>>,
+        weak_constraints: Option<Vec<String>>,
+        strong_constraints: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        Ok(PyRunExports {
+            inner: RunExports {
+                weak: to_dependencies(weak.unwrap_or_default())?,
+                strong: to_dependencies(strong.unwrap_or_default())?,
+                noarch: to_dependencies(noarch.unwrap_or_default())?,
+                weak_constraints: to_dependencies(weak_constraints.unwrap_or_default())?,
+                strong_constraints: to_dependencies(strong_constraints.unwrap_or_default())?,
+            },
+        })
+    }
+
+    #[getter]
+    pub fn weak(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.weak)
+    }
+
+    #[getter]
+    pub fn strong(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.strong)
+    }
+
+    #[getter]
+    pub fn noarch(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.noarch)
+    }
+
+    #[getter]
+    pub fn weak_constraints(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.weak_constraints)
+    }
+
+    #[getter]
+    pub fn strong_constraints(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.strong_constraints)
+    }
+}
+
+impl Display for PyRunExports {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
+    }
+}
+
+#[pyclass(str)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PyIgnoreRunExports {
+    pub(crate) inner: IgnoreRunExports,
+}
+
+#[pymethods]
+impl PyIgnoreRunExports {
+    #[new]
+    #[pyo3(signature = (by_name=None, from_package=None))]
+    pub fn new(by_name: Option<Vec<String>>, from_package: Option<Vec<String>>) -> Self {
+        PyIgnoreRunExports {
+            inner: IgnoreRunExports {
+                by_name: to_conditional_list(by_name.unwrap_or_default()),
+                from_package: to_conditional_list(from_package.unwrap_or_default()),
+            },
+        }
+    }
+
+    #[getter]
+    pub fn by_name(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.by_name)
+    }
+
+    #[getter]
+    pub fn from_package(&self) -> Vec<String> {
+        from_conditional_list(&self.inner.from_package)
+    }
+}
+
+impl Display for PyIgnoreRunExports {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
+    }
+}
+
+#[pyclass(str)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct PyTest {
+    pub(crate) inner: Test,
+}
+
+/// Converts a list of strings into a conditional list.
+fn to_conditional_list(values: Vec<String>) -> Vec<Item<String>> {
+    values
+        .into_iter()
+        .map(|s| s.parse().expect("Failed to parse item"))
+        .collect()
+}
+
+/// Converts a conditional list back into a list of strings.
+fn from_conditional_list<T: Display>(values: &[Item<T>]) -> Vec<String> {
+    values.iter().map(|value| value.to_string()).collect()
+}
+
+/// Parses a list of package dependencies.
+fn to_dependencies(values: Vec<String>) -> PyResult<Vec<Item<PackageDependency>>> {
+    values
+        .into_iter()
+        .map(|s| {
+            s.parse()
+                .map_err(|e| PyValueError::new_err(format!("Failed to parse '{s}': {e}")))
+        })
+        .collect()
+}
+
+#[pymethods]
+impl PyTest {
+    /// Creates a test that runs a script in an environment that contains
+    /// the package.
+    #[staticmethod]
+    #[pyo3(signature = (script, run_requirements=None, build_requirements=None, source_files=None, recipe_files=None))]
+    pub fn script_test(
+        py: Python,
+        script: PyScript,
+        run_requirements: Option<Vec<String>>,
+        build_requirements: Option<Vec<String>>,
+        source_files: Option<Vec<String>>,
+        recipe_files: Option<Vec<String>>,
+    ) -> PyResult<Self> {
+        let requirements = TestRequirements {
+            build: to_dependencies(build_requirements.unwrap_or_default())?,
+            run: to_dependencies(run_requirements.unwrap_or_default())?,
+        };
+        let files = TestFiles {
+            source: to_conditional_list(source_files.unwrap_or_default()),
+            recipe: to_conditional_list(recipe_files.unwrap_or_default()),
+        };
+
+        Ok(PyTest {
+            inner: Test {
+                script: Some(script.into_script(py)),
+                requirements: (!requirements.build.is_empty() || !requirements.run.is_empty())
+                    .then_some(requirements),
+                files: (!files.source.is_empty() || !files.recipe.is_empty()).then_some(files),
+                ..Test::default()
+            },
+        })
+    }
+
+    /// Creates a test that imports Python modules and optionally runs
+    /// `pip check`.
+    #[staticmethod]
+    #[pyo3(signature = (imports, pip_check=None))]
+    pub fn python_test(imports: Vec<String>, pip_check: Option<bool>) -> Self {
+        PyTest {
+            inner: Test {
+                python: Some(PythonTest {
+                    imports: to_conditional_list(imports),
+                    pip_check,
+                }),
+                ..Test::default()
+            },
+        }
+    }
+
+    /// Creates a test that checks the contents of the package.
+    #[staticmethod]
+    #[pyo3(signature = (include=None, files=None, site_packages=None))]
+    pub fn package_contents_test(
+        include: Option<Vec<String>>,
+        files: Option<Vec<String>>,
+        site_packages: Option<Vec<String>>,
+    ) -> Self {
+        PyTest {
+            inner: Test {
+                package_contents: Some(PackageContents {
+                    include: include.map(to_conditional_list),
+                    files: files.map(to_conditional_list),
+                    site_packages: site_packages.map(to_conditional_list),
+                }),
+                ..Test::default()
+            },
+        }
+    }
+
+    /// Creates a test that runs the tests of a downstream package.
+    #[staticmethod]
+    pub fn downstream_test(package: String) -> Self {
+        PyTest {
+            inner: Test {
+                downstream: Some(package),
+                ..Test::default()
+            },
+        }
+    }
+
+    #[getter]
+    pub fn script(&self, py: Python) -> Option<PyScript> {
+        self.inner
+            .script
+            .clone()
+            .map(|script| PyScript::from_script(py, script))
+    }
+
+    #[getter]
+    pub fn python_imports(&self) -> Option<Vec<String>> {
+        self.inner.python.as_ref().map(|python| {
+            python
+                .imports
+                .iter()
+                .map(|import| import.to_string())
+                .collect()
+        })
+    }
+
+    #[getter]
+    pub fn pip_check(&self) -> Option<bool> {
+        self.inner
+            .python
+            .as_ref()
+            .and_then(|python| python.pip_check)
+    }
+
+    #[getter]
+    pub fn downstream(&self) -> Option<String> {
+        self.inner.downstream.clone()
+    }
+}
+
+impl Display for PyTest {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.inner)
+    }
+}
+
+impl From<Test> for PyTest {
+    fn from(test: Test) -> Self {
+        PyTest { inner: test }
+    }
+}
+
+impl Deref for PyTest {
+    type Target = Test;
+    fn deref(&self) -> &Self::Target {
+        &self.inner
+    }
+}
 
 #[pyclass(str)]
 #[derive(Clone, Default, Serialize, Deserialize)]
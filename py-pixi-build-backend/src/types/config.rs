@@ -1,6 +1,6 @@
 use std::path::{Path, PathBuf};
 
-use pixi_build_backend::generated_recipe::BackendConfig;
+use pixi_build_backend::{common::CommonOptions, generated_recipe::BackendConfig};
 use pyo3::{Py, PyAny, Python, pyclass, pymethods};
 use pythonize::pythonize;
 use serde::Deserialize;
@@ -11,6 +11,21 @@ use serde::Deserializer;
 pub struct PyBackendConfig {
     pub(crate) model: Py<PyAny>,
     pub(crate) debug_dir: Option<PathBuf>,
+    pub(crate) common: CommonOptions,
+}
+
+/// Reads the options shared by all backends from the configuration, accepting
+/// both `snake_case` and `kebab-case` keys.
+fn common_options(data: &serde_json::Value) -> serde_json::Result<CommonOptions> {
+    let Some(obj) = data.as_object() else {
+        return Ok(CommonOptions::default());
+    };
+
+    let normalized: serde_json::Map<String, serde_json::Value> = obj
+        .iter()
+        .map(|(key, value)| (key.replace('_', "-"), value.clone()))
+        .collect();
+    serde_json::from_value(serde_json::Value::Object(normalized))
 }
 
 impl<'de> Deserialize<'de> for PyBackendConfig {
@@ -32,9 +47,12 @@ impl<'de> Deserialize<'de> for PyBackendConfig {
                 .and_then(|obj| obj.get("debug-dir").or_else(|| obj.get("debug_dir")))
                 .and_then(|v| v.as_str().map(PathBuf::from));
 
+            let common = common_options(&data).map_err(serde::de::Error::custom)?;
+
             Ok(PyBackendConfig {
                 model: model.unbind(),
                 debug_dir,
+                common,
             })
         })
     }
@@ -43,13 +61,37 @@ impl<'de> Deserialize<'de> for PyBackendConfig {
 #[pymethods]
 impl PyBackendConfig {
     #[new]
-    fn new(debug_dir: Option<PathBuf>, model: Py<PyAny>) -> Self {
-        PyBackendConfig { debug_dir, model }
+    #[pyo3(signature = (debug_dir, model, recipe_overlay=None))]
+    fn new(debug_dir: Option<PathBuf>, model: Py<PyAny>, recipe_overlay: Option<PathBuf>) -> Self {
+        PyBackendConfig {
+            debug_dir,
+            model,
+            common: CommonOptions {
+                recipe_overlay,
+                ..CommonOptions::default()
+            },
+        }
     }
 
     fn debug_dir(&self) -> Option<&Path> {
         BackendConfig::debug_dir(self)
     }
+
+    fn recipe_overlay(&self) -> Option<&Path> {
+        BackendConfig::recipe_overlay(self)
+    }
+
+    fn build_number(&self) -> Option<u64> {
+        self.common.build_number
+    }
+
+    fn build_string(&self) -> Option<&str> {
+        self.common.build_string.as_deref()
+    }
+
+    fn reproducible(&self) -> Option<bool> {
+        BackendConfig::reproducible(self)
+    }
 }
 
 impl BackendConfig for PyBackendConfig {
@@ -57,11 +99,17 @@ impl BackendConfig for PyBackendConfig {
         self.debug_dir.as_deref()
     }
 
+    fn common_options(&self) -> Option<&CommonOptions> {
+        Some(&self.common)
+    }
+
     fn merge_with_target_config(&self, target_config: &Self) -> miette::Result<Self> {
         if target_config.debug_dir.is_some() {
             miette::bail!("`debug_dir` cannot have a target specific value");
         }
 
-        Ok(self.clone())
+        let mut merged = self.clone();
+        merged.common = self.common.merge(&target_config.common);
+        Ok(merged)
     }
 }
@@ -11,6 +11,7 @@ use miette::IntoDiagnostic;
 use pixi_build_backend::generated_recipe::{
     DefaultMetadataProvider, GenerateRecipe, GeneratedRecipe, PythonParams,
 };
+use pixi_build_backend::input_globs::input_globs_respecting_ignores;
 use pixi_build_backend::{NormalizedKey, Variable};
 use pixi_build_types::ProjectModelV1;
 use pyo3::{
@@ -256,6 +257,7 @@ impl GenerateRecipe for PyGenerateRecipe {
     fn extract_input_globs_from_build(
         &self,
         config: &Self::Config,
+        source_dir: impl AsRef<Path>,
         workdir: impl AsRef<Path>,
         editable: bool,
     ) -> miette::Result<BTreeSet<String>> {
@@ -275,10 +277,13 @@ impl GenerateRecipe for PyGenerateRecipe {
                 )
                 .into_diagnostic()?
                 .extract::<Vec<String>>()
-                .into_diagnostic()?
-                .into_iter()
-                .collect::<BTreeSet<String>>();
-            Ok::<_, miette::Report>(input_globs)
+                .into_diagnostic()?;
+            Ok::<_, miette::Report>(input_globs_respecting_ignores(
+                source_dir.as_ref(),
+                input_globs,
+                [],
+                &[],
+            ))
         })
     }
 
@@ -1,6 +1,14 @@
 from pathlib import Path
 from typing import Any
-from pixi_build_backend.types.intermediate_recipe import IntermediateRecipe, Python
+from pixi_build_backend.types.intermediate_recipe import (
+    DynamicLinking,
+    IgnoreRunExports,
+    IntermediateRecipe,
+    Python,
+    RunExports,
+    Script,
+    Test,
+)
 from pixi_build_backend.types.item import ItemPackageDependency
 
 
@@ -54,3 +62,47 @@ def test_package_types_conditional() -> None:
 
     assert package.concrete is None
     assert package.template
+
+
+def test_tests_are_serialized() -> None:
+    recipe = IntermediateRecipe()
+    recipe.tests = [
+        Test.python(["boltons"], pip_check=False),
+        Test.script(Script("pytest tests"), run_requirements=["pytest"], source_files=["tests/"]),
+        Test.downstream("boltons-plugin"),
+    ]
+
+    assert len(recipe.tests) == 3
+    assert recipe.tests[0].python_imports == ["boltons"]
+    assert recipe.tests[0].pip_check is False
+    assert recipe.tests[1].script_content == ["pytest tests"]
+    assert recipe.tests[2].downstream_package == "boltons-plugin"
+
+    roundtrip = IntermediateRecipe.from_yaml(recipe.to_yaml())
+    assert roundtrip.tests[0].python_imports == ["boltons"]
+    assert roundtrip.tests[2].downstream_package == "boltons-plugin"
+
+
+def test_library_build_settings_are_serialized() -> None:
+    recipe = IntermediateRecipe()
+    build = recipe.build
+    build.files = ["lib/libfoo*"]
+    build.dynamic_linking = DynamicLinking(missing_dso_allowlist=["/usr/lib/libGL.so*"], overlinking_behavior="error")
+    recipe.build = build
+
+    requirements = recipe.requirements
+    requirements.run_exports = RunExports(weak=["${{ pin_subpackage('libfoo', upper_bound='x.x') }}"])
+    requirements.ignore_run_exports = IgnoreRunExports(from_package=["zlib"])
+    recipe.requirements = requirements
+
+    roundtrip = IntermediateRecipe.from_yaml(recipe.to_yaml())
+    assert roundtrip.build.files == ["lib/libfoo*"]
+    dynamic_linking = roundtrip.build.dynamic_linking
+    assert dynamic_linking is not None
+    assert dynamic_linking.overlinking_behavior == "error"
+    run_exports = roundtrip.requirements.run_exports
+    assert run_exports is not None
+    assert run_exports.weak == ["${{ pin_subpackage('libfoo', upper_bound='x.x') }}"]
+    ignore_run_exports = roundtrip.requirements.ignore_run_exports
+    assert ignore_run_exports is not None
+    assert ignore_run_exports.from_package == ["zlib"]